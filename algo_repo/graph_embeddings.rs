@@ -6,13 +6,14 @@ use rand::seq::SliceRandom;
 use rand::Rng;
 use std::collections::HashMap;
 use petgraph::graph::NodeIndex;
-use rand::rngs::ThreadRng;
 use rand::distributions::WeightedIndex;
 use std::thread::current;
 use rand::distributions::Distribution;
 
 
-pub fn node2vec(
+// Every random draw, from the initial embeddings to the steps of the walks, comes from `rng`, so a seeded
+// generator such as `StdRng::seed_from_u64(seed)` gives the same embeddings on every run
+pub fn node2vec<R: Rng + ?Sized>(
     graph: &Graph<(f64, f64), f64, Undirected>,
     dimensions: usize,
     walk_length: usize,
@@ -21,12 +22,12 @@ pub fn node2vec(
     q: f64,
     learning_rate: f64,
     epochs: usize,
+    rng: &mut R,
 ) -> HashMap<usize, Vec<f64>> {
     let mut embeddings: HashMap<usize, Vec<f64>> = HashMap::new();
 
     // Initialize random embeddings for each node
     for node in graph.node_indices() {
-        let mut rng = rand::thread_rng();
         let embedding: Vec<f64> = (0..dimensions)
             .map(|_| rng.gen_range(-1.0..1.0))
             .collect();
@@ -34,7 +35,7 @@ pub fn node2vec(
     }
 
     // Generate random walks
-    let walks = random_walks(graph, num_walks, walk_length, p, q);
+    let walks = random_walks(graph, num_walks, walk_length, p, q, rng);
 
     for _ in 0..epochs {
         for walk in &walks {
@@ -74,13 +75,13 @@ pub fn node2vec(
 
 
 
-pub fn random_walk(
+pub fn random_walk<R: Rng + ?Sized>(
     graph: &Graph<(f64, f64), f64, Undirected>,
     start_node: NodeIndex,
     walk_length: usize,
     p: f64,
     q: f64,
-    rng: &mut ThreadRng,
+    rng: &mut R,
 ) -> Vec<NodeIndex> {
     let mut walk = Vec::with_capacity(walk_length);
     walk.push(start_node);
@@ -111,19 +112,19 @@ pub fn random_walk(
     walk
 }
 
-pub fn random_walks(
+pub fn random_walks<R: Rng + ?Sized>(
     graph: &Graph<(f64, f64), f64, Undirected>,
     walk_length: usize,
     num_walks: usize,
     p: f64,
     q: f64,
+    rng: &mut R,
 ) -> Vec<Vec<NodeIndex>> {
     let mut walks = Vec::new();
-    let mut rng = rand::thread_rng();
 
     for _ in 0..num_walks {
         for node in graph.node_indices() {
-            let walk = random_walk(graph, node, walk_length, p, q, &mut rng);
+            let walk = random_walk(graph, node, walk_length, p, q, rng);
             walks.push(walk);
         }
     }
@@ -1,12 +1,18 @@
 // assort.rs
 
-use petgraph::visit::{EdgeRef, IntoNeighbors, NodeIndexable};
-use petgraph::Graph;
-use std::collections::HashMap;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNeighbors, IntoNodeIdentifiers, NodeIndexable};
+
+// Degree assortativity over the edges of any petgraph graph reference; node and edge payloads are ignored
+pub fn calculate_assortativity_coefficient<G>(graph: G) -> f64
+where
+    G: IntoEdgeReferences + IntoNeighbors + IntoNodeIdentifiers + NodeIndexable,
+{
+    // Count the degree of every node once, rather than twice per edge
+    let mut degrees = vec![0usize; graph.node_bound()];
+    for node in graph.node_identifiers() {
+        degrees[graph.to_index(node)] = graph.neighbors(node).count();
+    }
 
-pub fn calculate_assortativity_coefficient<N, E, Ty: petgraph::EdgeType>(
-    graph: &Graph<N, E, Ty>,
-) -> f64 {
     let mut sum1 = 0.0; // Sum of the product of degrees of connected nodes
     let mut sum2 = 0.0; // Sum of the degrees of all nodes
     let mut sum3 = 0.0; // Sum of the square of degrees of all nodes
@@ -14,8 +20,8 @@ pub fn calculate_assortativity_coefficient<N, E, Ty: petgraph::EdgeType>(
 
     // Iterate over all edges in the graph
     for edge in graph.edge_references() {
-        let source_degree = graph.neighbors(edge.source()).count(); // Degree of the source node
-        let target_degree = graph.neighbors(edge.target()).count(); // Degree of the target node
+        let source_degree = degrees[graph.to_index(edge.source())]; // Degree of the source node
+        let target_degree = degrees[graph.to_index(edge.target())]; // Degree of the target node
 
         sum1 += (source_degree * target_degree) as f64;
         sum2 += (source_degree + target_degree) as f64;
@@ -41,7 +47,7 @@ pub fn calculate_assortativity_coefficient<N, E, Ty: petgraph::EdgeType>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use petgraph::Undirected;
+    use petgraph::{Graph, Undirected};
 
     fn create_linear_graph() -> Graph<(), (), Undirected> {
         let mut graph = Graph::new_undirected();
@@ -100,6 +106,13 @@ mod tests {
         assert!((result - (-0.16666666666666666)).abs() < 1e-10);
     }
 
+    #[test]
+    fn test_calculate_assortativity_coefficient_graph_map() {
+        let graph = petgraph::graphmap::UnGraphMap::<u32, ()>::from_edges([(1, 2), (2, 3), (3, 4)]);
+        let result = calculate_assortativity_coefficient(&graph);
+        assert!((result - (-0.034482758620689655)).abs() < 1e-10);
+    }
+
 }
 
 
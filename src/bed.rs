@@ -1,57 +1,82 @@
 // bed.rs
 
-use petgraph::graph::Graph;
-use petgraph::Undirected;
-use petgraph::visit::{Dfs, GraphRef, Visitable};
-use petgraph::graph::NodeIndex;
+use petgraph::visit::{Dfs, IntoNeighbors, IntoNodeIdentifiers, NodeIndexable, Visitable};
 use std::collections::HashSet;
+use std::hash::Hash;
+use crate::progress::{NoProgress, Progress};
+
+// The find_bridge_edges function takes any undirected petgraph graph reference, such as `&RoadGraph` or
+// `&RoadNetwork`. It returns a HashSet containing pairs of node identifiers representing the bridge edges
+// in the graph. A bridge edge is an edge whose removal increases the number of connected components in the graph.
+pub fn find_bridge_edges<G>(graph: G) -> HashSet<(G::NodeId, G::NodeId)>
+where
+    G: IntoNeighbors + IntoNodeIdentifiers + NodeIndexable + Visitable,
+    G::NodeId: Hash + Eq,
+{
+    find_bridge_edges_with_progress(graph, &NoProgress)
+}
 
-// The find_bridge_edges function takes a reference to an undirected graph with nodes representing
-// points in 2D space and edges with associated weights. It returns a HashSet containing pairs of
-// NodeIndex values representing the bridge edges in the graph. A bridge edge is an edge whose removal
-// increases the number of connected components in the graph.
-pub fn find_bridge_edges(graph: &Graph<(f64, f64), f64, Undirected>) -> HashSet<(NodeIndex, NodeIndex)> {
+// The bridge search as above, reporting every node the depth-first search discovers to `progress`
+pub fn find_bridge_edges_with_progress<G>(graph: G, progress: &dyn Progress) -> HashSet<(G::NodeId, G::NodeId)>
+where
+    G: IntoNeighbors + IntoNodeIdentifiers + NodeIndexable + Visitable,
+    G::NodeId: Hash + Eq,
+{
     let mut bridges = HashSet::new();
 
     // If the graph is empty, return an empty set of bridges.
-    if graph.node_count() == 0 {
+    let Some(first) = graph.node_identifiers().next() else {
         return bridges;
-    }
+    };
 
     // Initialize a depth-first search (DFS) traversal starting from the first node in the graph.
-    let mut dfs = Dfs::new(graph, NodeIndex::new(0));
-    // Initialize vectors to store low-link values, discovery times, and parent nodes for each node.
-    let mut low = vec![0; graph.node_count()];
-    let mut disc = vec![0; graph.node_count()];
-    let mut parent = vec![None; graph.node_count()];
-    // Initialize a time counter for discovery times.
-    let mut time = 0;
+    let mut dfs = Dfs::new(graph, first);
+    // Initialize the low-link values, discovery times, parent nodes and the time counter of the traversal.
+    let mut state = DfsState {
+        low: vec![0; graph.node_bound()],
+        disc: vec![0; graph.node_bound()],
+        parent: vec![None; graph.node_bound()],
+        time: 0,
+    };
 
     // Perform the DFS traversal and update low-link values, discovery times, and parent nodes.
+    // The search only reaches the component of the first node, whose size is not known in advance
+    progress.start("Bridges", None);
     while let Some(node) = dfs.next(graph) {
-        if disc[node.index()] == 0 {
-            bridge_dfs(graph, node, &mut low, &mut disc, &mut parent, &mut time, &mut bridges);
+        if state.disc[graph.to_index(node)] == 0 {
+            bridge_dfs(graph, node, &mut state, &mut bridges, progress);
         }
     }
+    progress.finish();
 
     // Return the set of bridge edges.
     bridges
 }
 
 
-fn bridge_dfs(
-    graph: &Graph<(f64, f64), f64, Undirected>, // The input graph
-    start: NodeIndex, // The starting node for the DFS traversal
-    low: &mut Vec<usize>, // Vector to store low-link values for each node
-    disc: &mut Vec<usize>, // Vector to store discovery times for each node
-    parent: &mut Vec<Option<NodeIndex>>, // Vector to store parent nodes for each node
-    time: &mut usize, // Counter for discovery times
-    bridges: &mut HashSet<(NodeIndex, NodeIndex)>, // Set to store identified bridge edges
-) {
+// The state of the depth-first traversal, with one entry per node indexed by `to_index`
+struct DfsState<N> {
+    low: Vec<usize>, // Low-link values
+    disc: Vec<usize>, // Discovery times, 0 for undiscovered nodes
+    parent: Vec<Option<N>>, // Parent nodes in the DFS tree
+    time: usize, // Counter for discovery times
+}
+
+
+fn bridge_dfs<G>(
+    graph: G, // The input graph
+    start: G::NodeId, // The starting node for the DFS traversal
+    search: &mut DfsState<G::NodeId>, // Low-link values, discovery times and parent nodes of the traversal
+    bridges: &mut HashSet<(G::NodeId, G::NodeId)>, // Set to store identified bridge edges
+    progress: &dyn Progress, // Receiver of the discovered nodes
+) where
+    G: IntoNeighbors + NodeIndexable,
+    G::NodeId: Hash + Eq,
+{
     // Define an enum to represent the state of the DFS traversal (entering or exiting a node).
-    enum State {
-        Enter(NodeIndex),
-        Exit(NodeIndex),
+    enum State<N> {
+        Enter(N),
+        Exit(N),
     }
 
     // Initialize a stack for the DFS traversal and push the starting node with the Enter state.
@@ -61,33 +86,38 @@ fn bridge_dfs(
     while let Some(state) = stack.pop() {
         match state {
             State::Enter(u) => {
+                // A node can be pushed by several neighbors before it is entered; only its first discovery is progress.
+                if search.disc[graph.to_index(u)] == 0 {
+                    progress.advance(1);
+                }
+
                 // Increment the time counter and update the discovery time and low-link value for the current node.
-                *time += 1;
-                disc[u.index()] = *time;
-                low[u.index()] = *time;
+                search.time += 1;
+                search.disc[graph.to_index(u)] = search.time;
+                search.low[graph.to_index(u)] = search.time;
 
                 // Push the current node with the Exit state onto the stack.
                 stack.push(State::Exit(u));
 
                 // Explore the neighbors of the current node.
                 for neighbor in graph.neighbors(u) {
-                    if disc[neighbor.index()] == 0 {
+                    if search.disc[graph.to_index(neighbor)] == 0 {
                         // If the neighbor is undiscovered, set its parent to the current node and push it with the Enter state.
-                        parent[neighbor.index()] = Some(u);
+                        search.parent[graph.to_index(neighbor)] = Some(u);
                         stack.push(State::Enter(neighbor));
-                    } else if parent[u.index()] != Some(neighbor) {
+                    } else if search.parent[graph.to_index(u)] != Some(neighbor) {
                         // If the neighbor is discovered and is not the parent of the current node, update the low-link value of the current node.
-                        low[u.index()] = usize::min(low[u.index()], disc[neighbor.index()]);
+                        search.low[graph.to_index(u)] = usize::min(search.low[graph.to_index(u)], search.disc[graph.to_index(neighbor)]);
                     }
                 }
             }
             State::Exit(u) => {
                 // When exiting a node, update the low-link value of its parent based on the low-link value of the current node.
-                if let Some(parent_u) = parent[u.index()] {
-                    low[parent_u.index()] = usize::min(low[parent_u.index()], low[u.index()]);
+                if let Some(parent_u) = search.parent[graph.to_index(u)] {
+                    search.low[graph.to_index(parent_u)] = usize::min(search.low[graph.to_index(parent_u)], search.low[graph.to_index(u)]);
                     // If the low-link value of the current node is greater than the discovery time of its parent,
                     // then the edge between the current node and its parent is a bridge edge.
-                    if low[u.index()] > disc[parent_u.index()] {
+                    if search.low[graph.to_index(u)] > search.disc[graph.to_index(parent_u)] {
                         bridges.insert((parent_u, u));
                     }
                 }
@@ -101,24 +131,23 @@ fn bridge_dfs(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use petgraph::Graph;
+    use crate::data::{RoadEdge, RoadGraph, RoadNetwork, RoadNode};
     use petgraph::graph::NodeIndex;
 
-    fn build_test_graph() -> Graph<(f64, f64), f64, Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let nodes = (0..6).map(|_| graph.add_node((0.0, 0.0))).collect::<Vec<_>>();
-
-        graph.add_edge(nodes[0], nodes[1], 1.0);
-        graph.add_edge(nodes[1], nodes[2], 1.0);
-        graph.add_edge(nodes[2], nodes[0], 1.0);
-        graph.add_edge(nodes[1], nodes[3], 1.0);
-        graph.add_edge(nodes[3], nodes[4], 1.0);
-        graph.add_edge(nodes[4], nodes[5], 1.0);
-        graph.add_edge(nodes[5], nodes[3], 1.0);
-
+    // Create a graph whose nodes have the ids 10, 11, ... joined by unit-weight edges given by node index
+    fn build_graph(node_count: usize, edges: &[(usize, usize)]) -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let nodes = (0..node_count).map(|i| graph.add_node(RoadNode::new(10 + i))).collect::<Vec<_>>();
+        for &(a, b) in edges {
+            graph.add_edge(nodes[a], nodes[b], RoadEdge::default());
+        }
         graph
     }
 
+    fn build_test_graph() -> RoadGraph {
+        build_graph(6, &[(0, 1), (1, 2), (2, 0), (1, 3), (3, 4), (4, 5), (5, 3)])
+    }
+
     #[test]
     fn test_find_bridge_edges() {
         let graph = build_test_graph();
@@ -132,23 +161,36 @@ mod tests {
 
     #[test]
     fn test_find_bridge_edges_empty_graph() {
-        let graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
+        let graph = RoadGraph::new_undirected();
         let bridges = find_bridge_edges(&graph);
         assert!(bridges.is_empty());
     }
 
     #[test]
     fn test_find_bridge_edges_no_bridge_edges() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let nodes = (0..3).map(|_| graph.add_node((0.0, 0.0))).collect::<Vec<_>>();
-
-        graph.add_edge(nodes[0], nodes[1], 1.0);
-        graph.add_edge(nodes[1], nodes[2], 1.0);
-        graph.add_edge(nodes[2], nodes[0], 1.0);
+        let graph = build_graph(3, &[(0, 1), (1, 2), (2, 0)]);
 
         let bridges = find_bridge_edges(&graph);
         assert!(bridges.is_empty());
     }
-}
 
+    #[test]
+    fn test_find_bridge_edges_graph_map() {
+        // On a GraphMap the bridges are reported in the original ids
+        let graph = RoadNetwork::from_edges([(10, 11), (11, 12), (12, 10), (11, 13), (13, 14), (14, 15), (15, 13)]);
+        let bridges = find_bridge_edges(&graph);
+        assert_eq!(bridges.len(), 1);
+        assert!(bridges.contains(&(11, 13)) || bridges.contains(&(13, 11)));
+    }
 
+    #[test]
+    fn test_find_bridge_edges_reports_discovered_nodes() {
+        let graph = build_test_graph();
+        let progress = crate::progress::tests::CountingProgress::default();
+        let bridges = find_bridge_edges_with_progress(&graph, &progress);
+
+        assert_eq!(bridges, find_bridge_edges(&graph));
+        assert_eq!(progress.tasks(), vec![("Bridges".to_string(), None)]);
+        assert_eq!(progress.steps(), 6);
+    }
+}
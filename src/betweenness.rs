@@ -0,0 +1,576 @@
+// betweenness.rs
+
+// Betweenness centrality after Brandes (2001): how many of the shortest paths between other nodes run through a
+// node or along an edge, which singles out the intersections and streets that carry through-traffic. Paths are
+// counted in hops or by edge length, the single-source searches run in parallel on the rayon worker threads, and
+// on networks too large for a search from every node a random sample of pivot sources (Brandes & Pich, 2007)
+// gives an estimate together with a bound on its error.
+
+use crate::progress::{NoProgress, Progress};
+use crate::shortest_path::select_landmarks;
+use petgraph::visit::{EdgeRef, GraphProp, IntoEdgeReferences, IntoNodeIdentifiers, NodeIndexable};
+use rand::Rng;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+// Betweenness of every node and edge, keyed by the node and edge identifiers of the graph: the original ids on
+// a GraphMap, where an edge is identified by its endpoints, and node and edge indices on a Graph.
+//
+// Unnormalized, a node scores the number of shortest paths between pairs of other nodes that run through it,
+// every pair counting as one path split evenly among its shortest paths, and an edge the same over all pairs.
+// Normalized, the node scores are divided by the number of pairs of other nodes, `(n - 1)(n - 2)`, and the edge
+// scores by the number of pairs, `n (n - 1)`; on an undirected graph both are counted as ordered pairs, so the
+// normalized scores of directed and undirected graphs lie between 0 and 1 alike. Estimates from sampled pivots
+// are extrapolated to all sources.
+#[derive(Debug, Clone)]
+pub struct Betweenness<N, E> {
+    pub nodes: HashMap<N, f64>,
+    pub edges: HashMap<E, f64>,
+    pub normalized: bool,
+    pub directed: bool,
+    pub node_count: usize,
+    pub edge_count: usize,
+    // Number of sources whose shortest paths were counted; every node unless pivots were sampled
+    pub sources: usize,
+}
+
+impl<N, E> Betweenness<N, E> {
+    // Whether every node was a source, so the scores are exact
+    pub fn is_exact(&self) -> bool {
+        self.sources >= self.node_count
+    }
+
+    // Half-width of an interval around the node scores that contains the exact scores of all nodes at once with
+    // probability `confidence`, e.g. 0.95. It follows from Hoeffding's inequality, which also holds for pivots
+    // drawn without replacement, and a union bound over the nodes. Zero when the scores are exact.
+    pub fn node_error_bound(&self, confidence: f64) -> f64 {
+        let n = self.node_count as f64;
+        if self.is_exact() || self.node_count < 3 {
+            return 0.0;
+        }
+        // A source adds at most n - 2 to a node, which the extrapolation by n / k turns into n (n - 2)
+        let normalized = n / (n - 1.0) * hoeffding_bound(self.sources, self.node_count, confidence);
+        if self.normalized {
+            normalized
+        } else {
+            normalized * (n - 1.0) * (n - 2.0) * self.pair_share()
+        }
+    }
+
+    // The same bound for the edge scores, with the union bound taken over the edges
+    pub fn edge_error_bound(&self, confidence: f64) -> f64 {
+        let n = self.node_count as f64;
+        if self.is_exact() || self.node_count < 2 || self.edge_count == 0 {
+            return 0.0;
+        }
+        // A source adds at most n - 1 to an edge
+        let normalized = hoeffding_bound(self.sources, self.edge_count, confidence);
+        if self.normalized {
+            normalized
+        } else {
+            normalized * n * (n - 1.0) * self.pair_share()
+        }
+    }
+
+    // Unnormalized scores of an undirected graph count every pair once instead of in both directions
+    fn pair_share(&self) -> f64 {
+        if self.directed {
+            1.0
+        } else {
+            0.5
+        }
+    }
+}
+
+// Deviation of the mean of `samples` draws from values in [0, 1) from its expectation that `items` means at once
+// stay within with probability `confidence`
+fn hoeffding_bound(samples: usize, items: usize, confidence: f64) -> f64 {
+    let failure = (1.0 - confidence).max(f64::MIN_POSITIVE);
+    ((2.0 * items.max(1) as f64 / failure).ln() / (2.0 * samples.max(1) as f64)).sqrt()
+}
+
+// Draw `k` distinct pivot sources uniformly at random for sampled betweenness; a seeded generator draws the same
+// pivots on every run. With `k` at least the number of nodes every node is a pivot and the scores are exact.
+pub fn select_pivots<G: IntoNodeIdentifiers, R: Rng + ?Sized>(graph: G, k: usize, rng: &mut R) -> Vec<G::NodeId> {
+    // Landmarks are drawn the same way
+    select_landmarks(graph, k, rng)
+}
+
+// Betweenness centrality with paths counted in hops. It takes any petgraph graph reference, such as `&RoadGraph`
+// or `&RoadNetwork`; on a directed graph paths follow the arcs. With `pivots` only the shortest paths from those
+// sources are counted, once each, and the scores are extrapolated to all sources; `None` searches from every node.
+pub fn betweenness_centrality<G>(
+    graph: G,
+    pivots: Option<&[G::NodeId]>,
+    normalized: bool,
+) -> Betweenness<G::NodeId, G::EdgeId>
+where
+    G: IntoEdgeReferences + IntoNodeIdentifiers + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+    G::EdgeId: Hash + Eq,
+{
+    betweenness_centrality_with_progress(graph, pivots, normalized, &NoProgress)
+}
+
+// Betweenness centrality in hops as above, reporting every source that is searched to `progress`
+pub fn betweenness_centrality_with_progress<G>(
+    graph: G,
+    pivots: Option<&[G::NodeId]>,
+    normalized: bool,
+    progress: &dyn Progress,
+) -> Betweenness<G::NodeId, G::EdgeId>
+where
+    G: IntoEdgeReferences + IntoNodeIdentifiers + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+    G::EdgeId: Hash + Eq,
+{
+    brandes(graph, None::<fn(&G::EdgeWeight) -> f64>, pivots, normalized, progress)
+}
+
+// Betweenness centrality with paths measured by the edge lengths `edge_weight` reads from the edge payload, e.g.
+// `|edge: &RoadEdge| edge.weight`. Lengths must be positive; paths whose lengths are exactly equal are all
+// shortest paths.
+pub fn weighted_betweenness_centrality<G, F>(
+    graph: G,
+    edge_weight: F,
+    pivots: Option<&[G::NodeId]>,
+    normalized: bool,
+) -> Betweenness<G::NodeId, G::EdgeId>
+where
+    G: IntoEdgeReferences + IntoNodeIdentifiers + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+    G::EdgeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    weighted_betweenness_centrality_with_progress(graph, edge_weight, pivots, normalized, &NoProgress)
+}
+
+// Weighted betweenness centrality as above, reporting every source that is searched to `progress`
+pub fn weighted_betweenness_centrality_with_progress<G, F>(
+    graph: G,
+    edge_weight: F,
+    pivots: Option<&[G::NodeId]>,
+    normalized: bool,
+    progress: &dyn Progress,
+) -> Betweenness<G::NodeId, G::EdgeId>
+where
+    G: IntoEdgeReferences + IntoNodeIdentifiers + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+    G::EdgeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    brandes(graph, Some(edge_weight), pivots, normalized, progress)
+}
+
+fn brandes<G, F>(
+    graph: G,
+    edge_weight: Option<F>,
+    pivots: Option<&[G::NodeId]>,
+    normalized: bool,
+    progress: &dyn Progress,
+) -> Betweenness<G::NodeId, G::EdgeId>
+where
+    G: IntoEdgeReferences + IntoNodeIdentifiers + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+    G::EdgeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    let (arcs, edge_ids) = Arcs::new(graph, edge_weight);
+    let node_count = graph.node_identifiers().count();
+    // A pivot given twice is searched once, so the extrapolation and the error bound count distinct sources
+    let mut source_set = HashSet::new();
+    let sources: Vec<usize> = match pivots {
+        Some(pivots) => {
+            pivots.iter().map(|&node| graph.to_index(node)).filter(|&source| source_set.insert(source)).collect()
+        }
+        None => graph.node_identifiers().map(|node| graph.to_index(node)).collect(),
+    };
+
+    let edge_count = edge_ids.len();
+    // Every worker thread sums the dependencies of its sources into scores of its own, which are added up at the end
+    progress.start("Betweenness", Some(sources.len() as u64));
+    let (node_scores, edge_scores) = sources
+        .par_iter()
+        .fold(
+            || Search::new(&arcs, edge_count),
+            |mut search, &source| {
+                search.add_source(&arcs, source);
+                progress.advance(1);
+                search
+            },
+        )
+        .map(|search| (search.node_scores, search.edge_scores))
+        .reduce(
+            || (vec![0.0; arcs.node_bound()], vec![0.0; edge_count]),
+            |(mut node_scores, mut edge_scores), (other_nodes, other_edges)| {
+                node_scores.iter_mut().zip(other_nodes).for_each(|(score, other)| *score += other);
+                edge_scores.iter_mut().zip(other_edges).for_each(|(score, other)| *score += other);
+                (node_scores, edge_scores)
+            },
+        );
+    progress.finish();
+
+    // Extrapolate the pivots to all sources, then normalize or count the pairs of an undirected graph once
+    let n = node_count as f64;
+    let extrapolation = if sources.is_empty() { 1.0 } else { n / sources.len() as f64 };
+    let directed = graph.is_directed();
+    let pair_share = if directed { 1.0 } else { 0.5 };
+    let node_scale = match (normalized, node_count > 2) {
+        (true, true) => 1.0 / ((n - 1.0) * (n - 2.0)),
+        (true, false) => 1.0,
+        (false, _) => pair_share,
+    };
+    let edge_scale = match (normalized, node_count > 1) {
+        (true, true) => 1.0 / (n * (n - 1.0)),
+        (true, false) => 1.0,
+        (false, _) => pair_share,
+    };
+
+    Betweenness {
+        nodes: graph
+            .node_identifiers()
+            .map(|node| (node, node_scores[graph.to_index(node)] * extrapolation * node_scale))
+            .collect(),
+        edges: edge_ids
+            .into_iter()
+            .zip(edge_scores)
+            .map(|(edge, score)| (edge, score * extrapolation * edge_scale))
+            .collect(),
+        normalized,
+        directed,
+        node_count,
+        edge_count,
+        sources: sources.len(),
+    }
+}
+
+// The arcs of a graph in compressed rows indexed by `to_index`, each with its length, if paths are weighted, and
+// the position of its edge in `edge_references`. An undirected edge is an arc in both directions; self-loops are
+// left out, as no shortest path uses them.
+struct Arcs {
+    offsets: Vec<usize>,
+    targets: Vec<usize>,
+    edges: Vec<usize>,
+    lengths: Option<Vec<f64>>,
+}
+
+impl Arcs {
+    fn new<G, F>(graph: G, edge_weight: Option<F>) -> (Self, Vec<G::EdgeId>)
+    where
+        G: IntoEdgeReferences + NodeIndexable + GraphProp,
+        F: Fn(&G::EdgeWeight) -> f64,
+    {
+        let mut edge_ids = Vec::new();
+        let mut entries = Vec::new();
+        for edge in graph.edge_references() {
+            let (source, target) = (graph.to_index(edge.source()), graph.to_index(edge.target()));
+            let length = edge_weight.as_ref().map_or(1.0, |edge_weight| edge_weight(edge.weight()));
+            if source != target {
+                entries.push((source, target, edge_ids.len(), length));
+                if !graph.is_directed() {
+                    entries.push((target, source, edge_ids.len(), length));
+                }
+            }
+            edge_ids.push(edge.id());
+        }
+
+        let mut offsets = vec![0; graph.node_bound() + 1];
+        for &(source, ..) in &entries {
+            offsets[source + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        let mut next = offsets.clone();
+        let mut targets = vec![0; entries.len()];
+        let mut edges = vec![0; entries.len()];
+        let mut lengths = vec![0.0; entries.len()];
+        for (source, target, edge, length) in entries {
+            let position = next[source];
+            next[source] += 1;
+            targets[position] = target;
+            edges[position] = edge;
+            lengths[position] = length;
+        }
+
+        let lengths = edge_weight.is_some().then_some(lengths);
+        (Arcs { offsets, targets, edges, lengths }, edge_ids)
+    }
+
+    fn node_bound(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn row(&self, node: usize) -> std::ops::Range<usize> {
+        self.offsets[node]..self.offsets[node + 1]
+    }
+}
+
+// A node reached at a tentative distance, ordered so the `BinaryHeap` pops the nearest first
+struct Tentative(f64, usize);
+
+impl PartialEq for Tentative {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Tentative {}
+
+impl PartialOrd for Tentative {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tentative {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0)
+    }
+}
+
+// The working arrays of the single-source searches of one worker thread, and the scores its sources add up to.
+// The arrays are reset after every source, touching only the nodes it reached.
+struct Search {
+    distance: Vec<f64>,
+    // Number of shortest paths from the source; a float, as the count overflows any integer on a large grid
+    paths: Vec<f64>,
+    dependency: Vec<f64>,
+    // The node and edge every shortest path to a node arrives from
+    predecessors: Vec<Vec<(usize, usize)>>,
+    // The reached nodes in the order of their distance from the source
+    order: Vec<usize>,
+    queue: VecDeque<usize>,
+    heap: BinaryHeap<Tentative>,
+    node_scores: Vec<f64>,
+    edge_scores: Vec<f64>,
+}
+
+impl Search {
+    fn new(arcs: &Arcs, edge_count: usize) -> Self {
+        let node_bound = arcs.node_bound();
+        Search {
+            distance: vec![f64::INFINITY; node_bound],
+            paths: vec![0.0; node_bound],
+            dependency: vec![0.0; node_bound],
+            predecessors: vec![Vec::new(); node_bound],
+            order: Vec::new(),
+            queue: VecDeque::new(),
+            heap: BinaryHeap::new(),
+            node_scores: vec![0.0; node_bound],
+            edge_scores: vec![0.0; edge_count],
+        }
+    }
+
+    // Count the shortest paths from `source`, then add the dependencies of the nodes and edges on it to the scores
+    fn add_source(&mut self, arcs: &Arcs, source: usize) {
+        self.distance[source] = 0.0;
+        self.paths[source] = 1.0;
+        match &arcs.lengths {
+            Some(lengths) => self.dijkstra(arcs, lengths, source),
+            None => self.breadth_first(arcs, source),
+        }
+
+        // Nodes further away pass their dependency back along the shortest paths, in proportion to the number of
+        // paths that arrive over each predecessor
+        for &node in self.order.iter().rev() {
+            let share = (1.0 + self.dependency[node]) / self.paths[node];
+            for &(predecessor, edge) in &self.predecessors[node] {
+                let dependency = self.paths[predecessor] * share;
+                self.dependency[predecessor] += dependency;
+                self.edge_scores[edge] += dependency;
+            }
+            if node != source {
+                self.node_scores[node] += self.dependency[node];
+            }
+        }
+
+        for &node in &self.order {
+            self.distance[node] = f64::INFINITY;
+            self.paths[node] = 0.0;
+            self.dependency[node] = 0.0;
+            self.predecessors[node].clear();
+        }
+        self.order.clear();
+    }
+
+    fn breadth_first(&mut self, arcs: &Arcs, source: usize) {
+        self.queue.push_back(source);
+        while let Some(node) = self.queue.pop_front() {
+            self.order.push(node);
+            let next = self.distance[node] + 1.0;
+            for arc in arcs.row(node) {
+                let target = arcs.targets[arc];
+                if self.distance[target] == f64::INFINITY {
+                    self.distance[target] = next;
+                    self.queue.push_back(target);
+                }
+                if self.distance[target] == next {
+                    self.paths[target] += self.paths[node];
+                    self.predecessors[target].push((node, arcs.edges[arc]));
+                }
+            }
+        }
+    }
+
+    fn dijkstra(&mut self, arcs: &Arcs, lengths: &[f64], source: usize) {
+        self.heap.push(Tentative(0.0, source));
+        while let Some(Tentative(distance, node)) = self.heap.pop() {
+            // A node is pushed again whenever its distance improves; the older entries are stale
+            if distance > self.distance[node] {
+                continue;
+            }
+            self.order.push(node);
+            for arc in arcs.row(node) {
+                let target = arcs.targets[arc];
+                let next = distance + lengths[arc];
+                if next < self.distance[target] {
+                    // Reached for the first time or over a shorter path, which replaces the paths found so far
+                    self.distance[target] = next;
+                    self.paths[target] = self.paths[node];
+                    self.predecessors[target].clear();
+                    self.predecessors[target].push((node, arcs.edges[arc]));
+                    self.heap.push(Tentative(next, target));
+                } else if next == self.distance[target] {
+                    self.paths[target] += self.paths[node];
+                    self.predecessors[target].push((node, arcs.edges[arc]));
+                }
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::tests::grid;
+    use crate::data::{RoadEdge, RoadNetwork};
+    use petgraph::graphmap::DiGraphMap;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn test_betweenness_of_a_path() {
+        let graph = RoadNetwork::from_edges([(0, 1), (1, 2), (2, 3), (3, 4)]);
+        let betweenness = betweenness_centrality(&graph, None, false);
+
+        // The inner nodes lie between every pair on either side of them
+        let expected_nodes = [0.0, 3.0, 4.0, 3.0, 0.0];
+        for (node, expected) in expected_nodes.iter().enumerate() {
+            assert_close(betweenness.nodes[&node], *expected);
+        }
+        // An edge carries the paths between the nodes on either side of it, including its own endpoints
+        assert_close(betweenness.edges[&(0, 1)], 4.0);
+        assert_close(betweenness.edges[&(1, 2)], 6.0);
+        assert!(betweenness.is_exact());
+        assert_eq!(betweenness.node_error_bound(0.95), 0.0);
+
+        // Normalized, the center lies on 4 of the 6 pairs of other nodes
+        let normalized = betweenness_centrality(&graph, None, true);
+        assert_close(normalized.nodes[&2], 4.0 / 6.0);
+        assert_close(normalized.edges[&(1, 2)], 6.0 / 10.0);
+    }
+
+    #[test]
+    fn test_betweenness_splits_equal_paths() {
+        // Opposite corners of a square are joined by two shortest paths, one through each other corner
+        let graph = RoadNetwork::from_edges([(0, 1), (1, 2), (2, 3), (3, 0)]);
+        let betweenness = betweenness_centrality(&graph, None, false);
+        for node in 0..4 {
+            assert_close(betweenness.nodes[&node], 0.5);
+        }
+    }
+
+    #[test]
+    fn test_weighted_betweenness_follows_lengths() {
+        // The direct edge between 0 and 2 is longer than the detour through 1
+        let mut graph = RoadNetwork::new();
+        graph.add_edge(0, 1, RoadEdge::from_weight(1.0));
+        graph.add_edge(1, 2, RoadEdge::from_weight(1.0));
+        graph.add_edge(0, 2, RoadEdge::from_weight(3.0));
+
+        let hops = betweenness_centrality(&graph, None, false);
+        assert_close(hops.nodes[&1], 0.0);
+        let weighted = weighted_betweenness_centrality(&graph, |edge: &RoadEdge| edge.weight, None, false);
+        assert_close(weighted.nodes[&1], 1.0);
+        assert_close(weighted.edges[&(0, 2)], 0.0);
+        assert_close(weighted.edges[&(0, 1)], 2.0);
+    }
+
+    #[test]
+    fn test_betweenness_follows_arcs() {
+        // Only 0 -> 2 passes through 1; 2 cannot be left
+        let graph = DiGraphMap::<u32, f64>::from_edges([(0, 1, 1.0), (1, 2, 1.0), (0, 2, 5.0)]);
+        let betweenness = weighted_betweenness_centrality(&graph, |weight: &f64| *weight, None, false);
+        assert_close(betweenness.nodes[&1], 1.0);
+        assert_close(betweenness.edges[&(0, 1)], 2.0);
+        assert_close(betweenness.edges[&(1, 2)], 2.0);
+        assert_close(betweenness.edges[&(0, 2)], 0.0);
+    }
+
+    #[test]
+    fn test_sampled_betweenness_stays_within_its_bound() {
+        let graph = grid(8);
+        let exact = betweenness_centrality(&graph, None, true);
+
+        // Every node as a pivot gives the exact scores
+        let all: Vec<usize> = graph.nodes().collect();
+        let everything = betweenness_centrality(&graph, Some(&all), true);
+        assert!(everything.is_exact());
+        for (node, score) in &exact.nodes {
+            assert_close(everything.nodes[node], *score);
+        }
+
+        let pivots = select_pivots(&graph, 16, &mut StdRng::seed_from_u64(42));
+        let progress = crate::progress::tests::CountingProgress::default();
+        let sampled = betweenness_centrality_with_progress(&graph, Some(&pivots), true, &progress);
+        assert_eq!(progress.tasks(), vec![("Betweenness".to_string(), Some(16))]);
+        assert_eq!(progress.steps(), 16);
+        assert!(!sampled.is_exact());
+
+        let node_bound = sampled.node_error_bound(0.95);
+        let edge_bound = sampled.edge_error_bound(0.95);
+        assert!(node_bound > 0.0 && edge_bound > 0.0);
+        assert!(exact.nodes.iter().all(|(node, score)| (sampled.nodes[node] - score).abs() <= node_bound));
+        assert!(exact.edges.iter().all(|(edge, score)| (sampled.edges[edge] - score).abs() <= edge_bound));
+
+        // The bound shrinks with more pivots and scales with the scores
+        let more = select_pivots(&graph, 48, &mut StdRng::seed_from_u64(42));
+        assert!(betweenness_centrality(&graph, Some(&more), true).node_error_bound(0.95) < node_bound);
+        let unnormalized = betweenness_centrality(&graph, Some(&pivots), false);
+        assert_close(unnormalized.node_error_bound(0.95), node_bound * 63.0 * 62.0 / 2.0);
+    }
+
+    #[test]
+    fn test_repeated_pivots_count_once() {
+        let graph = grid(4);
+        let exact = betweenness_centrality(&graph, None, true);
+
+        // Every node twice is still every node once, so the scores are exact and not doubled
+        let twice: Vec<usize> = graph.nodes().chain(graph.nodes()).collect();
+        let repeated = betweenness_centrality(&graph, Some(&twice), true);
+        assert_eq!(repeated.sources, 16);
+        assert!(repeated.is_exact());
+        assert_eq!(repeated.node_error_bound(0.95), 0.0);
+        for (node, score) in &exact.nodes {
+            assert_close(repeated.nodes[node], *score);
+        }
+
+        let pivots = [0, 5, 5, 0, 10];
+        let sampled = betweenness_centrality(&graph, Some(&pivots), true);
+        assert_eq!(sampled.sources, 3);
+        let distinct = betweenness_centrality(&graph, Some(&[0, 5, 10]), true);
+        assert_close(sampled.node_error_bound(0.95), distinct.node_error_bound(0.95));
+        for (node, score) in &distinct.nodes {
+            assert_close(sampled.nodes[node], *score);
+        }
+    }
+}
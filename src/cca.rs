@@ -1,36 +1,58 @@
 // cca.rs
 
-use petgraph::Graph;
-use petgraph::prelude::*;
-use petgraph::algo::connected_components;
+use petgraph::algo::tarjan_scc;
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNeighbors, IntoNodeIdentifiers, NodeIndexable};
 use std::collections::HashMap;
 
-// The analyze_connected_components function takes a reference to an undirected graph with nodes
-// representing points in 2D space and edges with associated weights. It returns a tuple containing
-// the number of connected components in the graph and a vector of vectors, where each inner vector
-// represents the nodes belonging to a specific connected component.
-pub fn analyze_connected_components(graph: &Graph<(f64, f64), f64, petgraph::Undirected>) -> (usize, Vec<Vec<NodeIndex>>) {
-    // Calculate the number of connected components in the graph using the connected_components function
-    // from the petgraph crate.
-    let num_connected_components = connected_components(&graph);
-
+// The analyze_connected_components function takes any undirected petgraph graph reference, such as
+// `&RoadGraph` or `&RoadNetwork`. It returns a tuple containing the number of connected components in the
+// graph and a vector of vectors, where each inner vector holds the identifiers of the nodes belonging to
+// a specific connected component.
+pub fn analyze_connected_components<G>(graph: G) -> (usize, Vec<Vec<G::NodeId>>)
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+{
     // Initialize a HashMap to store the nodes belonging to each connected component, where the key is
-    // the component ID and the value is a vector of NodeIndex values representing the nodes in the component.
-    let mut components: HashMap<usize, Vec<NodeIndex>> = HashMap::new();
+    // the component ID and the value is a vector of the nodes in the component.
+    let mut components: HashMap<usize, Vec<G::NodeId>> = HashMap::new();
+
+    // Merge the endpoints of every edge so that each node's representative identifies its component.
+    let mut union_find = UnionFind::new(graph.node_bound());
+    for edge in graph.edge_references() {
+        union_find.union(graph.to_index(edge.source()), graph.to_index(edge.target()));
+    }
 
     // Iterate over all nodes in the graph and add them to the corresponding connected component in the HashMap.
-    for node in graph.node_indices() {
-        let component_id = graph[node].1 as usize;
-        components.entry(component_id).or_insert_with(Vec::new).push(node);
+    for node in graph.node_identifiers() {
+        let component_id = union_find.find_mut(graph.to_index(node));
+        components.entry(component_id).or_default().push(node);
     }
 
     // Convert the HashMap into a vector of vectors representing the connected components, and sort the
     // components by size in descending order.
-    let mut component_sizes = components.into_iter().map(|(_id, nodes)| nodes).collect::<Vec<_>>();
-    component_sizes.sort_by(|a, b| b.len().cmp(&a.len()));
+    let mut component_sizes = components.into_values().collect::<Vec<_>>();
+    component_sizes.sort_by_key(|nodes| std::cmp::Reverse(nodes.len()));
 
     // Return the number of connected components and the sorted vector of connected components.
-    (num_connected_components, component_sizes)
+    (component_sizes.len(), component_sizes)
+}
+
+// The analyze_strongly_connected_components function takes any directed petgraph graph reference and returns
+// a tuple containing the number of strongly connected components and the components themselves, sorted by
+// size in descending order. Within a strongly connected component every node can reach every other node
+// while respecting one-way streets, so nodes outside the largest component are only reachable one way.
+pub fn analyze_strongly_connected_components<G>(graph: G) -> (usize, Vec<Vec<G::NodeId>>)
+where
+    G: IntoNodeIdentifiers + IntoNeighbors + NodeIndexable,
+{
+    // Tarjan's algorithm finds all strongly connected components in a single depth-first pass.
+    let mut components = tarjan_scc(graph);
+
+    // Sort the components by size in descending order, matching analyze_connected_components.
+    components.sort_by_key(|nodes| std::cmp::Reverse(nodes.len()));
+
+    (components.len(), components)
 }
 
 
@@ -38,36 +60,43 @@ pub fn analyze_connected_components(graph: &Graph<(f64, f64), f64, petgraph::Und
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::{RoadEdge, RoadGraph, RoadNode};
     use petgraph::graph::NodeIndex;
 
-    fn create_test_graph1() -> Graph<(f64, f64), f64, petgraph::Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, petgraph::Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 0.0));
-        graph.add_edge(a, b, 1.0);
+    // Add a node with an id that differs from its index
+    fn add_node<Ty: petgraph::EdgeType>(graph: &mut RoadGraph<Ty>, position: (f64, f64)) -> NodeIndex {
+        let id = 100 + graph.node_count();
+        graph.add_node(RoadNode { id, position: Some(position) })
+    }
+
+    fn create_test_graph1() -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let a = add_node(&mut graph, (0.0, 0.0));
+        let b = add_node(&mut graph, (1.0, 0.0));
+        graph.add_edge(a, b, RoadEdge::default());
 
         graph
     }
 
-    fn create_test_graph2() -> Graph<(f64, f64), f64, petgraph::Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, petgraph::Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 0.0));
-        let c = graph.add_node((2.0, 1.0));
-        graph.add_edge(a, b, 1.0);
-        graph.add_edge(b, c, 1.0);
+    fn create_test_graph2() -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let a = add_node(&mut graph, (0.0, 0.0));
+        let b = add_node(&mut graph, (1.0, 0.0));
+        let c = add_node(&mut graph, (2.0, 1.0));
+        graph.add_edge(a, b, RoadEdge::default());
+        graph.add_edge(b, c, RoadEdge::default());
 
         graph
     }
 
-    fn create_test_graph3() -> Graph<(f64, f64), f64, petgraph::Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, petgraph::Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 0.0));
-        let c = graph.add_node((2.0, 1.0));
-        let d = graph.add_node((3.0, 1.0));
-        graph.add_edge(a, b, 1.0);
-        graph.add_edge(c, d, 1.0);
+    fn create_test_graph3() -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let a = add_node(&mut graph, (0.0, 0.0));
+        let b = add_node(&mut graph, (1.0, 0.0));
+        let c = add_node(&mut graph, (2.0, 1.0));
+        let d = add_node(&mut graph, (3.0, 1.0));
+        graph.add_edge(a, b, RoadEdge::default());
+        graph.add_edge(c, d, RoadEdge::default());
 
         graph
     }
@@ -82,6 +111,17 @@ mod tests {
         assert_eq!(component_sizes[0].len(), 2);
     }
 
+    #[test]
+    fn test_analyze_connected_components_path() {
+        // The nodes sit at different y coordinates but still form a single component
+        let graph = create_test_graph2();
+        let (num_connected_components, component_sizes) = analyze_connected_components(&graph);
+
+        assert_eq!(num_connected_components, 1);
+        assert_eq!(component_sizes.len(), 1);
+        assert_eq!(component_sizes[0].len(), 3);
+    }
+
     #[test]
     fn test_analyze_connected_components_two_components() {
         let graph = create_test_graph3();
@@ -95,11 +135,49 @@ mod tests {
 
     #[test]
     fn test_analyze_connected_components_empty_graph() {
-        let graph = Graph::<(f64, f64), f64, petgraph::Undirected>::new_undirected();
+        let graph = RoadGraph::new_undirected();
         let (num_connected_components, component_sizes) = analyze_connected_components(&graph);
 
         assert_eq!(num_connected_components, 0);
         assert_eq!(component_sizes.len(), 0);
     }
-}
 
+    #[test]
+    fn test_analyze_strongly_connected_components() {
+        // A one-way loop a -> b -> c -> a with a one-way exit c -> d
+        let mut graph = RoadGraph::<petgraph::Directed>::default();
+        let a = add_node(&mut graph, (0.0, 0.0));
+        let b = add_node(&mut graph, (1.0, 0.0));
+        let c = add_node(&mut graph, (1.0, 1.0));
+        let d = add_node(&mut graph, (2.0, 1.0));
+        for (from, to) in [(a, b), (b, c), (c, a), (c, d)] {
+            graph.add_edge(from, to, RoadEdge::default());
+        }
+
+        let (num_components, components) = analyze_strongly_connected_components(&graph);
+
+        assert_eq!(num_components, 2);
+        assert_eq!(components[0].len(), 3);
+        assert_eq!(components[1], vec![d]);
+    }
+
+    #[test]
+    fn test_analyze_components_on_other_graph_types() {
+        // A GraphMap reports its components in the original ids
+        let graph = crate::data::DirectedRoadNetwork::from_edges([(7, 8), (8, 7), (8, 9)]);
+        let (num_components, components) = analyze_strongly_connected_components(&graph);
+        assert_eq!(num_components, 2);
+        assert_eq!(components[1], vec![9]);
+
+        // A StableGraph with a removed node still numbers its components correctly
+        let mut stable = petgraph::stable_graph::StableUnGraph::<(), ()>::default();
+        let [a, b, c, d] = [(); 4].map(|_| stable.add_node(()));
+        stable.add_edge(a, b, ());
+        stable.add_edge(c, d, ());
+        stable.remove_node(c);
+        let (num_components, components) = analyze_connected_components(&stable);
+        assert_eq!(num_components, 2);
+        assert_eq!(components[0], vec![a, b]);
+        assert_eq!(components[1], vec![d]);
+    }
+}
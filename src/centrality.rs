@@ -1,28 +1,44 @@
 // centrality.rs
 
-use petgraph::graphmap::UnGraphMap;
-use petgraph::visit::{EdgeRef, IntoEdgeReferences, NodeCount, EdgeCount};
-use ndarray::{Array1, Array2, s};
-use ndarray_linalg::c64;
-use lapack::dsyev;
-use std::collections::{HashMap, VecDeque};
+use crate::progress::{NoProgress, Progress};
+use nalgebra::{DMatrix, SymmetricEigen};
+use petgraph::algo::dijkstra;
+use petgraph::unionfind::UnionFind;
+use petgraph::visit::{
+    EdgeRef, GraphProp, IntoEdgeReferences, IntoEdges, IntoNeighbors, IntoNeighborsDirected, IntoNodeIdentifiers,
+    NodeIndexable, Visitable,
+};
+use petgraph::Direction;
 use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+// Scores of a centrality measure keyed by the node identifiers of the graph, the result every measure here
+// returns, so they can stand in for one another in reports and comparisons
+pub type CentralityScores<N> = HashMap<N, f64>;
 
 // This function calculates the degree centrality of a given graph.
-// It takes a reference to a petgraph UnGraphMap object as input and
-// returns a HashMap where the keys are the node indices and the values
-// are the degree centrality of each node.
+// It takes any petgraph graph reference that can list its nodes and their neighbors as input and
+// returns a HashMap where the keys are the node identifiers of the graph and the values
+// are the degree centrality of each node. On a GraphMap the identifiers are the original node ids,
+// on a Graph they are node indices. Directed graphs count the outgoing arcs.
 
-pub fn degree_centrality(graph: &UnGraphMap<usize, f64>) -> HashMap<usize, f64> {
+pub fn degree_centrality<G>(graph: G) -> CentralityScores<G::NodeId>
+where
+    G: IntoNodeIdentifiers + IntoNeighbors,
+    G::NodeId: Hash + Eq,
+{
     // Create an empty HashMap to store the degree centrality values
     let mut centrality = HashMap::new();
 
     // Iterate over all nodes in the graph
-    for node in graph.nodes() {
+    for node in graph.node_identifiers() {
         // Count the number of neighbors for the current node
         let degree = graph.neighbors(node).count();
 
-        // Insert the node index and its degree centrality into the HashMap
+        // Insert the node identifier and its degree centrality into the HashMap
         centrality.insert(node, degree as f64);
     }
 
@@ -30,8 +46,704 @@ pub fn degree_centrality(graph: &UnGraphMap<usize, f64>) -> HashMap<usize, f64>
     centrality
 }
 
-use std::time::Instant;
-use rand::Rng;
+// Degree centrality divided by n - 1, the most neighbors a node can have, so the scores lie between 0 and 1 and
+// compare across graphs of different sizes
+pub fn normalized_degree_centrality<G>(graph: G) -> CentralityScores<G::NodeId>
+where
+    G: IntoNodeIdentifiers + IntoNeighbors,
+    G::NodeId: Hash + Eq,
+{
+    normalize_by_node_count(degree_centrality(graph))
+}
+
+// Divide the scores of the n nodes of a graph by n - 1, as normalized_degree_centrality does; the in- and
+// out-degrees normalize the same way. A single node keeps its score.
+pub fn normalize_by_node_count<N>(mut scores: CentralityScores<N>) -> CentralityScores<N> {
+    let others = scores.len().saturating_sub(1).max(1) as f64;
+    for score in scores.values_mut() {
+        *score /= others;
+    }
+    scores
+}
+
+// Weighted degree, or strength: the total weight of the edges at every node, read from the edge payload by
+// `edge_weight`, e.g. `|edge: &RoadEdge| edge.weight`. Directed graphs sum the outgoing arcs.
+pub fn weighted_degree_centrality<G, F>(graph: G, edge_weight: F) -> CentralityScores<G::NodeId>
+where
+    G: IntoNodeIdentifiers + IntoEdges,
+    G::NodeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    graph
+        .node_identifiers()
+        .map(|node| (node, graph.edges(node).map(|edge| edge_weight(edge.weight())).sum()))
+        .collect()
+}
+
+// Number of arcs arriving at every node. On an undirected graph every edge arrives at both its endpoints, so
+// this is the degree.
+pub fn in_degree_centrality<G>(graph: G) -> CentralityScores<G::NodeId>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected,
+    G::NodeId: Hash + Eq,
+{
+    graph
+        .node_identifiers()
+        .map(|node| (node, graph.neighbors_directed(node, Direction::Incoming).count() as f64))
+        .collect()
+}
+
+// Number of arcs leaving every node; the degree on an undirected graph
+pub fn out_degree_centrality<G>(graph: G) -> CentralityScores<G::NodeId>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected,
+    G::NodeId: Hash + Eq,
+{
+    graph
+        .node_identifiers()
+        .map(|node| (node, graph.neighbors_directed(node, Direction::Outgoing).count() as f64))
+        .collect()
+}
+
+// How eigenvector_centrality finds the leading eigenvector of each connected component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EigenSolver {
+    // The dense solver for components of up to `dense_threshold` nodes, Lanczos for larger ones
+    #[default]
+    Auto,
+    // Full eigendecomposition of the adjacency matrix; memory grows with the square of the component size
+    Dense,
+    // Power iteration on the adjacency matrix shifted by the identity, so bipartite grids do not oscillate
+    Power,
+    // Restarted Lanczos iteration, which needs far fewer matrix-vector products than power iteration when
+    // the leading eigenvalues lie close together, as they do on large road networks
+    Lanczos,
+}
+
+// Settings of eigenvector_centrality
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EigenvectorOptions {
+    pub solver: EigenSolver,
+    // A component has converged once the residual `|Ax - λx|` of its unit eigenvector estimate is at most
+    // `tolerance * λ`
+    pub tolerance: f64,
+    // Matrix-vector products allowed per component before giving up
+    pub max_iterations: usize,
+    // Largest component that `Auto` hands to the dense solver
+    pub dense_threshold: usize,
+}
+
+impl Default for EigenvectorOptions {
+    fn default() -> Self {
+        EigenvectorOptions { solver: EigenSolver::Auto, tolerance: 1e-6, max_iterations: 10_000, dense_threshold: 256 }
+    }
+}
+
+// Errors of the iterative centrality measures
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CentralityError {
+    // The iteration did not reach the tolerance within the allowed number of iterations
+    NotConverged { iterations: usize, residual: f64 },
+    // The Katz attenuation is not positive or not below `limit`, one over the spectral radius, so the walks it
+    // sums grow without bound
+    InvalidAttenuation { attenuation: f64, limit: f64 },
+}
+
+impl fmt::Display for CentralityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CentralityError::NotConverged { iterations, residual } => {
+                write!(f, "did not converge within {} iterations (residual {:e})", iterations, residual)
+            }
+            CentralityError::InvalidAttenuation { attenuation, limit } => {
+                write!(f, "attenuation {} must be positive and below {}, one over the spectral radius", attenuation, limit)
+            }
+        }
+    }
+}
+
+impl Error for CentralityError {}
+
+// Size of the Lanczos basis, and the number of Ritz vectors it keeps across a restart
+const LANCZOS_VECTORS: usize = 24;
+const LANCZOS_KEPT: usize = 8;
+
+// The adjacency of a graph in compressed rows indexed by `to_index`
+struct Adjacency {
+    offsets: Vec<usize>,
+    neighbors: Vec<usize>,
+}
+
+impl Adjacency {
+    // The symmetric adjacency: every edge appears in the rows of both its endpoints and a self-loop once, so the
+    // arcs of a directed graph are treated as undirected edges
+    fn new<G>(graph: G) -> Self
+    where
+        G: IntoEdgeReferences + NodeIndexable,
+    {
+        let mut entries = Vec::new();
+        for edge in graph.edge_references() {
+            let (a, b) = (graph.to_index(edge.source()), graph.to_index(edge.target()));
+            entries.push((a, b));
+            if a != b {
+                entries.push((b, a));
+            }
+        }
+        Adjacency::from_entries(graph.node_bound(), entries)
+    }
+
+    // `A + Aᵀ` of a directed graph: every arc appears in the rows of both its endpoints, a self-loop twice
+    fn symmetrized<G>(graph: G) -> Self
+    where
+        G: IntoEdgeReferences + NodeIndexable,
+    {
+        let mut entries = Vec::new();
+        for edge in graph.edge_references() {
+            let (a, b) = (graph.to_index(edge.source()), graph.to_index(edge.target()));
+            entries.extend([(a, b), (b, a)]);
+        }
+        Adjacency::from_entries(graph.node_bound(), entries)
+    }
+
+    // The row of a node lists the sources of the arcs arriving at it; an undirected edge arrives at both ends
+    fn incoming<G>(graph: G) -> Self
+    where
+        G: IntoEdgeReferences + NodeIndexable + GraphProp,
+    {
+        let mut entries = Vec::new();
+        for edge in graph.edge_references() {
+            let (a, b) = (graph.to_index(edge.source()), graph.to_index(edge.target()));
+            entries.push((b, a));
+            if a != b && !graph.is_directed() {
+                entries.push((a, b));
+            }
+        }
+        Adjacency::from_entries(graph.node_bound(), entries)
+    }
+
+    // Rows from (node, neighbor) entries
+    fn from_entries(node_bound: usize, entries: Vec<(usize, usize)>) -> Self {
+        let mut offsets = vec![0; node_bound + 1];
+        for &(node, _) in &entries {
+            offsets[node + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        let mut next = offsets.clone();
+        let mut neighbors = vec![0; entries.len()];
+        for (node, neighbor) in entries {
+            neighbors[next[node]] = neighbor;
+            next[node] += 1;
+        }
+        Adjacency { offsets, neighbors }
+    }
+
+    fn row(&self, node: usize) -> &[usize] {
+        &self.neighbors[self.offsets[node]..self.offsets[node + 1]]
+    }
+}
+
+// One connected component: its nodes by `to_index`, and the position of every node of the graph within it
+struct Component<'a> {
+    adjacency: &'a Adjacency,
+    members: &'a [usize],
+    position: &'a [usize],
+}
+
+impl Component<'_> {
+    fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    // y = A x, over the positions of the component
+    fn multiply(&self, x: &[f64], y: &mut [f64]) {
+        for (i, &node) in self.members.iter().enumerate() {
+            y[i] = self.adjacency.row(node).iter().map(|&neighbor| x[self.position[neighbor]]).sum();
+        }
+    }
+
+    // Relative residual `|Ax - λx| / λ` of a unit vector x, together with its Rayleigh quotient λ
+    fn residual(&self, x: &[f64], ax: &mut [f64]) -> (f64, f64) {
+        self.multiply(x, ax);
+        let lambda = dot(x, ax);
+        let residual = ax.iter().zip(x).map(|(a, b)| (a - lambda * b).powi(2)).sum::<f64>().sqrt();
+        (lambda, residual / lambda.abs().max(f64::MIN_POSITIVE))
+    }
+
+    // The leading unit eigenvector, from the solver the options choose for a component of this size
+    fn leading(&self, options: &EigenvectorOptions) -> Result<Vec<f64>, CentralityError> {
+        let dense = match options.solver {
+            EigenSolver::Auto => self.len() <= options.dense_threshold,
+            EigenSolver::Dense => true,
+            EigenSolver::Power | EigenSolver::Lanczos => false,
+        };
+        if dense {
+            Ok(self.dense())
+        } else if options.solver == EigenSolver::Power {
+            self.power(options)
+        } else {
+            self.lanczos(options)
+        }
+    }
+
+    fn dense(&self) -> Vec<f64> {
+        let mut matrix = DMatrix::<f64>::zeros(self.len(), self.len());
+        for (i, &node) in self.members.iter().enumerate() {
+            for &neighbor in self.adjacency.row(node) {
+                matrix[(i, self.position[neighbor])] += 1.0;
+            }
+        }
+        let eigen = SymmetricEigen::new(matrix);
+        let leading = eigen.eigenvalues.imax();
+        eigen.eigenvectors.column(leading).iter().copied().collect()
+    }
+
+    fn power(&self, options: &EigenvectorOptions) -> Result<Vec<f64>, CentralityError> {
+        let mut x = vec![1.0 / (self.len() as f64).sqrt(); self.len()];
+        let mut ax = vec![0.0; self.len()];
+        let mut residual = f64::INFINITY;
+        for _ in 0..options.max_iterations {
+            let (_, current) = self.residual(&x, &mut ax);
+            residual = current;
+            if residual <= options.tolerance {
+                return Ok(x);
+            }
+            // Multiplying by A + I keeps the Perron root dominant even when -λ is an eigenvalue too
+            for (value, product) in x.iter_mut().zip(&ax) {
+                *value += product;
+            }
+            normalize(&mut x);
+        }
+        Err(CentralityError::NotConverged { iterations: options.max_iterations, residual })
+    }
+
+    // Thick-restart Lanczos. The basis grows one Krylov vector per matrix-vector product, kept orthonormal by
+    // full reorthogonalization; its projection `h = Vᵀ A V` is read off the reorthogonalization coefficients.
+    // When the basis is full, the leading Ritz vectors of the projection and the last Krylov vector become the
+    // start of the next basis, so a restart keeps what the previous cycles learned about the top of the spectrum.
+    fn lanczos(&self, options: &EigenvectorOptions) -> Result<Vec<f64>, CentralityError> {
+        let size = self.len();
+        let basis_size = LANCZOS_VECTORS.min(size);
+        let keep = LANCZOS_KEPT.min(basis_size - 1);
+        let mut basis: Vec<Vec<f64>> = vec![vec![1.0 / (size as f64).sqrt(); size]];
+        // Column j holds the coefficients of A v_j on the basis vectors up to v_{j+1}
+        let mut h = DMatrix::<f64>::zeros(basis_size + 1, basis_size);
+        let mut w = vec![0.0; size];
+        let mut products = 0;
+        let mut residual = f64::INFINITY;
+
+        while products < options.max_iterations {
+            let mut columns = basis.len() - 1;
+            let mut invariant = false;
+            while columns < basis_size {
+                let j = columns;
+                self.multiply(&basis[j], &mut w);
+                products += 1;
+                // Two passes of Gram-Schmidt keep the basis orthogonal to working precision
+                for _ in 0..2 {
+                    for (i, vector) in basis.iter().enumerate() {
+                        let overlap = dot(&w, vector);
+                        h[(i, j)] += overlap;
+                        for (value, component) in w.iter_mut().zip(vector) {
+                            *value -= overlap * component;
+                        }
+                    }
+                }
+                let beta = dot(&w, &w).sqrt();
+                columns += 1;
+                // A vanishing remainder means the basis spans an invariant subspace and the projection is exact
+                if beta <= 1e-12 * h[(j, j)].abs().max(1.0) {
+                    invariant = true;
+                    break;
+                }
+                h[(j + 1, j)] = beta;
+                basis.push(w.iter().map(|value| value / beta).collect());
+            }
+
+            // The projection is symmetric; take its upper triangle, which the reorthogonalization computed
+            let projection = DMatrix::from_fn(columns, columns, |i, j| h[(i.min(j), i.max(j))]);
+            let eigen = SymmetricEigen::new(projection);
+            let mut order: Vec<usize> = (0..columns).collect();
+            order.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+            let ritz_vector = |k: usize| {
+                let mut vector = vec![0.0; size];
+                for (coefficient, basis_vector) in eigen.eigenvectors.column(k).iter().zip(&basis) {
+                    for (value, component) in vector.iter_mut().zip(basis_vector) {
+                        *value += coefficient * component;
+                    }
+                }
+                vector
+            };
+
+            let mut x = ritz_vector(order[0]);
+            normalize(&mut x);
+            let (_, current) = self.residual(&x, &mut w);
+            products += 1;
+            residual = current;
+            if residual <= options.tolerance || invariant {
+                return Ok(x);
+            }
+
+            // Restart from the leading Ritz vectors followed by the last Krylov vector. The Ritz vectors are
+            // eigenvectors of the projection, so the new projection starts out diagonal; its couplings to the
+            // Krylov vector come out of the reorthogonalization of the next column.
+            let mut restarted: Vec<Vec<f64>> = order[..keep].iter().map(|&k| ritz_vector(k)).collect();
+            restarted.push(basis.pop().expect("a full basis has a next Krylov vector"));
+            basis = restarted;
+            h.fill(0.0);
+            for (i, &k) in order[..keep].iter().enumerate() {
+                h[(i, i)] = eigen.eigenvalues[k];
+            }
+        }
+        Err(CentralityError::NotConverged { iterations: products, residual })
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalize(x: &mut [f64]) {
+    let norm = dot(x, x).sqrt();
+    for value in x.iter_mut() {
+        *value /= norm;
+    }
+}
+
+// The nodes of every connected component by `to_index`, in node order so results do not depend on hashing. Arcs
+// of a directed graph join their endpoints as undirected edges.
+fn connected_components<G>(graph: G) -> Vec<Vec<usize>>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+{
+    let mut union_find = UnionFind::new(graph.node_bound());
+    for edge in graph.edge_references() {
+        union_find.union(graph.to_index(edge.source()), graph.to_index(edge.target()));
+    }
+    let mut component_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    for node in graph.node_identifiers() {
+        let index = graph.to_index(node);
+        let component = *component_of_root.entry(union_find.find_mut(index)).or_insert_with(|| {
+            components.push(Vec::new());
+            components.len() - 1
+        });
+        components[component].push(index);
+    }
+    components
+}
+
+// Eigenvector centrality: every node scores in proportion to the sum of its neighbors' scores, so a node is
+// central when it is connected to other central nodes. The scores are the entries of the leading eigenvector of
+// the adjacency matrix, all non-negative. It takes any petgraph graph reference, such as `&RoadGraph` or
+// `&RoadNetwork`, and returns the scores keyed by its node identifiers, the original ids on a GraphMap and node
+// indices on a Graph. Arcs of a directed graph are treated as undirected edges.
+//
+// On a disconnected graph the leading eigenvector lives on a single component, so each connected component gets
+// the leading eigenvector of its own adjacency matrix instead. It is scaled to norm `sqrt(n_c / n)` for a
+// component of `n_c` of the `n` nodes, so the whole vector has unit norm, a connected graph gets the usual unit
+// eigenvector, and the nodes of every component are comparable. Isolated nodes score 0.
+pub fn eigenvector_centrality<G>(
+    graph: G,
+    options: &EigenvectorOptions,
+) -> Result<CentralityScores<G::NodeId>, CentralityError>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+    G::NodeId: Hash + Eq,
+{
+    eigenvector_centrality_with_progress(graph, options, &NoProgress)
+}
+
+// Eigenvector centrality as above, reporting every connected component that is done to `progress`
+pub fn eigenvector_centrality_with_progress<G>(
+    graph: G,
+    options: &EigenvectorOptions,
+    progress: &dyn Progress,
+) -> Result<CentralityScores<G::NodeId>, CentralityError>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+    G::NodeId: Hash + Eq,
+{
+    let adjacency = Adjacency::new(graph);
+    let components = connected_components(graph);
+    let node_count: usize = components.iter().map(Vec::len).sum();
+
+    let mut scores = vec![0.0; graph.node_bound()];
+    let mut position = vec![usize::MAX; graph.node_bound()];
+    progress.start("Eigenvector", Some(components.len() as u64));
+    for members in &components {
+        // An isolated node has no neighbors to take a score from
+        if members.len() > 1 {
+            for (i, &node) in members.iter().enumerate() {
+                position[node] = i;
+            }
+            let component = Component { adjacency: &adjacency, members, position: &position };
+            let vector = component.leading(options)?;
+
+            // The leading eigenvector of a connected component has entries of one sign; rounding may leave tiny
+            // values of the other
+            let sign = if vector.iter().sum::<f64>() < 0.0 { -1.0 } else { 1.0 };
+            let scale = (members.len() as f64 / node_count as f64).sqrt();
+            for (&node, value) in members.iter().zip(&vector) {
+                scores[node] = (sign * value).max(0.0) * scale;
+            }
+        }
+        progress.advance(1);
+    }
+    progress.finish();
+
+    Ok(graph.node_identifiers().map(|node| (node, scores[graph.to_index(node)])).collect())
+}
+
+// Settings of katz_centrality
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KatzOptions {
+    // Weight α of every further step of a walk; it must stay below one over the spectral radius
+    pub attenuation: f64,
+    // Score β every node has of its own
+    pub base: f64,
+    // The iteration stops once the scores change by less than `tolerance` per node, summed over all nodes
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    // Scale the scores to unit Euclidean norm
+    pub normalized: bool,
+    // How the spectral radius that bounds the attenuation is found
+    pub eigen: EigenvectorOptions,
+}
+
+impl Default for KatzOptions {
+    fn default() -> Self {
+        KatzOptions {
+            attenuation: 0.1,
+            base: 1.0,
+            tolerance: 1e-6,
+            max_iterations: 1000,
+            normalized: true,
+            eigen: EigenvectorOptions { tolerance: 1e-4, ..EigenvectorOptions::default() },
+        }
+    }
+}
+
+// Largest eigenvalue of the adjacency matrix of a graph, over all its connected components, found by the
+// solvers of eigenvector_centrality and rounded up by the residual of the eigenvector estimate. The adjacency
+// matrix of a directed graph is not symmetric; for it the result is half the largest eigenvalue of `A + Aᵀ`,
+// which bounds the spectral radius from above and matches it when every arc has an opposite arc.
+pub fn spectral_radius<G>(graph: G, options: &EigenvectorOptions) -> Result<f64, CentralityError>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable + GraphProp,
+{
+    let (adjacency, share) =
+        if graph.is_directed() { (Adjacency::symmetrized(graph), 0.5) } else { (Adjacency::new(graph), 1.0) };
+    let mut position = vec![usize::MAX; graph.node_bound()];
+    let mut radius: f64 = 0.0;
+    let mut product = Vec::new();
+    for members in connected_components(graph) {
+        if members.len() > 1 {
+            for (i, &node) in members.iter().enumerate() {
+                position[node] = i;
+            }
+            let component = Component { adjacency: &adjacency, members: &members, position: &position };
+            let vector = component.leading(options)?;
+            product.resize(members.len(), 0.0);
+            let (eigenvalue, residual) = component.residual(&vector, &mut product);
+            // Some eigenvalue lies within the residual of the estimate; the estimate is of the largest
+            radius = radius.max(eigenvalue.abs() * (1.0 + residual));
+        } else if !adjacency.row(members[0]).is_empty() {
+            // A single node with self-loops
+            radius = radius.max(adjacency.row(members[0]).len() as f64);
+        }
+    }
+    Ok(radius * share)
+}
+
+// Katz centrality: every node scores the number of walks that end at it, a walk of k steps weighted by the
+// attenuation to the power k, on top of a base score of its own, `x = α Aᵀ x + β`. Unlike eigenvector
+// centrality it gives every node a positive score, also on a directed graph, where walks follow the arcs. It
+// takes any petgraph graph reference and returns the scores keyed like degree_centrality.
+//
+// The attenuation is checked against the spectral radius first, as the walks only sum to a finite score below
+// one over it; otherwise the result is a `CentralityError::InvalidAttenuation` that names the limit.
+pub fn katz_centrality<G>(graph: G, options: &KatzOptions) -> Result<CentralityScores<G::NodeId>, CentralityError>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+{
+    katz_centrality_with_progress(graph, options, &NoProgress)
+}
+
+// Katz centrality as above, reporting every iteration to `progress`
+pub fn katz_centrality_with_progress<G>(
+    graph: G,
+    options: &KatzOptions,
+    progress: &dyn Progress,
+) -> Result<CentralityScores<G::NodeId>, CentralityError>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable + GraphProp,
+    G::NodeId: Hash + Eq,
+{
+    let radius = spectral_radius(graph, &options.eigen)?;
+    let limit = if radius > 0.0 { 1.0 / radius } else { f64::INFINITY };
+    let attenuation = options.attenuation;
+    if !(attenuation > 0.0 && attenuation < limit) {
+        return Err(CentralityError::InvalidAttenuation { attenuation, limit });
+    }
+    // Without nodes there is nothing to iterate, and no change would ever fall below a tolerance of zero
+    let node_count = graph.node_identifiers().count();
+    if node_count == 0 {
+        return Ok(CentralityScores::new());
+    }
+
+    let incoming = Adjacency::incoming(graph);
+    let mut scores = vec![0.0; graph.node_bound()];
+    let mut next = vec![0.0; graph.node_bound()];
+    let mut change = f64::INFINITY;
+    let mut converged = false;
+    progress.start("Katz", None);
+    for _ in 0..options.max_iterations {
+        for (node, value) in next.iter_mut().enumerate() {
+            *value = attenuation * incoming.row(node).iter().map(|&source| scores[source]).sum::<f64>() + options.base;
+        }
+        change = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        std::mem::swap(&mut scores, &mut next);
+        progress.advance(1);
+        if change < node_count as f64 * options.tolerance {
+            converged = true;
+            break;
+        }
+    }
+    progress.finish();
+    if !converged {
+        let residual = change / node_count as f64;
+        return Err(CentralityError::NotConverged { iterations: options.max_iterations, residual });
+    }
+
+    let mut centrality: CentralityScores<G::NodeId> =
+        graph.node_identifiers().map(|node| (node, scores[graph.to_index(node)])).collect();
+    if options.normalized {
+        let norm = centrality.values().map(|score| score * score).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            centrality.values_mut().for_each(|score| *score /= norm);
+        }
+    }
+    Ok(centrality)
+}
+
+// Closeness and harmonic centrality of every node, keyed like degree_centrality, from the shortest paths of
+// `sources` source nodes
+#[derive(Debug, Clone)]
+pub struct Closeness<N> {
+    // Closeness in the Wasserman-Faust form, which stays meaningful on a disconnected graph: with `r` of the `n`
+    // nodes able to reach a node at a total distance `d`, it scores `(r - 1)² / ((n - 1) d)`, the inverse of the
+    // mean distance scaled down by the share of the graph that reaches the node. Unreached nodes score 0.
+    pub closeness: CentralityScores<N>,
+    // Harmonic centrality, the sum of the inverse distances from all other nodes; an unreachable node adds 0
+    pub harmonic: CentralityScores<N>,
+    pub node_count: usize,
+    // Number of sources searched from; every node unless sources were sampled
+    pub sources: usize,
+}
+
+impl<N> Closeness<N> {
+    // Whether every node was a source, so the scores are exact
+    pub fn is_exact(&self) -> bool {
+        self.sources >= self.node_count
+    }
+}
+
+// Closeness and harmonic centrality, with distances read from the edge payload by `edge_weight`, e.g.
+// `|edge: &RoadEdge| edge.weight`, or `|_| 1.0` to count hops. It takes any petgraph graph reference; on a
+// directed graph the distances are those of the paths that follow the arcs towards a node, so the scores say how
+// easily a node is reached.
+//
+// With `sources` only the Dijkstra searches from those nodes are run, in parallel, and every node's scores are
+// estimated from the sources other than itself (Eppstein & Wang, 2004): the harmonic sum and the reach and total
+// distance that closeness needs are scaled up from the sampled sources to all nodes. Sources drawn uniformly,
+// as by `betweenness::select_pivots`, give unbiased estimates of the harmonic centrality and consistent ones
+// of closeness. `None` searches from every node and gives the exact scores.
+pub fn closeness_centrality<G, F>(
+    graph: G,
+    edge_weight: F,
+    sources: Option<&[G::NodeId]>,
+) -> Closeness<G::NodeId>
+where
+    G: IntoEdges + IntoNodeIdentifiers + NodeIndexable + Visitable + Sync,
+    G::NodeId: Hash + Eq + Send + Sync,
+    F: Fn(&G::EdgeWeight) -> f64 + Sync,
+{
+    closeness_centrality_with_progress(graph, edge_weight, sources, &NoProgress)
+}
+
+// Closeness and harmonic centrality as above, reporting every source that is searched to `progress`
+pub fn closeness_centrality_with_progress<G, F>(
+    graph: G,
+    edge_weight: F,
+    sources: Option<&[G::NodeId]>,
+    progress: &dyn Progress,
+) -> Closeness<G::NodeId>
+where
+    G: IntoEdges + IntoNodeIdentifiers + NodeIndexable + Visitable + Sync,
+    G::NodeId: Hash + Eq + Send + Sync,
+    F: Fn(&G::EdgeWeight) -> f64 + Sync,
+{
+    // A source given twice is searched once
+    let mut source_set = HashSet::new();
+    let sources: Vec<G::NodeId> = match sources {
+        Some(sources) => sources.iter().copied().filter(|&source| source_set.insert(source)).collect(),
+        None => graph.node_identifiers().filter(|&source| source_set.insert(source)).collect(),
+    };
+    let node_count = graph.node_identifiers().count();
+
+    // Per node: how many sources reach it, their total distance to it, and the sum of the inverse distances
+    let bound = graph.node_bound();
+    progress.start("Closeness", Some(sources.len() as u64));
+    let (reached, distances, inverses) = sources
+        .par_iter()
+        .fold(
+            || (vec![0usize; bound], vec![0.0; bound], vec![0.0; bound]),
+            |(mut reached, mut distances, mut inverses), &source| {
+                for (node, distance) in dijkstra(graph, source, None, |edge| edge_weight(edge.weight())) {
+                    if node != source {
+                        let index = graph.to_index(node);
+                        reached[index] += 1;
+                        distances[index] += distance;
+                        if distance > 0.0 {
+                            inverses[index] += 1.0 / distance;
+                        }
+                    }
+                }
+                progress.advance(1);
+                (reached, distances, inverses)
+            },
+        )
+        .reduce(
+            || (vec![0usize; bound], vec![0.0; bound], vec![0.0; bound]),
+            |(mut reached, mut distances, mut inverses), (other_reached, other_distances, other_inverses)| {
+                reached.iter_mut().zip(other_reached).for_each(|(value, other)| *value += other);
+                distances.iter_mut().zip(other_distances).for_each(|(value, other)| *value += other);
+                inverses.iter_mut().zip(other_inverses).for_each(|(value, other)| *value += other);
+                (reached, distances, inverses)
+            },
+        );
+    progress.finish();
+
+    // A node is not its own source, so its sums cover the sources other than itself
+    let mut closeness = HashMap::new();
+    let mut harmonic = HashMap::new();
+    for node in graph.node_identifiers() {
+        let index = graph.to_index(node);
+        let other_sources = (source_set.len() - usize::from(source_set.contains(&node))) as f64;
+        let (reached, distance) = (reached[index] as f64, distances[index]);
+        // Scaled up to all nodes, the reach and total distance are (n - 1) / k times the sampled ones, which
+        // cancels down to reached² / (k d)
+        let score = if distance > 0.0 { reached * reached / (other_sources * distance) } else { 0.0 };
+        closeness.insert(node, score);
+        let score = if other_sources > 0.0 { inverses[index] * (node_count as f64 - 1.0) / other_sources } else { 0.0 };
+        harmonic.insert(node, score);
+    }
+
+    Closeness { closeness, harmonic, node_count, sources: sources.len() }
+}
 
 // This test function checks if the degree_centrality function works correctly
 // by creating a simple graph and asserting that the calculated degree centrality
@@ -39,19 +751,302 @@ use rand::Rng;
 
 #[test]
 fn test_degree_centrality() {
+    use crate::data::RoadNetwork;
+
     // Create a simple graph with 4 nodes and 4 edges
-    let mut graph = UnGraphMap::new();
-    graph.add_edge(0, 1, 1.0);
-    graph.add_edge(0, 2, 1.0);
-    graph.add_edge(1, 2, 1.0);
-    graph.add_edge(1, 3, 1.0);
+    let graph = RoadNetwork::from_edges([(10, 11), (10, 12), (11, 12), (11, 13)]);
 
     // Calculate the degree centrality of the graph
     let centrality = degree_centrality(&graph);
 
     // Check if the calculated degree centrality values match the expected values
-    assert_eq!(centrality[&0], 2.0);
-    assert_eq!(centrality[&1], 3.0);
-    assert_eq!(centrality[&2], 2.0);
-    assert_eq!(centrality[&3], 1.0);
+    assert_eq!(centrality[&10], 2.0);
+    assert_eq!(centrality[&11], 3.0);
+    assert_eq!(centrality[&12], 2.0);
+    assert_eq!(centrality[&13], 1.0);
+}
+
+#[test]
+fn test_degree_centrality_on_road_graph() {
+    use crate::data::{build_road_graph, NodeCoordinates, RoadNetwork};
+    use petgraph::graph::NodeIndex;
+
+    // The same function runs on the road graph, keyed by node index
+    let network = RoadNetwork::from_edges([(10, 11), (11, 12)]);
+    let (graph, _) = build_road_graph(network, &NodeCoordinates::new());
+    let centrality = degree_centrality(&graph);
+
+    assert_eq!(centrality[&NodeIndex::new(1)], 2.0);
+    assert_eq!(graph[NodeIndex::new(1)].id, 11);
+}
+
+#[test]
+fn test_degree_centrality_variants() {
+    use crate::data::{RoadEdge, RoadNetwork};
+
+    let mut graph = RoadNetwork::new();
+    graph.add_edge(10, 11, RoadEdge::from_weight(2.0));
+    graph.add_edge(10, 12, RoadEdge::from_weight(0.5));
+    graph.add_edge(11, 12, RoadEdge::from_weight(1.0));
+    graph.add_edge(11, 13, RoadEdge::from_weight(4.0));
+
+    // Normalized by the three other nodes each could be adjacent to
+    let normalized = normalized_degree_centrality(&graph);
+    assert_eq!(normalized[&11], 1.0);
+    assert!((normalized[&13] - 1.0 / 3.0).abs() < 1e-12);
+
+    let strength = weighted_degree_centrality(&graph, |edge: &RoadEdge| edge.weight);
+    assert_eq!(strength[&10], 2.5);
+    assert_eq!(strength[&11], 7.0);
+    assert_eq!(strength[&13], 4.0);
+
+    // On an undirected graph both directions give the degree
+    assert_eq!(in_degree_centrality(&graph), degree_centrality(&graph));
+    assert_eq!(out_degree_centrality(&graph), degree_centrality(&graph));
+}
+
+#[test]
+fn test_directed_degree_centrality() {
+    // 0 points at 1 and 2, 1 at 2
+    let graph = petgraph::graphmap::DiGraphMap::<u32, ()>::from_edges([(0, 1), (0, 2), (1, 2)]);
+
+    let incoming = in_degree_centrality(&graph);
+    let outgoing = out_degree_centrality(&graph);
+    assert_eq!((incoming[&0], incoming[&1], incoming[&2]), (0.0, 1.0, 2.0));
+    assert_eq!((outgoing[&0], outgoing[&1], outgoing[&2]), (2.0, 1.0, 0.0));
+    assert_eq!(normalize_by_node_count(incoming)[&2], 1.0);
+}
+
+#[test]
+fn test_spectral_radius() {
+    use crate::data::RoadNetwork;
+
+    // A star with k leaves has spectral radius sqrt(k); the estimate may only round up
+    let star = RoadNetwork::from_edges([(0, 1), (0, 2), (0, 3), (0, 4), (5, 6)]);
+    let radius = spectral_radius(&star, &EigenvectorOptions::default()).unwrap();
+    assert!((2.0..2.0 + 1e-4).contains(&radius));
+
+    // A directed cycle has spectral radius 1, as has its symmetrization halved
+    let cycle = petgraph::graphmap::DiGraphMap::<u32, ()>::from_edges([(0, 1), (1, 2), (2, 0)]);
+    let radius = spectral_radius(&cycle, &EigenvectorOptions::default()).unwrap();
+    assert!((1.0..1.0 + 1e-4).contains(&radius));
+}
+
+#[test]
+fn test_katz_centrality_of_a_path() {
+    use crate::data::RoadNetwork;
+
+    // On a path of three nodes x = α A x + β solves to x_end = β (1 + α) / (1 - 2α²) and
+    // x_middle = β (1 + 2α) / (1 - 2α²)
+    let graph = RoadNetwork::from_edges([(0, 1), (1, 2)]);
+    let options = KatzOptions { attenuation: 0.3, tolerance: 1e-12, normalized: false, ..KatzOptions::default() };
+    let centrality = katz_centrality(&graph, &options).unwrap();
+    let scale = 1.0 / (1.0 - 2.0 * 0.3 * 0.3);
+    assert!((centrality[&0] - 1.3 * scale).abs() < 1e-9);
+    assert!((centrality[&1] - 1.6 * scale).abs() < 1e-9);
+    assert!((centrality[&2] - 1.3 * scale).abs() < 1e-9);
+
+    // Normalized, the scores have unit norm
+    let centrality = katz_centrality(&graph, &KatzOptions { attenuation: 0.3, ..KatzOptions::default() }).unwrap();
+    assert!((centrality.values().map(|score| score * score).sum::<f64>() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_katz_centrality_rejects_large_attenuation() {
+    use crate::data::RoadNetwork;
+
+    // The spectral radius of the path is sqrt(2), so the attenuation must stay below 1 / sqrt(2)
+    let graph = RoadNetwork::from_edges([(0, 1), (1, 2)]);
+    for attenuation in [0.75, 0.0, -0.1, f64::NAN] {
+        let options = KatzOptions { attenuation, ..KatzOptions::default() };
+        match katz_centrality(&graph, &options) {
+            Err(CentralityError::InvalidAttenuation { limit, .. }) => {
+                assert!((limit - 0.5_f64.sqrt()).abs() < 1e-3)
+            }
+            other => panic!("attenuation {} gave {:?}", attenuation, other),
+        }
+    }
+}
+
+#[test]
+fn test_katz_centrality_of_empty_graph() {
+    use crate::data::RoadNetwork;
+
+    let graph = RoadNetwork::new();
+    assert_eq!(katz_centrality(&graph, &KatzOptions::default()), Ok(CentralityScores::new()));
+}
+
+#[test]
+fn test_katz_centrality_follows_arcs() {
+    use crate::progress::tests::CountingProgress;
+
+    // Walks run 0 -> 1 -> 2, so 2 ends the most of them and 0 none but its own
+    let graph = petgraph::graphmap::DiGraphMap::<u32, ()>::from_edges([(0, 1), (1, 2)]);
+    let options = KatzOptions { attenuation: 0.5, tolerance: 1e-12, normalized: false, ..KatzOptions::default() };
+    let progress = CountingProgress::default();
+    let centrality = katz_centrality_with_progress(&graph, &options, &progress).unwrap();
+
+    assert!((centrality[&0] - 1.0).abs() < 1e-9);
+    assert!((centrality[&1] - 1.5).abs() < 1e-9);
+    assert!((centrality[&2] - 1.75).abs() < 1e-9);
+    assert_eq!(progress.tasks(), vec![("Katz".to_string(), None)]);
+    assert!(progress.steps() > 0);
+}
+
+#[cfg(test)]
+fn assert_unit_eigenvector(scores: &[f64], expected: &[f64]) {
+    let norm = expected.iter().map(|value| value * value).sum::<f64>().sqrt();
+    for (score, value) in scores.iter().zip(expected) {
+        assert!((score - value / norm).abs() < 1e-5, "{:?} is not {:?}", scores, expected);
+    }
+}
+
+#[test]
+fn test_eigenvector_centrality_of_a_star() {
+    use crate::data::RoadNetwork;
+
+    // The leading eigenvalue of a star with k leaves is sqrt(k), with the center at sqrt(k) times each leaf
+    let graph = RoadNetwork::from_edges([(0, 1), (0, 2), (0, 3), (0, 4)]);
+    for solver in [EigenSolver::Dense, EigenSolver::Power, EigenSolver::Lanczos] {
+        let options = EigenvectorOptions { solver, tolerance: 1e-9, ..EigenvectorOptions::default() };
+        let centrality = eigenvector_centrality(&graph, &options).unwrap();
+        let scores: Vec<f64> = (0..5).map(|node| centrality[&node]).collect();
+        assert_unit_eigenvector(&scores, &[2.0, 1.0, 1.0, 1.0, 1.0]);
+    }
+}
+
+#[test]
+fn test_eigenvector_centrality_solvers_agree_on_a_grid() {
+    use crate::data::tests::grid;
+
+    // A bipartite grid, on which plain power iteration would oscillate between two vectors
+    let size = 12;
+    let graph = grid(size);
+
+    let solve = |solver| {
+        let options = EigenvectorOptions { solver, tolerance: 1e-8, max_iterations: 100_000, ..EigenvectorOptions::default() };
+        eigenvector_centrality(&graph, &options).unwrap()
+    };
+    let dense = solve(EigenSolver::Dense);
+    for solver in [EigenSolver::Power, EigenSolver::Lanczos] {
+        let iterative = solve(solver);
+        assert!(dense.iter().all(|(node, score)| (score - iterative[node]).abs() < 1e-5));
+    }
+    // The middle of the grid is the most central, the corners the least
+    let middle = (size / 2) * size + size / 2;
+    assert!(dense[&middle] > dense[&0] * 10.0);
+}
+
+#[test]
+fn test_eigenvector_centrality_of_disconnected_graph() {
+    use crate::data::RoadNetwork;
+
+    // A triangle, a single edge and an isolated node
+    let mut graph = RoadNetwork::from_edges([(0, 1), (1, 2), (2, 0), (3, 4)]);
+    graph.add_node(5);
+    let centrality = eigenvector_centrality(&graph, &EigenvectorOptions::default()).unwrap();
+
+    // Each component is scaled to norm sqrt(n_c / n), so within a component the nodes share the score
+    let triangle = (3.0_f64 / 6.0).sqrt() / 3.0_f64.sqrt();
+    let pair = (2.0_f64 / 6.0).sqrt() / 2.0_f64.sqrt();
+    for node in 0..3 {
+        assert!((centrality[&node] - triangle).abs() < 1e-9);
+    }
+    assert!((centrality[&3] - pair).abs() < 1e-9 && (centrality[&4] - pair).abs() < 1e-9);
+    assert_eq!(centrality[&5], 0.0);
+    assert!((centrality.values().map(|score| score * score).sum::<f64>() - 5.0 / 6.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_eigenvector_centrality_reports_non_convergence() {
+    use crate::data::RoadNetwork;
+
+    let graph = RoadNetwork::from_edges([(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]);
+    let options = EigenvectorOptions { solver: EigenSolver::Power, max_iterations: 2, ..EigenvectorOptions::default() };
+    assert!(matches!(
+        eigenvector_centrality(&graph, &options),
+        Err(CentralityError::NotConverged { iterations: 2, .. })
+    ));
+}
+
+
+#[test]
+fn test_closeness_centrality_of_a_path() {
+    use crate::data::{RoadEdge, RoadNetwork};
+
+    let graph = RoadNetwork::from_edges([(0, 1), (1, 2), (2, 3), (3, 4)]);
+    let centrality = closeness_centrality(&graph, |_: &RoadEdge| 1.0, None);
+
+    // The middle is at distances 1, 1, 2 and 2 from the others
+    assert!(centrality.is_exact());
+    assert!((centrality.closeness[&2] - 4.0 / 6.0).abs() < 1e-12);
+    assert!((centrality.closeness[&0] - 4.0 / 10.0).abs() < 1e-12);
+    assert!((centrality.harmonic[&2] - 3.0).abs() < 1e-12);
+    assert!((centrality.harmonic[&0] - (1.0 + 1.0 / 2.0 + 1.0 / 3.0 + 1.0 / 4.0)).abs() < 1e-12);
+}
+
+#[test]
+fn test_closeness_centrality_of_disconnected_graph() {
+    use crate::data::{RoadEdge, RoadNetwork};
+
+    // A triangle of long edges, a single short edge and an isolated node
+    let mut graph = RoadNetwork::new();
+    graph.add_edge(0, 1, RoadEdge::from_weight(1.5));
+    graph.add_edge(1, 2, RoadEdge::from_weight(1.5));
+    graph.add_edge(2, 0, RoadEdge::from_weight(1.5));
+    graph.add_edge(3, 4, RoadEdge::from_weight(1.0));
+    graph.add_node(5);
+    let centrality = closeness_centrality(&graph, |edge: &RoadEdge| edge.weight, None);
+
+    // The triangle reaches more of the graph, which outweighs its longer distances in closeness
+    assert!((centrality.closeness[&0] - 2.0 * 2.0 / (5.0 * 3.0)).abs() < 1e-12);
+    assert!((centrality.closeness[&3] - 1.0 / 5.0).abs() < 1e-12);
+    assert!(centrality.closeness[&0] > centrality.closeness[&3]);
+    assert_eq!(centrality.closeness[&5], 0.0);
+    assert!((centrality.harmonic[&0] - 2.0 / 1.5).abs() < 1e-12);
+    assert!((centrality.harmonic[&3] - 1.0).abs() < 1e-12);
+    assert_eq!(centrality.harmonic[&5], 0.0);
+}
+
+#[test]
+fn test_closeness_centrality_follows_arcs() {
+    // Only 0 reaches 1, and both reach 2
+    let graph = petgraph::graphmap::DiGraphMap::<u32, f64>::from_edges([(0, 1, 1.0), (1, 2, 1.0)]);
+    let centrality = closeness_centrality(&graph, |weight: &f64| *weight, None);
+
+    assert_eq!(centrality.closeness[&0], 0.0);
+    assert!((centrality.closeness[&1] - 1.0 / 2.0).abs() < 1e-12);
+    assert!((centrality.closeness[&2] - 4.0 / 6.0).abs() < 1e-12);
+    assert!((centrality.harmonic[&2] - 1.5).abs() < 1e-12);
+}
+
+#[test]
+fn test_sampled_closeness_centrality() {
+    use crate::betweenness::select_pivots;
+    use crate::data::tests::grid;
+    use crate::data::RoadEdge;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let graph = grid(10);
+    let exact = closeness_centrality(&graph, |edge: &RoadEdge| edge.weight, None);
+
+    // Every node as a source, each given twice, is exact
+    let all: Vec<usize> = graph.nodes().chain(graph.nodes()).collect();
+    let everything = closeness_centrality(&graph, |edge: &RoadEdge| edge.weight, Some(&all));
+    assert_eq!(everything.sources, 100);
+    assert!(exact.closeness.iter().all(|(node, score)| (everything.closeness[node] - score).abs() < 1e-12));
+
+    // Half of the nodes as sources estimate every score closely; the harmonic sum varies more, as the few
+    // sampled neighbors of a node make up much of it
+    let sources = select_pivots(&graph, 50, &mut StdRng::seed_from_u64(42));
+    let progress = crate::progress::tests::CountingProgress::default();
+    let sampled = closeness_centrality_with_progress(&graph, |edge: &RoadEdge| edge.weight, Some(&sources), &progress);
+    assert_eq!(progress.tasks(), vec![("Closeness".to_string(), Some(50))]);
+    assert_eq!(progress.steps(), 50);
+    assert!(!sampled.is_exact());
+    for node in graph.nodes() {
+        assert!((sampled.closeness[&node] / exact.closeness[&node] - 1.0).abs() < 0.1, "closeness of {}", node);
+        assert!((sampled.harmonic[&node] / exact.harmonic[&node] - 1.0).abs() < 0.2, "harmonic of {}", node);
+    }
 }
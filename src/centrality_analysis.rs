@@ -1,15 +1,426 @@
 // centrality_analysis.rs
 
-// Import the necessary modules and functions
-use petgraph::prelude::UnGraphMap;
-use crate::centrality::{degree_centrality};
-use std::collections::HashMap;
+// Comparison of centrality measures. `analyze_centrality` runs several measures on one graph and collects their
+// scores into a `CentralityTable`, one row per node and one column per measure. `compare_centrality` then
+// measures how far the measures agree: the Spearman and Kendall rank correlations and the overlap of the top
+// nodes of every pair of measures, and the nodes that rank high on one measure but low on all the others, such
+// as a quiet intersection on the one street between two towns.
 
-// This function takes a reference to a petgraph UnGraphMap object and
-// calculates its degree centrality using the degree_centrality function.
-pub fn analyze_centrality(graph: &UnGraphMap<usize, f64>) -> HashMap<usize, f64> {
-    let degree = degree_centrality(&graph);
-    degree
+use crate::betweenness::betweenness_centrality_with_progress;
+use crate::centrality::{
+    closeness_centrality_with_progress, degree_centrality, eigenvector_centrality_with_progress,
+    katz_centrality_with_progress, CentralityError, CentralityScores, EigenvectorOptions, KatzOptions,
+};
+use crate::pagerank::pagerank_with_progress;
+use crate::progress::{NoProgress, Progress};
+use petgraph::visit::{
+    GraphProp, IntoEdges, IntoNeighborsDirected, IntoNodeIdentifiers, NodeCount, NodeIndexable, Visitable,
+};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+
+// The centrality measures analyze_centrality can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Measure {
+    Degree,
+    Eigenvector,
+    Katz,
+    // Betweenness in hops, normalized by the number of node pairs
+    Betweenness,
+    // Closeness and harmonic centrality in hops
+    Closeness,
+    Harmonic,
+    Pagerank,
+}
+
+impl Measure {
+    pub const ALL: [Measure; 7] = [
+        Measure::Degree,
+        Measure::Eigenvector,
+        Measure::Katz,
+        Measure::Betweenness,
+        Measure::Closeness,
+        Measure::Harmonic,
+        Measure::Pagerank,
+    ];
+
+    // Name of the measure, as on the command line and in the report
+    pub fn name(self) -> &'static str {
+        match self {
+            Measure::Degree => "degree",
+            Measure::Eigenvector => "eigenvector",
+            Measure::Katz => "katz",
+            Measure::Betweenness => "betweenness",
+            Measure::Closeness => "closeness",
+            Measure::Harmonic => "harmonic",
+            Measure::Pagerank => "pagerank",
+        }
+    }
+}
+
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Settings of analyze_centrality
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentralityOptions {
+    // The measures to run, in the order of the columns of the table
+    pub measures: Vec<Measure>,
+    pub eigenvector: EigenvectorOptions,
+    pub katz: KatzOptions,
+    pub damping: f64,
+    pub pagerank_iterations: usize,
+}
+
+impl Default for CentralityOptions {
+    fn default() -> Self {
+        CentralityOptions {
+            measures: Measure::ALL.to_vec(),
+            eigenvector: EigenvectorOptions::default(),
+            katz: KatzOptions::default(),
+            damping: 0.85,
+            pagerank_iterations: 100,
+        }
+    }
+}
+
+// Scores of every node under several measures: `scores[m][row]` is the score of `nodes[row]` under `measures[m]`.
+// It serializes row by row, as an array of `{ node, <measure>: score, ... }` objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentralityTable<N> {
+    pub nodes: Vec<N>,
+    pub measures: Vec<Measure>,
+    pub scores: Vec<Vec<f64>>,
+}
+
+impl<N: Copy + Hash + Eq> CentralityTable<N> {
+    // A table of the given nodes without any measures yet
+    pub fn new(nodes: Vec<N>) -> Self {
+        CentralityTable { nodes, measures: Vec::new(), scores: Vec::new() }
+    }
+
+    // Add the column of a measure; nodes without a score score 0
+    pub fn insert(&mut self, measure: Measure, scores: &CentralityScores<N>) {
+        let column = self.nodes.iter().map(|node| scores.get(node).copied().unwrap_or(0.0)).collect();
+        self.measures.push(measure);
+        self.scores.push(column);
+    }
+}
+
+impl<N> CentralityTable<N> {
+    // Scores of every node under `measure`, in row order
+    pub fn column(&self, measure: Measure) -> Option<&[f64]> {
+        let position = self.measures.iter().position(|&column| column == measure)?;
+        Some(&self.scores[position])
+    }
+
+    // The same table with every node translated by `f`, e.g. from node indices to dataset ids
+    pub fn map_nodes<M>(self, f: impl FnMut(N) -> M) -> CentralityTable<M> {
+        CentralityTable { nodes: self.nodes.into_iter().map(f).collect(), measures: self.measures, scores: self.scores }
+    }
+}
+
+impl<N: Serialize> Serialize for CentralityTable<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Row<'a, N> {
+            table: &'a CentralityTable<N>,
+            row: usize,
+        }
+
+        impl<N: Serialize> Serialize for Row<'_, N> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(self.table.measures.len() + 1))?;
+                map.serialize_entry("node", &self.table.nodes[self.row])?;
+                for (measure, column) in self.table.measures.iter().zip(&self.table.scores) {
+                    map.serialize_entry(measure.name(), &column[self.row])?;
+                }
+                map.end()
+            }
+        }
+
+        let mut rows = serializer.serialize_seq(Some(self.nodes.len()))?;
+        for row in 0..self.nodes.len() {
+            rows.serialize_element(&Row { table: self, row })?;
+        }
+        rows.end()
+    }
+}
+
+// Run `options.measures` on any petgraph graph reference and collect their scores into a table. Distances are
+// counted in hops, and on a directed graph they follow the arcs, as do Katz centrality and PageRank. With
+// `sources`, betweenness and closeness are estimated from the searches from those nodes only, as by
+// `betweenness::select_pivots`; `None` gives their exact scores. Fails when eigenvector or Katz centrality do.
+pub fn analyze_centrality<G>(
+    graph: G,
+    options: &CentralityOptions,
+    sources: Option<&[G::NodeId]>,
+) -> Result<CentralityTable<G::NodeId>, CentralityError>
+where
+    G: IntoEdges + IntoNeighborsDirected + IntoNodeIdentifiers + NodeIndexable + NodeCount + GraphProp + Visitable,
+    G: Sync,
+    G::NodeId: Hash + Eq + Send + Sync,
+    G::EdgeId: Hash + Eq,
+{
+    analyze_centrality_with_progress(graph, options, sources, &NoProgress)
+}
+
+// The centrality analysis as above, with every measure reporting to `progress`
+pub fn analyze_centrality_with_progress<G>(
+    graph: G,
+    options: &CentralityOptions,
+    sources: Option<&[G::NodeId]>,
+    progress: &dyn Progress,
+) -> Result<CentralityTable<G::NodeId>, CentralityError>
+where
+    G: IntoEdges + IntoNeighborsDirected + IntoNodeIdentifiers + NodeIndexable + NodeCount + GraphProp + Visitable,
+    G: Sync,
+    G::NodeId: Hash + Eq + Send + Sync,
+    G::EdgeId: Hash + Eq,
+{
+    let mut table = CentralityTable::new(graph.node_identifiers().collect());
+    // Closeness and harmonic centrality come out of the same searches
+    let mut closeness = None;
+    for &measure in &options.measures {
+        let scores = match measure {
+            Measure::Degree => degree_centrality(graph),
+            Measure::Eigenvector => eigenvector_centrality_with_progress(graph, &options.eigenvector, progress)?,
+            Measure::Katz => katz_centrality_with_progress(graph, &options.katz, progress)?,
+            Measure::Betweenness => betweenness_centrality_with_progress(graph, sources, true, progress).nodes,
+            Measure::Closeness | Measure::Harmonic => {
+                let closeness = closeness
+                    .get_or_insert_with(|| closeness_centrality_with_progress(graph, |_| 1.0, sources, progress));
+                if measure == Measure::Closeness {
+                    closeness.closeness.clone()
+                } else {
+                    closeness.harmonic.clone()
+                }
+            }
+            Measure::Pagerank => {
+                pagerank_with_progress(graph, options.damping, options.pagerank_iterations, progress)
+                    .into_iter()
+                    .collect()
+            }
+        };
+        table.insert(measure, &scores);
+    }
+    Ok(table)
+}
+
+// Settings of compare_centrality
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComparisonOptions {
+    // Number of top nodes of every measure whose overlap is measured
+    pub top_k: usize,
+    // A node is flagged when its percentile rank on one measure is at least `high_percentile` and on every other
+    // measure at most `low_percentile`
+    pub high_percentile: f64,
+    pub low_percentile: f64,
+}
+
+impl Default for ComparisonOptions {
+    fn default() -> Self {
+        ComparisonOptions { top_k: 100, high_percentile: 0.99, low_percentile: 0.5 }
+    }
+}
+
+// Agreement of two measures. The correlations are None when either measure gives every node the same score.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeasurePair {
+    pub first: Measure,
+    pub second: Measure,
+    // Spearman's rho, the correlation of the ranks, ties sharing their average rank
+    pub spearman: Option<f64>,
+    // Kendall's tau-b, the balance of concordant and discordant node pairs, corrected for ties
+    pub kendall: Option<f64>,
+    // Jaccard index of the top-k nodes of both measures, ties going to the earlier row
+    pub top_k_jaccard: f64,
+}
+
+// A node that ranks high on `measure` only. Percentile ranks run from 0 for the lowest score to 1 for the
+// highest, tied nodes sharing the average.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RankOutlier<N> {
+    pub node: N,
+    pub measure: Measure,
+    pub percentile: f64,
+    // Highest percentile rank of the node on any other measure
+    pub highest_other: f64,
+}
+
+// Result of compare_centrality
+#[derive(Debug, Clone, PartialEq)]
+pub struct CentralityComparison<N> {
+    // Every pair of measures, in table order
+    pub pairs: Vec<MeasurePair>,
+    // Nodes ranking high on one measure only, the widest gap between that measure and the others first
+    pub outliers: Vec<RankOutlier<N>>,
+}
+
+// Compare every pair of measures of a table, and flag the nodes that rank high on one measure but low on all the
+// others. The correlations take O(n log n) time per pair, so they run on the full networks.
+pub fn compare_centrality<N: Copy>(table: &CentralityTable<N>, options: &ComparisonOptions) -> CentralityComparison<N> {
+    let ranks: Vec<Vec<f64>> = table.scores.iter().map(|column| average_ranks(column)).collect();
+    let tops: Vec<HashSet<usize>> = table.scores.iter().map(|column| top_rows(column, options.top_k)).collect();
+
+    let mut pairs = Vec::new();
+    for first in 0..table.measures.len() {
+        for second in first + 1..table.measures.len() {
+            let shared = tops[first].intersection(&tops[second]).count();
+            let union = tops[first].len() + tops[second].len() - shared;
+            pairs.push(MeasurePair {
+                first: table.measures[first],
+                second: table.measures[second],
+                spearman: pearson(&ranks[first], &ranks[second]),
+                kendall: kendall_tau(&table.scores[first], &table.scores[second]),
+                top_k_jaccard: if union > 0 { shared as f64 / union as f64 } else { 1.0 },
+            });
+        }
+    }
+
+    // Percentile ranks, so measures with different numbers of ties compare
+    let last = table.nodes.len().saturating_sub(1).max(1) as f64;
+    let percentiles: Vec<Vec<f64>> =
+        ranks.iter().map(|column| column.iter().map(|rank| (rank - 1.0) / last).collect()).collect();
+    let mut outliers = Vec::new();
+    if table.measures.len() > 1 {
+        for (row, &node) in table.nodes.iter().enumerate() {
+            for (measure, column) in percentiles.iter().enumerate() {
+                let percentile = column[row];
+                if percentile < options.high_percentile {
+                    continue;
+                }
+                let highest_other = (0..percentiles.len())
+                    .filter(|&other| other != measure)
+                    .map(|other| percentiles[other][row])
+                    .fold(0.0, f64::max);
+                if highest_other <= options.low_percentile {
+                    outliers.push(RankOutlier { node, measure: table.measures[measure], percentile, highest_other });
+                }
+            }
+        }
+    }
+    // The sort is stable, so equal gaps keep the row order
+    outliers.sort_by(|a, b| (b.percentile - b.highest_other).total_cmp(&(a.percentile - a.highest_other)));
+
+    CentralityComparison { pairs, outliers }
+}
+
+// Rank of every score from 1 for the lowest, tied scores sharing the average of their ranks
+fn average_ranks(scores: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+    let mut ranks = vec![0.0; scores.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && scores[order[end]] == scores[order[start]] {
+            end += 1;
+        }
+        // Ranks start + 1 to end share their mean
+        let rank = (start + 1 + end) as f64 / 2.0;
+        for &row in &order[start..end] {
+            ranks[row] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+// Rows of the `k` highest scores, ties going to the earlier row
+fn top_rows(scores: &[f64], k: usize) -> HashSet<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+    order.into_iter().take(k).collect()
+}
+
+// Pearson correlation, None when either side is constant
+fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        covariance += (a - mean_x) * (b - mean_y);
+        variance_x += (a - mean_x) * (a - mean_x);
+        variance_y += (b - mean_y) * (b - mean_y);
+    }
+    (variance_x > 0.0 && variance_y > 0.0).then(|| covariance / (variance_x * variance_y).sqrt())
+}
+
+// Kendall's tau-b in O(n log n) (Knight, 1966): sorted by x, the discordant pairs are the inversions of y, which
+// a merge sort counts. None when either side is constant.
+fn kendall_tau(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len() as u64;
+    let mut order: Vec<usize> = (0..x.len()).collect();
+    order.sort_by(|&a, &b| x[a].total_cmp(&x[b]).then(y[a].total_cmp(&y[b])));
+
+    // Pairs tied in x, and tied in both
+    let tied_x = tied_pairs(&order, |a, b| x[a] == x[b]);
+    let tied_both = tied_pairs(&order, |a, b| x[a] == x[b] && y[a] == y[b]);
+    // Sorting by y counts the pairs in opposite order; pairs tied in x are in y order already
+    let discordant = merge_sort_inversions(&mut order, y);
+    let tied_y = tied_pairs(&order, |a, b| y[a] == y[b]);
+
+    let pairs = n * n.saturating_sub(1) / 2;
+    let (untied_x, untied_y) = (pairs - tied_x, pairs - tied_y);
+    if untied_x == 0 || untied_y == 0 {
+        return None;
+    }
+    let balance = pairs as f64 - tied_x as f64 - tied_y as f64 + tied_both as f64 - 2.0 * discordant as f64;
+    Some(balance / (untied_x as f64 * untied_y as f64).sqrt())
+}
+
+// Number of pairs within the runs of `order` whose neighbors are `tied`
+fn tied_pairs(order: &[usize], tied: impl Fn(usize, usize) -> bool) -> u64 {
+    let mut pairs = 0;
+    let mut run = 1u64;
+    for window in order.windows(2) {
+        if tied(window[0], window[1]) {
+            run += 1;
+        } else {
+            pairs += run * (run - 1) / 2;
+            run = 1;
+        }
+    }
+    pairs + run * (run - 1) / 2
+}
+
+// Sort `order` by `keys` and return the number of pairs it had in strictly decreasing key order
+fn merge_sort_inversions(order: &mut [usize], keys: &[f64]) -> u64 {
+    let mut buffer = order.to_vec();
+    let mut inversions = 0;
+    let mut width = 1;
+    while width < order.len() {
+        for start in (0..order.len()).step_by(2 * width) {
+            let middle = (start + width).min(order.len());
+            let end = (start + 2 * width).min(order.len());
+            let (mut left, mut right) = (start, middle);
+            for slot in &mut buffer[start..end] {
+                let take_left = right == end
+                    || (left < middle && keys[order[left]].total_cmp(&keys[order[right]]) != Ordering::Greater);
+                if take_left {
+                    *slot = order[left];
+                    left += 1;
+                } else {
+                    // Every left element still waiting is larger
+                    inversions += (middle - left) as u64;
+                    *slot = order[right];
+                    right += 1;
+                }
+            }
+        }
+        order.copy_from_slice(&buffer);
+        width *= 2;
+    }
+    inversions
 }
 
 
@@ -18,27 +429,111 @@ pub fn analyze_centrality(graph: &UnGraphMap<usize, f64>) -> HashMap<usize, f64>
 mod tests {
     // Import the necessary modules and functions
     use super::*;
+    use crate::data::RoadNetwork;
 
     // This helper function creates a simple test graph
-    fn create_test_graph() -> UnGraphMap<usize, f64> {
-        let mut graph = UnGraphMap::<usize, f64>::new();
-        graph.add_edge(0, 1, 1.0);
-        graph.add_edge(1, 2, 1.0);
-        graph.add_edge(1, 3, 1.0);
-        graph
+    fn create_test_graph() -> RoadNetwork {
+        RoadNetwork::from_edges([(0, 1), (1, 2), (1, 3)])
+    }
+
+    // Kendall's tau-b by comparing every pair, as the reference for the merge sort
+    fn kendall_by_pairs(x: &[f64], y: &[f64]) -> f64 {
+        let (mut balance, mut untied_x, mut untied_y) = (0.0, 0.0, 0.0);
+        for i in 0..x.len() {
+            for j in i + 1..x.len() {
+                let (dx, dy) = (x[i] - x[j], y[i] - y[j]);
+                balance += (dx * dy).signum() * ((dx * dy) != 0.0) as u8 as f64;
+                untied_x += (dx != 0.0) as u8 as f64;
+                untied_y += (dy != 0.0) as u8 as f64;
+            }
+        }
+        balance / (untied_x * untied_y).sqrt()
     }
 
-    // This test function checks if the analyze_centrality function works correctly
-    // by creating a simple graph and asserting that the calculated degree centrality
-    // for each node matches the expected values.
+    // This test function checks that analyze_centrality runs every measure on the graph and collects the scores
+    // by node, with the degree of each node matching the expected values.
     #[test]
     fn test_analyze_centrality() {
         let graph = create_test_graph();
-        let degree = degree_centrality(&graph);
+        let table = analyze_centrality(&graph, &CentralityOptions::default(), None).unwrap();
+
+        assert_eq!(table.measures, Measure::ALL.to_vec());
+        let row = |node| table.nodes.iter().position(|&other| other == node).unwrap();
+        let degree = table.column(Measure::Degree).unwrap();
+        assert_eq!([degree[row(0)], degree[row(1)], degree[row(2)], degree[row(3)]], [1.0, 3.0, 1.0, 1.0]);
+        // On a star the center leads every measure
+        for column in &table.scores {
+            assert!(table.nodes.iter().all(|&node| node == 1 || column[row(1)] > column[row(node)]));
+        }
+        // Only the center lies between other nodes
+        assert_eq!(table.column(Measure::Betweenness).unwrap()[row(1)], 1.0);
+    }
+
+    #[test]
+    fn test_centrality_table_serializes_by_row() {
+        let mut table = CentralityTable::new(vec![7, 9]);
+        table.insert(Measure::Degree, &CentralityScores::from([(7, 2.0), (9, 1.0)]));
+        table.insert(Measure::Katz, &CentralityScores::from([(9, 0.5)]));
+
+        let json = serde_json::to_value(&table).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([{ "node": 7, "degree": 2.0, "katz": 0.0 }, { "node": 9, "degree": 1.0, "katz": 0.5 }])
+        );
+        assert_eq!(table.map_nodes(|node| node + 1).nodes, vec![8, 10]);
+    }
+
+    #[test]
+    fn test_rank_correlations() {
+        // Ties in both measures, with the tie-corrected values of the definitions
+        let x = [1.0, 2.0, 2.0, 3.0, 4.0, 5.0, 5.0, 6.0];
+        let y = [2.0, 1.0, 3.0, 3.0, 6.0, 4.0, 5.0, 5.0];
+        let spearman = pearson(&average_ranks(&x), &average_ranks(&y)).unwrap();
+        assert!((spearman - 0.7804878).abs() < 1e-6, "{}", spearman);
+        let kendall = kendall_tau(&x, &y).unwrap();
+        assert!((kendall - kendall_by_pairs(&x, &y)).abs() < 1e-12);
+        assert!((kendall - 0.6153846).abs() < 1e-6, "{}", kendall);
+
+        // Reversed ranks correlate perfectly negatively, and a constant measure not at all
+        let reversed: Vec<f64> = x.iter().map(|value| -value).collect();
+        assert!((kendall_tau(&x, &reversed).unwrap() + 1.0).abs() < 1e-12);
+        assert_eq!(kendall_tau(&x, &[1.0; 8]), None);
+        assert_eq!(pearson(&average_ranks(&x), &average_ranks(&[1.0; 8])), None);
+    }
+
+    #[test]
+    fn test_kendall_tau_matches_pairwise_count() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        // Few distinct values, so there are many ties of every kind
+        let mut rng = StdRng::seed_from_u64(7);
+        let x: Vec<f64> = (0..300).map(|_| rng.gen_range(0..6) as f64).collect();
+        let y: Vec<f64> = x.iter().map(|value| value + rng.gen_range(0..4) as f64).collect();
+        assert!((kendall_tau(&x, &y).unwrap() - kendall_by_pairs(&x, &y)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_compare_centrality() {
+        // Ten nodes the measures agree on, apart from node 0, first on katz and last on degree
+        let nodes: Vec<usize> = (0..10).collect();
+        let mut table = CentralityTable::new(nodes.clone());
+        table.insert(Measure::Degree, &nodes.iter().map(|&node| (node, node as f64)).collect());
+        let katz = nodes.iter().map(|&node| (node, if node == 0 { 100.0 } else { node as f64 }));
+        table.insert(Measure::Katz, &katz.collect());
 
-        assert_eq!(degree[&0], 1.0);
-        assert_eq!(degree[&1], 3.0);
-        assert_eq!(degree[&2], 1.0);
-        assert_eq!(degree[&3], 1.0);
+        let options = ComparisonOptions { top_k: 3, high_percentile: 0.9, low_percentile: 0.5 };
+        let comparison = compare_centrality(&table, &options);
+        let pair = comparison.pairs[0];
+        assert_eq!((pair.first, pair.second), (Measure::Degree, Measure::Katz));
+        // Node 0 moves past the 9 others, so 9 of the 45 pairs turn discordant
+        assert!((pair.kendall.unwrap() - (36.0 - 9.0) / 45.0).abs() < 1e-12);
+        assert!(pair.spearman.unwrap() < 0.5);
+        // The top three are 7, 8, 9 and 0, 8, 9
+        assert!((pair.top_k_jaccard - 2.0 / 4.0).abs() < 1e-12);
+        assert_eq!(
+            comparison.outliers,
+            vec![RankOutlier { node: 0, measure: Measure::Katz, percentile: 1.0, highest_other: 0.0 }]
+        );
     }
 }
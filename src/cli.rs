@@ -0,0 +1,554 @@
+// cli.rs
+
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use crnsa::centrality_analysis::Measure;
+use crnsa::data::InputFormat;
+use crnsa::loader::InputOptions;
+use crnsa::pipeline::{
+    self, AnalysisConfig, BetweennessConfig, BridgesConfig, CentralityConfig, ClosenessConfig, CommunitiesConfig,
+    CompareCentralityConfig, CorePeripheryConfig, DegreeMeasure, OutputConfig, OutputFormat, PagerankConfig,
+    PipelineConfig, RouteConfig,
+};
+use crnsa::preprocess::PreprocessPolicy;
+use std::path::PathBuf;
+
+// Command line of the crnsa binary: one subcommand per analysis, or `--all` for the whole pipeline.
+// The input options are global, so they can be given before or after the subcommand. The flags are turned into the
+// library's pipeline configuration, whose defaults they share.
+#[derive(Debug, Parser)]
+#[command(name = "crnsa", version, about = "California road network structure analysis")]
+pub struct Cli {
+    /// Run the pipeline described by a TOML or JSON file instead of the flags
+    #[arg(long, exclusive = true)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// Seed of every random choice, such as route landmarks and endpoints; a fresh one is drawn by default
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Run the analyses of the original pipeline with default parameters; costlier centralities run when named
+    #[arg(long)]
+    pub all: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    // The analyses to run: the subcommand, or the whole pipeline with `--all`. None are given next to `--config`,
+    // whose file lists them. Giving both or neither, or either one next to `--config`, is a usage error, reported
+    // like any other by clap.
+    pub fn commands(&self) -> Result<Vec<AnalysisConfig>, clap::Error> {
+        if self.config.is_some() && (self.command.is_some() || self.all) {
+            return Err(Cli::command()
+                .error(ErrorKind::ArgumentConflict, "the configuration file lists the analyses; drop the subcommand"));
+        }
+        if self.config.is_some() {
+            return Ok(Vec::new());
+        }
+        match (&self.command, self.all) {
+            (Some(command), false) => Ok(vec![command.clone().into()]),
+            (None, true) => Ok(AnalysisConfig::pipeline()),
+            (Some(_), true) => {
+                Err(Cli::command().error(ErrorKind::ArgumentConflict, "--all runs every analysis; drop the subcommand"))
+            }
+            (None, false) => Err(Cli::command()
+                .error(ErrorKind::MissingSubcommand, "name an analysis to run, or pass --all to run them all")),
+        }
+    }
+
+    // The pipeline the command line asks for: one analysis, or every one with `--all`. The command line always
+    // preprocesses with the `shortest` policy.
+    pub fn pipeline_config(self) -> Result<PipelineConfig, clap::Error> {
+        let analyses = self.commands()?;
+        let config = PipelineConfig {
+            input: self.input.into(),
+            preprocess: PreprocessPolicy::shortest(),
+            output: self.output.into(),
+            seed: self.seed.unwrap_or_else(pipeline::random_seed),
+            analyses,
+        };
+        // The flags cannot describe an invalid pipeline, clap has checked them already
+        Ok(config.validate().expect("command line pipelines are valid"))
+    }
+}
+
+// Parse one of the values of a library enum by its name. The library does not depend on clap, so its enums list
+// their names instead of deriving ValueEnum.
+fn named<T: Copy + Send + Sync + 'static>(
+    values: &'static [T],
+    name: fn(T) -> &'static str,
+) -> impl TypedValueParser<Value = T> {
+    PossibleValuesParser::new(values.iter().map(|&value| name(value)))
+        .map(move |text| values.iter().copied().find(|&value| name(value) == text).expect("a listed value"))
+}
+
+// Where the road network comes from
+#[derive(Debug, Clone, Args)]
+pub struct InputArgs {
+    /// Road network to analyze: a SNAP edge list, a DIMACS .gr file, an OSM extract or a .snap snapshot
+    #[arg(short, long, global = true, default_value = "data/roadNet-CA.txt")]
+    pub input: String,
+
+    /// Format of the input; detected from the file name by default
+    #[arg(short, long, global = true, value_parser = named(&InputFormat::ALL, InputFormat::name))]
+    pub format: Option<InputFormat>,
+
+    /// `node_id lon lat` file whose coordinates are joined onto the graph
+    #[arg(long, global = true)]
+    pub coordinates: Option<String>,
+
+    /// Keep one-way streets as arcs; PageRank, strong components and routes then follow them
+    #[arg(long, global = true)]
+    pub directed: bool,
+
+    /// Restrict every analysis to the largest weakly connected component
+    #[arg(long, global = true)]
+    pub largest_component: bool,
+
+    /// Snapshot file that caches the parsed network between runs; `<input>.snap` by default
+    #[arg(long, global = true, value_name = "PATH")]
+    pub cache: Option<String>,
+
+    /// Parse the input on every run instead of caching it in a snapshot
+    #[arg(long, global = true, conflicts_with = "cache")]
+    pub no_cache: bool,
+}
+
+impl From<InputArgs> for InputOptions {
+    fn from(args: InputArgs) -> Self {
+        InputOptions {
+            format: args.format.unwrap_or_else(|| InputFormat::detect(&args.input)),
+            coordinates: args.coordinates,
+            directed: args.directed,
+            largest_component: args.largest_component,
+            // Parsed networks are cached next to the input unless told otherwise, so the next run on it starts from
+            // the snapshot
+            cache: match (args.no_cache, args.cache) {
+                (true, _) => None,
+                (false, Some(cache)) => Some(cache),
+                (false, None) => Some(format!("{}.snap", args.input)),
+            },
+            path: args.input,
+        }
+    }
+}
+
+// Where and how the outputs are written
+#[derive(Debug, Clone, Args)]
+pub struct OutputArgs {
+    /// Directory the JSON outputs are written to; created if it does not exist
+    #[arg(short = 'o', long = "output-dir", global = true, default_value = ".")]
+    pub dir: PathBuf,
+
+    /// Layout of the JSON outputs
+    #[arg(
+        id = "output_format",
+        long = "output-format",
+        global = true,
+        value_parser = named(&OutputFormat::ALL, OutputFormat::name),
+        default_value_t = OutputConfig::default().format
+    )]
+    pub format: OutputFormat,
+
+    /// Draw live progress bars of the long-running stages on stderr
+    #[arg(long, global = true)]
+    pub progress: bool,
+}
+
+impl From<OutputArgs> for OutputConfig {
+    fn from(args: OutputArgs) -> Self {
+        OutputConfig { dir: args.dir, format: args.format, progress: args.progress }
+    }
+}
+
+// The analyses, one subcommand each, with the flags of their parameters
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Node and edge counts, connectivity, degree distribution, clustering, density and assortativity
+    Stats,
+    /// Degree, eigenvector and Katz centrality of every node, written to sample_degree_centrality.json,
+    /// sample_eigenvector_centrality.json and sample_katz_centrality.json
+    Centrality(CentralityArgs),
+    /// Betweenness of nodes and edges, exact or estimated from sampled pivot sources, written to
+    /// sample_betweenness_centrality.json
+    Betweenness(BetweennessArgs),
+    /// Closeness and harmonic centrality, exact or estimated from sampled sources, written to
+    /// sample_closeness_centrality.json and sample_harmonic_centrality.json
+    Closeness(ClosenessArgs),
+    /// PageRank scores of the highest ranked nodes
+    Pagerank(PagerankArgs),
+    /// Rank correlations and top-k overlap between centrality measures, and the nodes ranking high on one only,
+    /// with the scores of every node under every measure written to sample_centrality_table.json
+    CompareCentrality(CompareCentralityArgs),
+    /// Leiden communities, written to sample_leiden_output.json
+    Communities(CommunitiesArgs),
+    /// Bridge edges, whose removal disconnects the network
+    Bridges(BridgesArgs),
+    /// Connected components, and strongly connected components in directed mode
+    Components,
+    /// Split the nodes into core and periphery by degree
+    CorePeriphery(CorePeripheryArgs),
+    /// Landmark-guided shortest paths
+    Route(RouteArgs),
+}
+
+impl From<Command> for AnalysisConfig {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::Stats => AnalysisConfig::Stats,
+            Command::Centrality(args) => AnalysisConfig::Centrality(args.into()),
+            Command::Betweenness(args) => AnalysisConfig::Betweenness(args.into()),
+            Command::Closeness(args) => AnalysisConfig::Closeness(args.into()),
+            Command::Pagerank(args) => AnalysisConfig::Pagerank(args.into()),
+            Command::CompareCentrality(args) => AnalysisConfig::CompareCentrality(args.into()),
+            Command::Communities(args) => AnalysisConfig::Communities(args.into()),
+            Command::Bridges(args) => AnalysisConfig::Bridges(args.into()),
+            Command::Components => AnalysisConfig::Components,
+            Command::CorePeriphery(args) => AnalysisConfig::CorePeriphery(args.into()),
+            Command::Route(args) => AnalysisConfig::Route(args.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CentralityArgs {
+    /// Number of nodes to print
+    #[arg(long, default_value_t = CentralityConfig::default().top)]
+    pub top: usize,
+
+    /// Which degree to score nodes by
+    #[arg(
+        long,
+        value_parser = named(&DegreeMeasure::ALL, DegreeMeasure::name),
+        default_value_t = CentralityConfig::default().degree
+    )]
+    pub degree: DegreeMeasure,
+
+    /// Relative residual at which the eigenvector centrality iteration stops, and change per node at which the
+    /// Katz iteration stops
+    #[arg(long, default_value_t = CentralityConfig::default().tolerance)]
+    pub tolerance: f64,
+
+    /// Matrix-vector products allowed per connected component for eigenvector centrality, and iterations for
+    /// Katz centrality
+    #[arg(long, default_value_t = CentralityConfig::default().max_iterations)]
+    pub max_iterations: usize,
+
+    /// Katz attenuation, the weight of every further step of a walk; it must stay below one over the spectral
+    /// radius of the graph
+    #[arg(long, default_value_t = CentralityConfig::default().attenuation)]
+    pub attenuation: f64,
+}
+
+impl From<CentralityArgs> for CentralityConfig {
+    fn from(args: CentralityArgs) -> Self {
+        CentralityConfig {
+            top: args.top,
+            degree: args.degree,
+            tolerance: args.tolerance,
+            max_iterations: args.max_iterations,
+            attenuation: args.attenuation,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct BetweennessArgs {
+    /// Number of pivot sources to sample; the scores are extrapolated from their shortest paths
+    #[arg(long, default_value_t = BetweennessConfig::default().pivots)]
+    pub pivots: usize,
+
+    /// Search from every node instead of sampling pivots
+    #[arg(long)]
+    pub exact: bool,
+
+    /// Measure paths by edge weight instead of counting hops
+    #[arg(long)]
+    pub weighted: bool,
+
+    /// Confidence of the error bounds of sampled scores
+    #[arg(long, default_value_t = BetweennessConfig::default().confidence)]
+    pub confidence: f64,
+
+    /// Number of nodes and edges to print
+    #[arg(long, default_value_t = BetweennessConfig::default().top)]
+    pub top: usize,
+}
+
+impl From<BetweennessArgs> for BetweennessConfig {
+    fn from(args: BetweennessArgs) -> Self {
+        BetweennessConfig {
+            pivots: args.pivots,
+            exact: args.exact,
+            weighted: args.weighted,
+            confidence: args.confidence,
+            top: args.top,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ClosenessArgs {
+    /// Number of source nodes to sample; the scores are estimated from their distances
+    #[arg(long, default_value_t = ClosenessConfig::default().sources)]
+    pub sources: usize,
+
+    /// Search from every node instead of sampling sources
+    #[arg(long)]
+    pub exact: bool,
+
+    /// Measure distances by edge weight instead of counting hops
+    #[arg(long)]
+    pub weighted: bool,
+
+    /// Number of nodes to print
+    #[arg(long, default_value_t = ClosenessConfig::default().top)]
+    pub top: usize,
+}
+
+impl From<ClosenessArgs> for ClosenessConfig {
+    fn from(args: ClosenessArgs) -> Self {
+        ClosenessConfig { sources: args.sources, exact: args.exact, weighted: args.weighted, top: args.top }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PagerankArgs {
+    /// Damping factor
+    #[arg(long, default_value_t = PagerankConfig::default().damping)]
+    pub damping: f64,
+
+    /// Number of power iterations
+    #[arg(long, default_value_t = PagerankConfig::default().iterations)]
+    pub iterations: usize,
+
+    /// Number of nodes to print
+    #[arg(long, default_value_t = PagerankConfig::default().top)]
+    pub top: usize,
+}
+
+impl From<PagerankArgs> for PagerankConfig {
+    fn from(args: PagerankArgs) -> Self {
+        PagerankConfig { damping: args.damping, iterations: args.iterations, top: args.top }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CompareCentralityArgs {
+    /// Measures to compare, separated by commas
+    #[arg(
+        long,
+        value_parser = named(&Measure::ALL, Measure::name),
+        value_delimiter = ',',
+        default_values_t = CompareCentralityConfig::default().measures
+    )]
+    pub measures: Vec<Measure>,
+
+    /// Number of source nodes to sample for betweenness and closeness
+    #[arg(long, default_value_t = CompareCentralityConfig::default().sources)]
+    pub sources: usize,
+
+    /// Search from every node instead of sampling sources
+    #[arg(long)]
+    pub exact: bool,
+
+    /// Katz attenuation; it must stay below one over the spectral radius of the graph
+    #[arg(long, default_value_t = CompareCentralityConfig::default().attenuation)]
+    pub attenuation: f64,
+
+    /// Number of top nodes of every measure whose overlap is measured
+    #[arg(long, default_value_t = CompareCentralityConfig::default().top_k)]
+    pub top_k: usize,
+
+    /// Percentile rank, from 0 to 1, at or above which a node ranks high on a measure
+    #[arg(long, default_value_t = CompareCentralityConfig::default().high_percentile)]
+    pub high_percentile: f64,
+
+    /// Percentile rank at or below which a node ranks low on a measure
+    #[arg(long, default_value_t = CompareCentralityConfig::default().low_percentile)]
+    pub low_percentile: f64,
+
+    /// Number of nodes ranking high on one measure only to print
+    #[arg(long, default_value_t = CompareCentralityConfig::default().top)]
+    pub top: usize,
+}
+
+impl From<CompareCentralityArgs> for CompareCentralityConfig {
+    fn from(args: CompareCentralityArgs) -> Self {
+        CompareCentralityConfig {
+            measures: args.measures,
+            sources: args.sources,
+            exact: args.exact,
+            attenuation: args.attenuation,
+            top_k: args.top_k,
+            high_percentile: args.high_percentile,
+            low_percentile: args.low_percentile,
+            top: args.top,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CommunitiesArgs {
+    /// Number of communities to print
+    #[arg(long, default_value_t = CommunitiesConfig::default().top)]
+    pub top: usize,
+}
+
+impl From<CommunitiesArgs> for CommunitiesConfig {
+    fn from(args: CommunitiesArgs) -> Self {
+        CommunitiesConfig { top: args.top }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct BridgesArgs {
+    /// Number of bridge edges to print
+    #[arg(long, default_value_t = BridgesConfig::default().limit)]
+    pub limit: usize,
+}
+
+impl From<BridgesArgs> for BridgesConfig {
+    fn from(args: BridgesArgs) -> Self {
+        BridgesConfig { limit: args.limit }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct CorePeripheryArgs {
+    /// Nodes with at least this degree belong to the core
+    #[arg(long, default_value_t = CorePeripheryConfig::default().degree_threshold)]
+    pub degree_threshold: usize,
+
+    /// Number of core and periphery nodes to print
+    #[arg(long, default_value_t = CorePeripheryConfig::default().top)]
+    pub top: usize,
+}
+
+impl From<CorePeripheryArgs> for CorePeripheryConfig {
+    fn from(args: CorePeripheryArgs) -> Self {
+        CorePeripheryConfig { degree_threshold: args.degree_threshold, top: args.top }
+    }
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RouteArgs {
+    /// Number of landmarks that guide the search
+    #[arg(long, default_value_t = RouteConfig::default().landmarks)]
+    pub landmarks: usize,
+
+    /// Number of paths to find
+    #[arg(long, default_value_t = RouteConfig::default().paths)]
+    pub paths: usize,
+
+    /// Original id of the start node; a random node by default
+    #[arg(long, requires = "to")]
+    pub from: Option<usize>,
+
+    /// Original id of the end node; a random node reachable from the start by default
+    #[arg(long, requires = "from")]
+    pub to: Option<usize>,
+}
+
+impl From<RouteArgs> for RouteConfig {
+    fn from(args: RouteArgs) -> Self {
+        RouteConfig { landmarks: args.landmarks, paths: args.paths, from: args.from, to: args.to }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_subcommand_with_global_input() {
+        let cli = Cli::parse_from(["crnsa", "pagerank", "--damping", "0.9", "-i", "test_data/test_graph.txt", "--directed", "-o", "out"]);
+
+        assert_eq!(cli.input.input, "test_data/test_graph.txt");
+        assert!(cli.input.directed && !cli.all);
+        assert_eq!(cli.output.dir, PathBuf::from("out"));
+        match cli.command {
+            Some(Command::Pagerank(args)) => {
+                assert_eq!(args.damping, 0.9);
+                assert_eq!(args.iterations, 100);
+            }
+            other => panic!("expected pagerank, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_commands_to_run() {
+        let commands = |args: &[&str]| Cli::parse_from(args).commands().map(|commands| commands.len());
+        assert_eq!(commands(&["crnsa", "--all"]).unwrap(), 8);
+        assert_eq!(commands(&["crnsa", "-i", "test_data/test_graph.txt", "stats"]).unwrap(), 1);
+        assert!(commands(&["crnsa", "--all", "stats"]).is_err());
+        assert!(commands(&["crnsa"]).is_err());
+        assert!(commands(&["crnsa", "--config", "pipeline.toml", "stats"]).is_err());
+        // A route needs both endpoints or neither
+        assert!(Cli::try_parse_from(["crnsa", "route", "--from", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_flags_default_to_configuration() {
+        // Every subcommand without flags runs the analysis a configuration entry naming it runs
+        for subcommand in Cli::command().get_subcommands() {
+            let name = subcommand.get_name();
+            let from_flags = Cli::parse_from(["crnsa", name]).commands().unwrap().remove(0);
+            let from_config: AnalysisConfig = serde_json::from_value(serde_json::json!({ "analysis": name })).unwrap();
+            assert_eq!(from_flags.name(), name);
+            assert_eq!(from_flags, from_config);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_from_flags() {
+        let cli = Cli::parse_from(["crnsa", "--all", "-i", "test_data/test_graph.txt", "--output-format", "json", "--seed", "7"]);
+        let config = cli.pipeline_config().unwrap();
+
+        assert_eq!(config.analyses, AnalysisConfig::pipeline());
+        assert_eq!(config.input.format, InputFormat::EdgeList);
+        assert_eq!(config.input.cache.as_deref(), Some("test_data/test_graph.txt.snap"));
+        assert_eq!(config.output.format, OutputFormat::Json);
+        assert_eq!(config.seed, 7);
+
+        let cli = Cli::parse_from(["crnsa", "stats", "-i", "test_data/test_graph.txt", "--format", "dimacs"]);
+        assert_eq!(cli.pipeline_config().unwrap().input.format, InputFormat::Dimacs);
+    }
+
+    #[test]
+    fn test_cache_flags() {
+        let cache = |args: &[&str]| Cli::try_parse_from(args).map(|cli| InputOptions::from(cli.input).cache);
+        assert_eq!(cache(&["crnsa", "stats", "-i", "a.gr"]).unwrap().as_deref(), Some("a.gr.snap"));
+        assert_eq!(cache(&["crnsa", "stats", "-i", "a.gr", "--cache", "/tmp/a.snap"]).unwrap().as_deref(), Some("/tmp/a.snap"));
+        assert_eq!(cache(&["crnsa", "stats", "-i", "a.gr", "--no-cache"]).unwrap(), None);
+        assert!(cache(&["crnsa", "stats", "--cache", "a.snap", "--no-cache"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_measures() {
+        let measures = |args: &[&str]| match Cli::try_parse_from(args).map(|cli| cli.command) {
+            Ok(Some(Command::CompareCentrality(args))) => Ok(args.measures),
+            other => Err(format!("{:?}", other)),
+        };
+        assert_eq!(measures(&["crnsa", "compare-centrality"]).unwrap(), Measure::ALL.to_vec());
+        let chosen = measures(&["crnsa", "compare-centrality", "--measures", "katz,pagerank"]).unwrap();
+        assert_eq!(chosen, vec![Measure::Katz, Measure::Pagerank]);
+        assert!(measures(&["crnsa", "compare-centrality", "--measures", "degree,influence"]).is_err());
+    }
+}
@@ -1,19 +1,23 @@
 // Core-Periphery Analysis
 
-use petgraph::Graph;
-use petgraph::Undirected;
-use petgraph::graph::NodeIndex;
+use petgraph::visit::{IntoNeighbors, IntoNodeIdentifiers};
 use std::collections::HashSet;
+use std::hash::Hash;
 
-pub fn core_periphery_analysis(
-    graph: &Graph<(f64, f64), f64, Undirected>,
+// Split the nodes of any petgraph graph reference into core and periphery by degree
+pub fn core_periphery_analysis<G>(
+    graph: G,
     degree_threshold: usize,
-) -> (HashSet<NodeIndex>, HashSet<NodeIndex>) {
+) -> (HashSet<G::NodeId>, HashSet<G::NodeId>)
+where
+    G: IntoNodeIdentifiers + IntoNeighbors,
+    G::NodeId: Hash + Eq,
+{
     let mut core_nodes = HashSet::new(); // Set to store the core nodes
     let mut periphery_nodes = HashSet::new(); // Set to store the periphery nodes
 
     // Iterate over all nodes in the graph
-    for node in graph.node_indices() {
+    for node in graph.node_identifiers() {
         let degree = graph.neighbors(node).count(); // Calculate the degree of the node
         // If the degree of the node is greater than or equal to the specified threshold,
         // classify the node as a core node, otherwise classify it as a periphery node.
@@ -32,21 +36,17 @@ pub fn core_periphery_analysis(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use petgraph::graph::Graph;
+    use crate::data::{RoadEdge, RoadGraph, RoadNode};
 
     #[test]
     fn test_core_periphery_analysis() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 1.0));
-        let c = graph.add_node((2.0, 2.0));
-        let d = graph.add_node((3.0, 3.0));
-        let e = graph.add_node((4.0, 4.0));
-
-        graph.add_edge(a, b, 1.0);
-        graph.add_edge(b, c, 1.0);
-        graph.add_edge(c, d, 1.0);
-        graph.add_edge(d, e, 1.0);
+        let mut graph = RoadGraph::new_undirected();
+        let [a, b, c, d, e] = [1, 2, 3, 4, 5].map(|id| graph.add_node(RoadNode { id, position: Some((id as f64, id as f64)) }));
+
+        graph.add_edge(a, b, RoadEdge::default());
+        graph.add_edge(b, c, RoadEdge::default());
+        graph.add_edge(c, d, RoadEdge::default());
+        graph.add_edge(d, e, RoadEdge::default());
 
         let degree_threshold = 2;
         let (core_nodes, periphery_nodes) = core_periphery_analysis(&graph, degree_threshold);
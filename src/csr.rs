@@ -0,0 +1,472 @@
+// csr.rs
+
+use fixedbitset::FixedBitSet;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{
+    Data, EdgeCount, EdgeRef, GraphBase, GraphProp, IntoEdgeReferences, IntoEdges, IntoEdgesDirected, IntoNeighbors,
+    IntoNeighborsDirected, IntoNodeIdentifiers, NodeCompactIndexable, NodeCount, NodeIndexable, Visitable,
+};
+use petgraph::{Direction, EdgeType, Undirected};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+// An immutable graph in compressed sparse row form: the neighbors of every node are stored next to each other
+// in one array, so the whole-graph analyses walk memory in order instead of chasing the linked adjacency lists
+// of a `Graph`, and a node's degree is the length of its row. Nodes keep the indices of the graph the CSR was
+// built from, so results can be translated back through the road graph.
+//
+// Every edge of an undirected graph is stored once in the row of each endpoint. A directed graph also keeps
+// the rows of incoming arcs, so rank and reverse searches can follow the arcs backwards.
+#[derive(Debug, Clone)]
+pub struct CsrGraph<Ty: EdgeType = Undirected> {
+    outgoing: Rows,
+    // Incoming rows of a directed graph; each entry is the position of the arc in the outgoing rows
+    incoming: Option<Rows>,
+    // Edge weight of every entry of the outgoing rows
+    weights: Vec<f64>,
+    edge_count: usize,
+    ty: PhantomData<Ty>,
+}
+
+// Adjacency rows: the entries of node i are at offsets[i]..offsets[i + 1]
+#[derive(Debug, Clone)]
+struct Rows {
+    offsets: Vec<usize>,
+    neighbors: Vec<NodeIndex>,
+    // Position of each entry in the outgoing rows, which is the edge id; only kept for incoming rows
+    edges: Vec<usize>,
+}
+
+impl Rows {
+    // Build the rows from (node, neighbor, edge id) entries, with every row sorted by neighbor
+    fn build(node_count: usize, mut entries: Vec<(NodeIndex, NodeIndex, usize)>) -> Self {
+        entries.sort_unstable_by_key(|&(node, neighbor, edge)| (node, neighbor, edge));
+
+        let mut offsets = vec![0; node_count + 1];
+        for &(node, _, _) in &entries {
+            offsets[node.index() + 1] += 1;
+        }
+        for i in 0..node_count {
+            offsets[i + 1] += offsets[i];
+        }
+
+        let neighbors = entries.iter().map(|&(_, neighbor, _)| neighbor).collect();
+        let edges = entries.into_iter().map(|(_, _, edge)| edge).collect();
+        Rows { offsets, neighbors, edges }
+    }
+
+    fn range(&self, node: NodeIndex) -> Range<usize> {
+        self.offsets[node.index()]..self.offsets[node.index() + 1]
+    }
+}
+
+impl<Ty: EdgeType> CsrGraph<Ty> {
+    // Function to build the CSR form of any petgraph graph, reading edge weights through `edge_weight`, e.g.
+    // `CsrGraph::from_graph(&road_graph, |edge: &RoadEdge| edge.weight)`. Node i of the CSR is the node the source
+    // graph numbers i in `to_index`; vacant indices of a `StableGraph` become isolated nodes.
+    pub fn from_graph<G, F>(graph: G, edge_weight: F) -> Self
+    where
+        G: IntoEdgeReferences + NodeIndexable + GraphProp<EdgeType = Ty>,
+        F: Fn(&G::EdgeWeight) -> f64,
+    {
+        let node_count = graph.node_bound();
+        let index = |node| NodeIndex::new(graph.to_index(node));
+
+        // Collect the arcs; an undirected edge becomes one arc in each direction, a self-loop only one
+        let mut arcs = Vec::new();
+        let mut edge_count = 0;
+        for edge in graph.edge_references() {
+            let (source, target, weight) = (index(edge.source()), index(edge.target()), edge_weight(edge.weight()));
+            arcs.push((source, target, weight));
+            if !Ty::is_directed() && source != target {
+                arcs.push((target, source, weight));
+            }
+            edge_count += 1;
+        }
+        arcs.sort_unstable_by_key(|&(source, target, _)| (source, target));
+
+        // The arcs are already in row order, so their position is their edge id and need not be stored
+        let mut outgoing = Rows::build(node_count, arcs.iter().enumerate().map(|(edge, &(s, t, _))| (s, t, edge)).collect());
+        outgoing.edges = Vec::new();
+        let incoming = Ty::is_directed()
+            .then(|| Rows::build(node_count, arcs.iter().enumerate().map(|(edge, &(s, t, _))| (t, s, edge)).collect()));
+        let weights = arcs.into_iter().map(|(_, _, weight)| weight).collect();
+
+        CsrGraph { outgoing, incoming, weights, edge_count, ty: PhantomData }
+    }
+
+    // Number of arcs leaving the node; on an undirected graph its degree
+    pub fn out_degree(&self, node: NodeIndex) -> usize {
+        self.outgoing.range(node).len()
+    }
+
+    // Number of arcs entering the node; on an undirected graph its degree
+    pub fn in_degree(&self, node: NodeIndex) -> usize {
+        self.incoming.as_ref().unwrap_or(&self.outgoing).range(node).len()
+    }
+
+    // Whether an edge leads from a to b, found by a binary search of a's row
+    pub fn contains_edge(&self, a: NodeIndex, b: NodeIndex) -> bool {
+        self.outgoing.neighbors[self.outgoing.range(a)].binary_search(&b).is_ok()
+    }
+
+    fn rows(&self, direction: Direction) -> &Rows {
+        match direction {
+            Direction::Outgoing => &self.outgoing,
+            Direction::Incoming => self.incoming.as_ref().unwrap_or(&self.outgoing),
+        }
+    }
+}
+
+// Iterator over the neighbors in one row. Counting them reads the row length, so `neighbors(n).count()` is a
+// constant-time degree lookup.
+#[derive(Debug, Clone)]
+pub struct Neighbors<'a> {
+    iter: std::slice::Iter<'a, NodeIndex>,
+}
+
+impl Iterator for Neighbors<'_> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<NodeIndex> {
+        self.iter.next().copied()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl ExactSizeIterator for Neighbors<'_> {}
+
+// An edge of the CSR graph, as yielded by `edge_references` and `edges`
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeReference<'a> {
+    source: NodeIndex,
+    target: NodeIndex,
+    id: usize,
+    weight: &'a f64,
+}
+
+impl<'a> EdgeRef for EdgeReference<'a> {
+    type NodeId = NodeIndex;
+    type EdgeId = usize;
+    type Weight = f64;
+
+    fn source(&self) -> NodeIndex {
+        self.source
+    }
+
+    fn target(&self) -> NodeIndex {
+        self.target
+    }
+
+    fn weight(&self) -> &'a f64 {
+        self.weight
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+// Iterator over the entries of one row as edges. Incoming rows report the arcs in their own direction, so the
+// node whose row is walked is the target.
+#[derive(Debug, Clone)]
+pub struct Edges<'a, Ty: EdgeType> {
+    graph: &'a CsrGraph<Ty>,
+    node: NodeIndex,
+    direction: Direction,
+    entries: Range<usize>,
+}
+
+impl<'a, Ty: EdgeType> Iterator for Edges<'a, Ty> {
+    type Item = EdgeReference<'a>;
+
+    fn next(&mut self) -> Option<EdgeReference<'a>> {
+        let entry = self.entries.next()?;
+        let rows = self.graph.rows(self.direction);
+        let other = rows.neighbors[entry];
+        let id = if rows.edges.is_empty() { entry } else { rows.edges[entry] };
+        let (source, target) = match self.direction {
+            Direction::Outgoing => (self.node, other),
+            Direction::Incoming => (other, self.node),
+        };
+        Some(EdgeReference { source, target, id, weight: &self.graph.weights[id] })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+// Iterator over every edge once; an undirected edge is reported from its endpoint with the smaller index
+#[derive(Debug, Clone)]
+pub struct EdgeReferences<'a, Ty: EdgeType> {
+    graph: &'a CsrGraph<Ty>,
+    node: usize,
+    entry: usize,
+}
+
+impl<'a, Ty: EdgeType> Iterator for EdgeReferences<'a, Ty> {
+    type Item = EdgeReference<'a>;
+
+    fn next(&mut self) -> Option<EdgeReference<'a>> {
+        let rows = &self.graph.outgoing;
+        while self.entry < rows.neighbors.len() {
+            let entry = self.entry;
+            self.entry += 1;
+            while rows.offsets[self.node + 1] <= entry {
+                self.node += 1;
+            }
+            let (source, target) = (NodeIndex::new(self.node), rows.neighbors[entry]);
+            if Ty::is_directed() || source <= target {
+                return Some(EdgeReference { source, target, id: entry, weight: &self.graph.weights[entry] });
+            }
+        }
+        None
+    }
+}
+
+impl<Ty: EdgeType> GraphBase for CsrGraph<Ty> {
+    type NodeId = NodeIndex;
+    // Position of the edge in the outgoing rows
+    type EdgeId = usize;
+}
+
+impl<Ty: EdgeType> GraphProp for CsrGraph<Ty> {
+    type EdgeType = Ty;
+}
+
+impl<Ty: EdgeType> Data for CsrGraph<Ty> {
+    type NodeWeight = ();
+    type EdgeWeight = f64;
+}
+
+impl<Ty: EdgeType> NodeCount for CsrGraph<Ty> {
+    fn node_count(&self) -> usize {
+        self.outgoing.offsets.len() - 1
+    }
+}
+
+impl<Ty: EdgeType> EdgeCount for CsrGraph<Ty> {
+    fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+}
+
+impl<Ty: EdgeType> NodeIndexable for CsrGraph<Ty> {
+    fn node_bound(&self) -> usize {
+        self.node_count()
+    }
+
+    fn to_index(&self, node: NodeIndex) -> usize {
+        node.index()
+    }
+
+    fn from_index(&self, index: usize) -> NodeIndex {
+        NodeIndex::new(index)
+    }
+}
+
+impl<Ty: EdgeType> NodeCompactIndexable for CsrGraph<Ty> {}
+
+impl<Ty: EdgeType> Visitable for CsrGraph<Ty> {
+    type Map = FixedBitSet;
+
+    fn visit_map(&self) -> FixedBitSet {
+        FixedBitSet::with_capacity(self.node_count())
+    }
+
+    fn reset_map(&self, map: &mut FixedBitSet) {
+        map.clear();
+        map.grow(self.node_count());
+    }
+}
+
+impl<'a, Ty: EdgeType> IntoNeighbors for &'a CsrGraph<Ty> {
+    type Neighbors = Neighbors<'a>;
+
+    fn neighbors(self, node: NodeIndex) -> Neighbors<'a> {
+        self.neighbors_directed(node, Direction::Outgoing)
+    }
+}
+
+impl<'a, Ty: EdgeType> IntoNeighborsDirected for &'a CsrGraph<Ty> {
+    type NeighborsDirected = Neighbors<'a>;
+
+    fn neighbors_directed(self, node: NodeIndex, direction: Direction) -> Neighbors<'a> {
+        let rows = self.rows(direction);
+        Neighbors { iter: rows.neighbors[rows.range(node)].iter() }
+    }
+}
+
+impl<Ty: EdgeType> IntoNodeIdentifiers for &CsrGraph<Ty> {
+    type NodeIdentifiers = std::iter::Map<Range<usize>, fn(usize) -> NodeIndex>;
+
+    fn node_identifiers(self) -> Self::NodeIdentifiers {
+        (0..self.node_count()).map(NodeIndex::new as fn(usize) -> NodeIndex)
+    }
+}
+
+impl<'a, Ty: EdgeType> IntoEdgeReferences for &'a CsrGraph<Ty> {
+    type EdgeRef = EdgeReference<'a>;
+    type EdgeReferences = EdgeReferences<'a, Ty>;
+
+    fn edge_references(self) -> EdgeReferences<'a, Ty> {
+        EdgeReferences { graph: self, node: 0, entry: 0 }
+    }
+}
+
+impl<'a, Ty: EdgeType> IntoEdges for &'a CsrGraph<Ty> {
+    type Edges = Edges<'a, Ty>;
+
+    fn edges(self, node: NodeIndex) -> Edges<'a, Ty> {
+        self.edges_directed(node, Direction::Outgoing)
+    }
+}
+
+impl<'a, Ty: EdgeType> IntoEdgesDirected for &'a CsrGraph<Ty> {
+    type EdgesDirected = Edges<'a, Ty>;
+
+    fn edges_directed(self, node: NodeIndex, direction: Direction) -> Edges<'a, Ty> {
+        let entries = self.rows(direction).range(node);
+        Edges { graph: self, node, direction, entries }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assort::calculate_assortativity_coefficient;
+    use crate::data::tests::grid;
+    use crate::data::{build_road_graph, NodeCoordinates, RoadEdge, RoadGraph, RoadNetwork};
+    use crate::network_analysis::clustering_coefficient;
+    use crate::pagerank::pagerank;
+    use petgraph::Directed;
+    use std::time::Instant;
+
+    fn road_weight(edge: &RoadEdge) -> f64 {
+        edge.weight
+    }
+
+    // A triangle 0 - 1 - 2 with a pendant node 3, given by the node ids 10..13
+    fn create_test_graph() -> RoadGraph {
+        let network = RoadNetwork::from_edges([(10, 11, RoadEdge::from_weight(2.0)), (11, 12, RoadEdge::default()), (12, 10, RoadEdge::default()), (12, 13, RoadEdge::default())]);
+        build_road_graph(network, &NodeCoordinates::new()).0
+    }
+
+    #[test]
+    fn test_csr_keeps_structure_and_indices() {
+        let graph = create_test_graph();
+        let csr = CsrGraph::from_graph(&graph, road_weight);
+
+        assert_eq!(csr.node_count(), 4);
+        assert_eq!(csr.edge_count(), 4);
+        assert_eq!(csr.edge_references().count(), 4);
+        for node in graph.node_indices() {
+            let mut expected: Vec<NodeIndex> = graph.neighbors(node).collect();
+            expected.sort_unstable();
+            assert_eq!(csr.neighbors(node).collect::<Vec<_>>(), expected);
+            assert_eq!(csr.out_degree(node), expected.len());
+        }
+
+        let (a, b) = (NodeIndex::new(0), NodeIndex::new(1));
+        assert!(csr.contains_edge(a, b) && csr.contains_edge(b, a));
+        assert!(!csr.contains_edge(a, NodeIndex::new(3)));
+        assert_eq!(csr.edges(a).find(|edge| edge.target() == b).map(|edge| *edge.weight()), Some(2.0));
+    }
+
+    #[test]
+    fn test_csr_directed_rows() {
+        let mut graph = petgraph::Graph::<(), f64, Directed>::new();
+        let [a, b, c] = [(); 3].map(|_| graph.add_node(()));
+        graph.extend_with_edges([(a, b, 1.0), (a, c, 2.0), (c, a, 3.0)]);
+        let csr = CsrGraph::from_graph(&graph, |weight: &f64| *weight);
+
+        assert_eq!(csr.edge_count(), 3);
+        assert_eq!((csr.out_degree(a), csr.in_degree(a)), (2, 1));
+        assert_eq!(csr.neighbors_directed(b, Direction::Incoming).collect::<Vec<_>>(), vec![a]);
+        let incoming: Vec<(NodeIndex, NodeIndex, f64)> =
+            csr.edges_directed(a, Direction::Incoming).map(|edge| (edge.source(), edge.target(), *edge.weight())).collect();
+        assert_eq!(incoming, vec![(c, a, 3.0)]);
+    }
+
+    #[test]
+    fn test_whole_graph_analyses_agree_on_csr() {
+        let graph = create_test_graph();
+        let csr = CsrGraph::from_graph(&graph, road_weight);
+
+        assert!((clustering_coefficient(&graph) - clustering_coefficient(&csr)).abs() < 1e-12);
+        assert!((calculate_assortativity_coefficient(&graph) - calculate_assortativity_coefficient(&csr)).abs() < 1e-12);
+
+        let mut on_graph = pagerank(&graph, 0.85, 50);
+        let mut on_csr = pagerank(&csr, 0.85, 50);
+        on_graph.sort_by_key(|(node, _)| *node);
+        on_csr.sort_by_key(|(node, _)| *node);
+        for ((graph_node, graph_rank), (csr_node, csr_rank)) in on_graph.into_iter().zip(on_csr) {
+            assert_eq!(graph_node, csr_node);
+            assert!((graph_rank - csr_rank).abs() < 1e-12);
+        }
+    }
+
+    // A road-like grid of side * side intersections with every fourth block closed
+    fn grid_graph(side: usize) -> RoadGraph {
+        let mut network = grid(side);
+        for row in 0..side {
+            for col in (0..side - 1).filter(|col| (row + col) % 4 == 0) {
+                network.remove_edge(row * side + col, row * side + col + 1);
+            }
+        }
+        build_road_graph(network, &NodeCoordinates::new()).0
+    }
+
+    // Times the whole-graph analyses on the adjacency-list graph and on its CSR form. Runs on roadNet-CA when it
+    // is at data/roadNet-CA.txt, otherwise on a grid of similar size:
+    //
+    //   cargo test --release benchmark_csr -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn benchmark_csr_whole_graph_analyses() {
+        let graph = match crate::data::read_and_preprocess_data("data/roadNet-CA.txt") {
+            Ok(network) => build_road_graph(network, &NodeCoordinates::new()).0,
+            Err(_) => grid_graph(1400),
+        };
+        println!("Benchmark graph: {} nodes, {} edges", graph.node_count(), graph.edge_count());
+
+        let start = Instant::now();
+        let csr = CsrGraph::from_graph(&graph, road_weight);
+        println!("CSR construction: {:.2?}", start.elapsed());
+
+        let time = |name: &str, on_graph: &dyn Fn() -> f64, on_csr: &dyn Fn() -> f64| {
+            let start = Instant::now();
+            let graph_result = on_graph();
+            let graph_time = start.elapsed();
+            let start = Instant::now();
+            let csr_result = on_csr();
+            let csr_time = start.elapsed();
+            println!(
+                "{:<16} Graph {:>10.2?}   CSR {:>10.2?}   speedup {:.1}x",
+                name,
+                graph_time,
+                csr_time,
+                graph_time.as_secs_f64() / csr_time.as_secs_f64()
+            );
+            assert!((graph_result - csr_result).abs() < 1e-9);
+        };
+
+        time("PageRank (20)", &|| pagerank(&graph, 0.85, 20)[0].1, &|| pagerank(&csr, 0.85, 20)[0].1);
+        time("Clustering", &|| clustering_coefficient(&graph), &|| clustering_coefficient(&csr));
+        time(
+            "Assortativity",
+            &|| calculate_assortativity_coefficient(&graph),
+            &|| calculate_assortativity_coefficient(&csr),
+        );
+    }
+}
@@ -1,61 +1,745 @@
 // data.rs
 
-use petgraph::graphmap::UnGraphMap;
+use petgraph::graphmap::{DiGraphMap, GraphMap, UnGraphMap};
+use petgraph::graph::{Graph, NodeIndex};
+use petgraph::{EdgeType, Undirected};
 use petgraph::algo::{connected_components, kosaraju_scc};
-use petgraph::visit::{IntoEdgeReferences, NodeCount, EdgeCount};
-use std::io::{BufRead, BufReader};
+use petgraph::visit::{GraphProp, IntoEdgeReferences, IntoNeighborsDirected, IntoNodeIdentifiers, NodeCompactIndexable, Visitable};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader};
 use std::fs::File;
-use flate2::read::GzDecoder;
+use flate2::read::MultiGzDecoder;
+use bzip2::read::MultiBzDecoder;
+use xz2::read::XzDecoder;
+use crate::preprocess::{EdgeNormalizer, PreprocessPolicy, PreprocessReport};
+
+// Functional road class of a segment, following the OpenStreetMap `highway=*` values.
+// Link roads (e.g. `motorway_link`) are folded into the class they connect to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighwayClass {
+    Motorway,
+    Trunk,
+    Primary,
+    Secondary,
+    Tertiary,
+    Unclassified,
+    Residential,
+    LivingStreet,
+    Service,
+}
+
+impl HighwayClass {
+    // Map an OSM `highway` tag value to a class, returning None for ways that are not drivable roads
+    pub fn from_osm_tag(value: &str) -> Option<Self> {
+        match value {
+            "motorway" | "motorway_link" => Some(HighwayClass::Motorway),
+            "trunk" | "trunk_link" => Some(HighwayClass::Trunk),
+            "primary" | "primary_link" => Some(HighwayClass::Primary),
+            "secondary" | "secondary_link" => Some(HighwayClass::Secondary),
+            "tertiary" | "tertiary_link" => Some(HighwayClass::Tertiary),
+            "unclassified" | "road" => Some(HighwayClass::Unclassified),
+            "residential" => Some(HighwayClass::Residential),
+            "living_street" => Some(HighwayClass::LivingStreet),
+            "service" => Some(HighwayClass::Service),
+            _ => None,
+        }
+    }
+}
+
+// Attributes carried by every road segment. `weight` is the value used by distance-based
+// analyses; the remaining fields keep whatever optional columns the input provided.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadEdge {
+    pub weight: f64,
+    // Segment length in meters
+    pub length: Option<f64>,
+    // Travel time in seconds
+    pub travel_time: Option<f64>,
+    // Number of lanes
+    pub lanes: Option<u32>,
+    // Road class, when the source data provides one
+    pub highway: Option<HighwayClass>,
+}
+
+impl RoadEdge {
+    // Create an edge that only carries a weight
+    pub fn from_weight(weight: f64) -> Self {
+        RoadEdge {
+            weight,
+            length: None,
+            travel_time: None,
+            lanes: None,
+            highway: None,
+        }
+    }
+}
+
+impl Default for RoadEdge {
+    // Unweighted inputs such as the SNAP edge lists count every edge as one hop
+    fn default() -> Self {
+        RoadEdge::from_weight(1.0)
+    }
+}
 
 // Define a type alias for the road network graph
-pub type RoadNetwork = UnGraphMap<usize, f64>;
+pub type RoadNetwork = UnGraphMap<usize, RoadEdge>;
 
-// Function to read the input data from the given file and preprocess it into a graph
-pub fn read_and_preprocess_data(file_path: &str) -> RoadNetwork {
-    let file = File::open(file_path).expect("Failed to open file");
+// A road network of either edge type, for code that works on directed and undirected networks alike
+pub type RoadNetworkOf<Ty> = GraphMap<usize, RoadEdge, Ty>;
 
-    // Check the file extension and create the appropriate reader
-    let reader: Box<dyn BufRead> = if file_path.ends_with(".gz") {
-        Box::new(BufReader::new(GzDecoder::new(file)))
-    } else {
-        Box::new(BufReader::new(file))
+// A road network whose edges are directed arcs, so one-way streets are represented.
+// Two-way streets appear as a pair of opposite arcs.
+pub type DirectedRoadNetwork = DiGraphMap<usize, RoadEdge>;
+
+// Node positions keyed by node id, stored as (longitude, latitude) in degrees
+pub type NodeCoordinates = HashMap<usize, (f64, f64)>;
+
+// A node of the road graph: the id it has in the input data and its (longitude, latitude) position,
+// when the input provides one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoadNode {
+    pub id: usize,
+    pub position: Option<(f64, f64)>,
+}
+
+impl RoadNode {
+    // Create a node without a position
+    pub fn new(id: usize) -> Self {
+        RoadNode { id, position: None }
+    }
+}
+
+// The graph every analysis module works on. Nodes are stored contiguously and addressed by `NodeIndex`,
+// while their payload keeps the original id so results can be reported in the ids of the dataset.
+pub type RoadGraph<Ty = Undirected> = Graph<RoadNode, RoadEdge, Ty>;
+
+// Maximum number of rejected lines kept verbatim in a LoadSummary; the rest are only counted
+pub const MAX_REPORTED_BAD_LINES: usize = 100;
+
+// Compression codecs recognised by open_input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Codec {
+    // File extensions the codecs are usually stored under, used only to recognise the underlying format
+    // of a compressed file (e.g. `roads.osm.bz2`), never to pick the decoder
+    const EXTENSIONS: [&'static str; 4] = [".gz", ".bz2", ".xz", ".zst"];
+
+    // Identify the codec from the first bytes of a file; anything unrecognised is read as plain text
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Codec::Gzip
+        } else if magic.starts_with(b"BZh") {
+            Codec::Bzip2
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Codec::Xz
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Codec::Zstd
+        } else {
+            Codec::None
+        }
+    }
+
+    // Strip a trailing compression extension from a file name
+    pub fn strip_extension(file_path: &str) -> &str {
+        Self::EXTENSIONS
+            .iter()
+            .find_map(|extension| file_path.strip_suffix(extension))
+            .unwrap_or(file_path)
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Codec::None => "uncompressed",
+            Codec::Gzip => "gzip",
+            Codec::Bzip2 => "bzip2",
+            Codec::Xz => "xz",
+            Codec::Zstd => "zstd",
+        };
+        f.write_str(name)
+    }
+}
+
+// Formats of the road network inputs the loader reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InputFormat {
+    EdgeList,
+    Dimacs,
+    OsmXml,
+    OsmPbf,
+    Snapshot,
+}
+
+impl InputFormat {
+    pub const ALL: [InputFormat; 5] =
+        [InputFormat::EdgeList, InputFormat::Dimacs, InputFormat::OsmXml, InputFormat::OsmPbf, InputFormat::Snapshot];
+
+    // Name of the format, as on the command line and in a pipeline configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            InputFormat::EdgeList => "edge-list",
+            InputFormat::Dimacs => "dimacs",
+            InputFormat::OsmXml => "osm-xml",
+            InputFormat::OsmPbf => "osm-pbf",
+            InputFormat::Snapshot => "snapshot",
+        }
+    }
+
+    // Detect the format from the file name, looking through compression extensions; anything unrecognised is
+    // read as an edge list
+    pub fn detect(file_path: &str) -> Self {
+        if file_path.ends_with(".snap") {
+            InputFormat::Snapshot
+        } else if Codec::strip_extension(file_path).ends_with(".gr") {
+            InputFormat::Dimacs
+        } else {
+            match crate::osm::OsmFormat::from_path(file_path) {
+                Some(crate::osm::OsmFormat::Xml) => InputFormat::OsmXml,
+                Some(crate::osm::OsmFormat::Pbf) => InputFormat::OsmPbf,
+                None => InputFormat::EdgeList,
+            }
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// Errors that can occur while loading an edge list
+#[derive(Debug)]
+pub enum LoadError {
+    // The input file could not be opened or read
+    Io(io::Error),
+    // The input was compressed with the given codec but the stream is corrupt or truncated
+    Decompress(Codec, io::Error),
+    // The given (0-based) column on the given (1-based) line could not be parsed
+    Parse { line: usize, column: usize, text: String },
+    // The given (1-based) line has fewer columns than the column mapping requires
+    TooFewColumns { line: usize, text: String, expected: usize, found: usize },
+    // The LoadOptions are inconsistent, e.g. the weight refers to a column that is not mapped
+    InvalidOptions(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "I/O error: {}", err),
+            LoadError::Decompress(codec, err) => write!(f, "{} decompression error: {}", codec, err),
+            LoadError::Parse { line, column, text } => {
+                write!(f, "line {}: failed to parse column {} in {:?}", line, column, text)
+            }
+            LoadError::TooFewColumns { line, text, expected, found } => {
+                write!(f, "line {}: expected at least {} columns, found {} in {:?}", line, expected, found, text)
+            }
+            LoadError::InvalidOptions(message) => write!(f, "invalid load options: {}", message),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(err) | LoadError::Decompress(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl LoadError {
+    // Classify a read error by whether it came out of a decompressor
+    pub(crate) fn read(codec: Codec, err: io::Error) -> Self {
+        match codec {
+            Codec::None => LoadError::Io(err),
+            codec => LoadError::Decompress(codec, err),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+// Column separator used by the edge list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delimiter {
+    // Any run of spaces or tabs, as in the SNAP datasets
+    #[default]
+    Whitespace,
+    Tab,
+    Comma,
+}
+
+impl Delimiter {
+    // Iterate over the columns of a line without allocating, trimming the padding around delimited fields
+    pub(crate) fn fields(self, line: &str) -> Fields<'_> {
+        Fields { rest: Some(line), delimiter: self }
+    }
+
+    // Split a line into its columns
+    fn split(self, line: &str) -> Vec<&str> {
+        self.fields(line).collect()
+    }
+}
+
+// Iterator over the columns of a line, see Delimiter::fields
+pub(crate) struct Fields<'a> {
+    rest: Option<&'a str>,
+    delimiter: Delimiter,
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let separator = match self.delimiter {
+            Delimiter::Whitespace => {
+                // Runs of whitespace form a single separator and never produce empty columns
+                let rest = self.rest?.trim_start();
+                if rest.is_empty() {
+                    self.rest = None;
+                    return None;
+                }
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                self.rest = Some(&rest[end..]);
+                return Some(&rest[..end]);
+            }
+            Delimiter::Tab => '\t',
+            Delimiter::Comma => ',',
+        };
+
+        let rest = self.rest?;
+        match rest.find(separator) {
+            Some(end) => {
+                self.rest = Some(&rest[end + 1..]);
+                Some(rest[..end].trim())
+            }
+            None => {
+                self.rest = None;
+                Some(rest.trim())
+            }
+        }
+    }
+}
+
+// Which edge attribute becomes the `weight` used by the analyses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WeightSource {
+    // Every edge has weight 1.0
+    #[default]
+    Unit,
+    Length,
+    TravelTime,
+}
+
+// Zero-based positions of the columns in each edge line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub source: usize,
+    pub target: usize,
+    pub length: Option<usize>,
+    pub travel_time: Option<usize>,
+    pub lanes: Option<usize>,
+}
+
+impl ColumnMapping {
+    // The mapped columns in the order source, target, length, travel time, lanes
+    fn slots(&self) -> [Option<usize>; 5] {
+        [Some(self.source), Some(self.target), self.length, self.travel_time, self.lanes]
+    }
+
+    // Number of columns a line needs for every mapped column to be present
+    fn required_columns(&self) -> usize {
+        self.slots().iter().flatten().max().map_or(0, |column| column + 1)
+    }
+}
+
+impl Default for ColumnMapping {
+    // Plain `source target` edge lists
+    fn default() -> Self {
+        ColumnMapping {
+            source: 0,
+            target: 1,
+            length: None,
+            travel_time: None,
+            lanes: None,
+        }
+    }
+}
+
+// Options controlling how an edge list is parsed
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    // When true, malformed lines are skipped and recorded in the LoadSummary instead of aborting the load
+    pub lenient: bool,
+    pub delimiter: Delimiter,
+    pub columns: ColumnMapping,
+    pub weight: WeightSource,
+    // When true, the first non-comment line is treated as a header and ignored
+    pub has_header: bool,
+    // How self-loops, parallel edges and reciprocal edges are handled while the graph is built
+    pub preprocess: PreprocessPolicy,
+}
+
+impl LoadOptions {
+    // Check that the weight source refers to a mapped column
+    pub(crate) fn validate(&self) -> Result<(), LoadError> {
+        match self.weight {
+            WeightSource::Length if self.columns.length.is_none() => Err(LoadError::InvalidOptions(
+                "weight is taken from the length column, but no length column is mapped".to_string(),
+            )),
+            WeightSource::TravelTime if self.columns.travel_time.is_none() => Err(LoadError::InvalidOptions(
+                "weight is taken from the travel time column, but no travel time column is mapped".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Statistics gathered while loading an edge list
+#[derive(Debug, Default)]
+pub struct LoadSummary {
+    // Total number of lines read from the input, including comments and blank lines
+    pub lines_read: usize,
+    // Number of lines that described an edge and were handed to the preprocessing stage
+    pub edges_loaded: usize,
+    // Number of malformed lines skipped in lenient mode
+    pub skipped_lines: usize,
+    // The first MAX_REPORTED_BAD_LINES errors behind the skipped lines
+    pub bad_lines: Vec<LoadError>,
+    // Self-loops, parallel edges and reciprocal edges met while building the graph
+    pub preprocess: PreprocessReport,
+}
+
+impl LoadSummary {
+    // Record a malformed line that was skipped in lenient mode
+    pub(crate) fn record_bad_line(&mut self, err: LoadError) {
+        self.skipped_lines += 1;
+        if self.bad_lines.len() < MAX_REPORTED_BAD_LINES {
+            self.bad_lines.push(err);
+        }
+    }
+}
+
+// Function to parse a single non-comment line into a pair of node identifiers and the edge attributes.
+// The mapped columns are picked out while scanning the line, so no per-line allocation happens
+// unless the line is malformed.
+pub(crate) fn parse_edge_line(line: &str, line_number: usize, options: &LoadOptions) -> Result<(usize, usize, RoadEdge), LoadError> {
+    let slots = options.columns.slots();
+    let expected = options.columns.required_columns();
+
+    let mut values = [""; 5];
+    let mut found = 0;
+    for (column, field) in options.delimiter.fields(line).take(expected).enumerate() {
+        found = column + 1;
+        for (slot, mapped) in slots.iter().enumerate() {
+            if *mapped == Some(column) {
+                values[slot] = field;
+            }
+        }
+    }
+    if found < expected {
+        return Err(LoadError::TooFewColumns {
+            line: line_number,
+            text: line.to_string(),
+            expected,
+            found,
+        });
+    }
+
+    let parse_error = |slot: usize| LoadError::Parse {
+        line: line_number,
+        column: slots[slot].unwrap_or_default(),
+        text: line.to_string(),
+    };
+    let parse_node = |slot: usize| values[slot].parse::<usize>().map_err(|_| parse_error(slot));
+    // Weights must be finite and non-negative for Dijkstra and the weighted analyses to make sense
+    let parse_weight = |slot: usize| {
+        slots[slot]
+            .map(|_| match values[slot].parse::<f64>() {
+                Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+                _ => Err(parse_error(slot)),
+            })
+            .transpose()
     };
 
-    let mut graph = RoadNetwork::new();
+    let node_a = parse_node(0)?;
+    let node_b = parse_node(1)?;
+    let length = parse_weight(2)?;
+    let travel_time = parse_weight(3)?;
+    let lanes = slots[4]
+        .map(|_| values[4].parse::<u32>().map_err(|_| parse_error(4)))
+        .transpose()?;
+
+    // validate() guarantees the selected attribute is mapped, so the fallback only applies to Unit
+    let weight = match options.weight {
+        WeightSource::Unit => None,
+        WeightSource::Length => length,
+        WeightSource::TravelTime => travel_time,
+    }
+    .unwrap_or(1.0);
+
+    Ok((node_a, node_b, RoadEdge { weight, length, travel_time, lanes, highway: None }))
+}
+
+// Function to open an input file for buffered reading, transparently decompressing it when it starts
+// with the magic bytes of a supported codec. The file name plays no part in the detection.
+// Returns the reader together with the codec the stream is being decoded with.
+pub(crate) fn open_input(file_path: &str) -> io::Result<(Box<dyn BufRead>, Codec)> {
+    let mut file = BufReader::new(File::open(file_path)?);
+    let codec = Codec::detect(file.fill_buf()?);
+
+    // Wrap the file in the matching decoder; the peeked bytes are still buffered and not consumed
+    let reader: Box<dyn BufRead> = match codec {
+        Codec::None => Box::new(file),
+        Codec::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        Codec::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(file))),
+        Codec::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(file))),
+        Codec::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(file)?)),
+    };
+    Ok((reader, codec))
+}
+
+// Function to read the input data from the given file and preprocess it into a graph,
+// returning a summary of what was loaded and skipped alongside the graph
+pub fn load_edge_list(file_path: &str, options: &LoadOptions) -> Result<(RoadNetwork, LoadSummary), LoadError> {
+    load_edge_list_as::<Undirected>(file_path, options)
+}
+
+// Function to read an edge list into a graph of the given edge type. With `Directed` every line is an arc
+// from the source to the target column, so one-way streets are kept; with `Undirected` this is load_edge_list.
+pub fn load_edge_list_as<Ty: EdgeType>(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(RoadNetworkOf<Ty>, LoadSummary), LoadError> {
+    options.validate()?;
+    let (reader, codec) = open_input(file_path)?;
+
+    let mut graph = RoadNetworkOf::new();
+    let mut summary = LoadSummary::default();
+    let mut normalizer = EdgeNormalizer::new(options.preprocess);
+    let mut header_pending = options.has_header;
 
     // Read each line from the input file and process it
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        // Ignore lines starting with '#'
-        if line.starts_with('#') {
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| LoadError::read(codec, err))?;
+        summary.lines_read += 1;
+
+        // Ignore comment lines starting with '#' and blank lines
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        // Skip the header row of delimited files
+        if header_pending {
+            header_pending = false;
             continue;
         }
 
-        // Parse the line into nodes
-        let nodes: Vec<usize> = line
-            .split_whitespace()
-            .map(|n| n.parse::<usize>().expect("Failed to parse node"))
-            .collect();
+        match parse_edge_line(&line, index + 1, options) {
+            Ok((node_a, node_b, edge)) => {
+                // Add an edge between the nodes carrying the parsed attributes, subject to the preprocessing policy
+                normalizer.add_edge(&mut graph, node_a, node_b, edge);
+                summary.edges_loaded += 1;
+            }
+            Err(err) if options.lenient => summary.record_bad_line(err),
+            Err(err) => return Err(err),
+        }
+    }
+
+    summary.preprocess = normalizer.report();
+    Ok((graph, summary))
+}
+
+// Function to read the input data from the given file and preprocess it into a graph,
+// failing on the first malformed line. Like the other library loaders it keeps every edge as read.
+pub fn read_and_preprocess_data(file_path: &str) -> Result<RoadNetwork, LoadError> {
+    load_edge_list(file_path, &LoadOptions::default()).map(|(graph, _)| graph)
+}
+
+// Function to read a coordinate sidecar file with one `node_id lon lat` entry per line.
+// Lines starting with '#' and blank lines are ignored; later entries for the same node win.
+pub fn load_coordinates(file_path: &str, delimiter: Delimiter) -> Result<NodeCoordinates, LoadError> {
+    let (reader, codec) = open_input(file_path)?;
+    let mut coordinates = NodeCoordinates::new();
 
-        let node_a = nodes[0];
-        let node_b = nodes[1];
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| LoadError::read(codec, err))?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let columns = delimiter.split(&line);
+        if columns.len() < 3 {
+            return Err(LoadError::TooFewColumns {
+                line: index + 1,
+                text: line.clone(),
+                expected: 3,
+                found: columns.len(),
+            });
+        }
+
+        let parse_error = |column: usize| LoadError::Parse {
+            line: index + 1,
+            column,
+            text: line.clone(),
+        };
+        let node = columns[0].parse::<usize>().map_err(|_| parse_error(0))?;
+        let parse_coordinate = |column: usize| match columns[column].parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(parse_error(column)),
+        };
+        coordinates.insert(node, (parse_coordinate(1)?, parse_coordinate(2)?));
+    }
+
+    Ok(coordinates)
+}
+
+// Summary of how well a set of coordinates covered the nodes of a graph
+#[derive(Debug, Default)]
+pub struct CoordinateReport {
+    // Number of graph nodes that received a position
+    pub nodes_with_coordinates: usize,
+    // Ids of graph nodes that had no entry in the coordinates, in ascending order
+    pub missing_nodes: Vec<usize>,
+    // Number of coordinate entries whose node is not part of the graph
+    pub unused_coordinates: usize,
+}
+
+// Function to convert the road network into the `RoadGraph` every analysis runs on, giving each node its
+// original id and its (longitude, latitude) position. Nodes without coordinates get no position and are
+// listed in the returned report. Directed networks keep their arc directions. The network is consumed, so
+// only one copy of a large graph is held at a time.
+pub fn build_road_graph<Ty: EdgeType>(
+    graph: RoadNetworkOf<Ty>,
+    coordinates: &NodeCoordinates,
+) -> (RoadGraph<Ty>, CoordinateReport) {
+    let mut road_graph: RoadGraph<Ty> = Graph::with_capacity(graph.node_count(), graph.edge_count());
+    let mut report = CoordinateReport::default();
+
+    let node_map: HashMap<usize, _> = graph
+        .nodes()
+        .map(|node| {
+            let position = coordinates.get(&node).copied();
+            match position {
+                Some(_) => report.nodes_with_coordinates += 1,
+                None => report.missing_nodes.push(node),
+            }
+            (node, road_graph.add_node(RoadNode { id: node, position }))
+        })
+        .collect();
+
+    for (a, b, edge) in graph.all_edges() {
+        road_graph.add_edge(node_map[&a], node_map[&b], *edge);
+    }
+
+    report.missing_nodes.sort_unstable();
+    report.unused_coordinates = coordinates.keys().filter(|node| !node_map.contains_key(node)).count();
+
+    (road_graph, report)
+}
+
+// Function to look up the node index of every original node id, for callers that are given ids
+pub fn node_indices_by_id<Ty: EdgeType>(graph: &RoadGraph<Ty>) -> HashMap<usize, NodeIndex> {
+    graph.node_indices().map(|node| (graph[node].id, node)).collect()
+}
 
-        // Add an edge between the nodes with a weight of 1.0
-        graph.add_edge(node_a, node_b, 1.0);
+// Function to collapse a directed network into an undirected one for the analyses that ignore direction.
+// Opposite arcs between the same nodes become a single edge carrying the smaller weight.
+pub fn undirected_network(graph: &DirectedRoadNetwork) -> RoadNetwork {
+    let mut undirected = RoadNetwork::with_capacity(graph.node_count(), graph.edge_count());
+    for node in graph.nodes() {
+        undirected.add_node(node);
     }
+    for (a, b, edge) in graph.all_edges() {
+        let shorter = match undirected.edge_weight(a, b) {
+            Some(existing) => edge.weight < existing.weight,
+            None => true,
+        };
+        if shorter {
+            undirected.add_edge(a, b, *edge);
+        }
+    }
+    undirected
+}
 
-    graph
+// Connectivity summary of a network: its weakly connected components, and for directed networks its strongly
+// connected components as well
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkProperties {
+    pub weakly_connected_components: usize,
+    pub strongly_connected_components: Option<usize>,
+    // Node count of the largest strongly connected component
+    pub largest_strongly_connected_component: Option<usize>,
 }
 
-// Function to calculate and print the number of weakly and strongly connected components in the graph
-pub fn network_properties(graph: &RoadNetwork) {
+// Function to count the weakly connected components of the graph, and for directed networks the strongly
+// connected components as well. On an undirected graph the two notions coincide, so only the weak components
+// are counted. Works on any petgraph graph reference, such as `&RoadGraph` or `&RoadNetwork`.
+pub fn network_properties<G>(graph: G) -> NetworkProperties
+where
+    G: NodeCompactIndexable + IntoEdgeReferences + IntoNeighborsDirected + IntoNodeIdentifiers + Visitable + GraphProp,
+{
     // Weakly Connected Components
-    let wcc = connected_components(graph);
-    println!("Number of weakly connected components: {}", wcc);
+    let weakly_connected_components = connected_components(graph);
+
+    if graph.is_directed() {
+        // Strongly Connected Components: the parts of the network in which every node can reach every other
+        let scc = kosaraju_scc(graph);
+        let largest = scc.iter().map(Vec::len).max().unwrap_or(0);
+        NetworkProperties {
+            weakly_connected_components,
+            strongly_connected_components: Some(scc.len()),
+            largest_strongly_connected_component: Some(largest),
+        }
+    } else {
+        NetworkProperties {
+            weakly_connected_components,
+            strongly_connected_components: None,
+            largest_strongly_connected_component: None,
+        }
+    }
+}
+
 
-    // Strongly Connected Components
-    let scc = kosaraju_scc(graph);
-    println!("Number of strongly connected components: {}", scc.len());
+// Graphs shared by the tests of several modules
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // A square grid of `size` by `size` nodes with unit edges, numbered row by row. Grids are bipartite, have one
+    // most central node or square in the middle, and grow to road network sizes.
+    pub(crate) fn grid(size: usize) -> RoadNetwork {
+        let mut graph = RoadNetwork::with_capacity(size * size, 2 * size * size);
+        for row in 0..size {
+            for column in 0..size {
+                let node = row * size + column;
+                if column + 1 < size {
+                    graph.add_edge(node, node + 1, RoadEdge::default());
+                }
+                if row + 1 < size {
+                    graph.add_edge(node, node + size, RoadEdge::default());
+                }
+            }
+        }
+        graph
+    }
 }
 
 
@@ -69,7 +753,7 @@ fn test_empty_graph() {
 #[test]
 fn test_single_edge_graph() {
     let mut graph: RoadNetwork = UnGraphMap::new();
-    graph.add_edge(1, 2, 1.0);
+    graph.add_edge(1, 2, RoadEdge::default());
     network_properties(&graph);
 }
 
@@ -77,25 +761,27 @@ fn test_single_edge_graph() {
 #[test]
 fn test_disconnected_graph() {
     let mut graph: RoadNetwork = UnGraphMap::new();
-    graph.add_edge(1, 2, 1.0);
-    graph.add_edge(3, 4, 1.0);
-    network_properties(&graph);
+    graph.add_edge(1, 2, RoadEdge::default());
+    graph.add_edge(3, 4, RoadEdge::default());
+    let properties = network_properties(&graph);
+    assert_eq!(properties.weakly_connected_components, 2);
+    assert_eq!(properties.strongly_connected_components, None);
 }
 
 
 #[test]
 fn test_connected_graph() {
     let mut graph: RoadNetwork = UnGraphMap::new();
-    graph.add_edge(1, 2, 1.0);
-    graph.add_edge(2, 3, 1.0);
-    graph.add_edge(3, 4, 1.0);
+    graph.add_edge(1, 2, RoadEdge::default());
+    graph.add_edge(2, 3, RoadEdge::default());
+    graph.add_edge(3, 4, RoadEdge::default());
     network_properties(&graph);
 }
 
 #[test]
 fn test_self_loop_graph() {
     let mut graph: RoadNetwork = UnGraphMap::new();
-    graph.add_edge(1, 1, 1.0);
+    graph.add_edge(1, 1, RoadEdge::default());
     network_properties(&graph);
 }
 
@@ -103,11 +789,11 @@ fn test_self_loop_graph() {
 #[test]
 fn test_multiple_edge_graph() {
     let mut graph: RoadNetwork = UnGraphMap::new();
-    graph.add_edge(1, 2, 1.0);
-    graph.add_edge(2, 3, 1.0);
-    graph.add_edge(3, 4, 1.0);
-    graph.add_edge(4, 5, 1.0);
-    graph.add_edge(5, 1, 1.0);
+    graph.add_edge(1, 2, RoadEdge::default());
+    graph.add_edge(2, 3, RoadEdge::default());
+    graph.add_edge(3, 4, RoadEdge::default());
+    graph.add_edge(4, 5, RoadEdge::default());
+    graph.add_edge(5, 1, RoadEdge::default());
     network_properties(&graph);
 }
 
@@ -131,14 +817,250 @@ fn test_disconnected_nodes_graph() {
 }
 
 
+#[test]
+fn test_read_and_preprocess_data() {
+    let graph = read_and_preprocess_data("test_data/test_graph.txt").unwrap();
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 3);
+}
+
+
+#[test]
+fn test_read_and_preprocess_data_missing_file() {
+    let result = read_and_preprocess_data("test_data/does_not_exist.txt");
+    assert!(matches!(result, Err(LoadError::Io(_))));
+}
+
+
+#[test]
+fn test_strict_load_reports_bad_line() {
+    let result = read_and_preprocess_data("test_data/malformed_graph.txt");
+    match result {
+        Err(LoadError::TooFewColumns { line, found, .. }) => {
+            assert_eq!(line, 4);
+            assert_eq!(found, 1);
+        }
+        other => panic!("expected TooFewColumns, got {:?}", other),
+    }
+}
+
+
+#[test]
+fn test_lenient_load_skips_bad_lines() {
+    let options = LoadOptions { lenient: true, ..LoadOptions::default() };
+    let (graph, summary) = load_edge_list("test_data/malformed_graph.txt", &options).unwrap();
+
+    assert_eq!(graph.edge_count(), 3);
+    assert_eq!(summary.edges_loaded, 3);
+    assert_eq!(summary.skipped_lines, 2);
+    assert!(matches!(summary.bad_lines[0], LoadError::TooFewColumns { line: 4, .. }));
+    assert!(matches!(summary.bad_lines[1], LoadError::Parse { line: 6, .. }));
+}
+
+
+#[test]
+fn test_load_weighted_csv() {
+    let options = LoadOptions {
+        delimiter: Delimiter::Comma,
+        columns: ColumnMapping {
+            length: Some(2),
+            travel_time: Some(3),
+            lanes: Some(4),
+            ..ColumnMapping::default()
+        },
+        weight: WeightSource::Length,
+        has_header: true,
+        ..LoadOptions::default()
+    };
+    let (graph, summary) = load_edge_list("test_data/weighted_graph.csv", &options).unwrap();
+
+    assert_eq!(summary.edges_loaded, 3);
+    let edge = graph.edge_weight(1, 2).unwrap();
+    assert_eq!(edge.weight, 250.5);
+    assert_eq!(edge.length, Some(250.5));
+    assert_eq!(edge.travel_time, Some(18.0));
+    assert_eq!(edge.lanes, Some(2));
+}
+
+
+#[test]
+fn test_load_weighted_rejects_unmapped_weight() {
+    let options = LoadOptions {
+        weight: WeightSource::TravelTime,
+        ..LoadOptions::default()
+    };
+    let result = load_edge_list("test_data/test_graph.txt", &options);
+    assert!(matches!(result, Err(LoadError::InvalidOptions(_))));
+}
+
+
+#[test]
+fn test_load_weighted_rejects_negative_length() {
+    let options = LoadOptions {
+        delimiter: Delimiter::Tab,
+        columns: ColumnMapping {
+            length: Some(2),
+            ..ColumnMapping::default()
+        },
+        ..LoadOptions::default()
+    };
+    let result = load_edge_list("test_data/negative_weight_graph.tsv", &options);
+    assert!(matches!(result, Err(LoadError::Parse { line: 2, column: 2, .. })));
+}
+
+
+#[test]
+fn test_load_coordinates() {
+    let coordinates = load_coordinates("test_data/test_graph_coordinates.txt", Delimiter::Whitespace).unwrap();
+    assert_eq!(coordinates.len(), 4);
+    assert_eq!(coordinates[&0], (-122.2727, 37.8716));
+    assert_eq!(coordinates[&9], (-121.0, 38.0));
+}
+
+
+#[test]
+fn test_build_road_graph_reports_missing_nodes() {
+    let graph = read_and_preprocess_data("test_data/test_graph.txt").unwrap();
+    let coordinates = load_coordinates("test_data/test_graph_coordinates.txt", Delimiter::Whitespace).unwrap();
+    let (road_graph, report) = build_road_graph(graph, &coordinates);
+
+    assert_eq!(road_graph.node_count(), 4);
+    assert_eq!(road_graph.edge_count(), 3);
+    assert_eq!(report.nodes_with_coordinates, 3);
+    assert_eq!(report.missing_nodes, vec![3]);
+    assert_eq!(report.unused_coordinates, 1);
+
+    // Every node keeps its original id, whatever index it was stored at
+    let ids = node_indices_by_id(&road_graph);
+    let mut sorted_ids: Vec<usize> = ids.keys().copied().collect();
+    sorted_ids.sort_unstable();
+    assert_eq!(sorted_ids, vec![0, 1, 2, 3]);
+    assert_eq!(road_graph[ids[&3]].position, None);
+    assert!(road_graph.node_weights().any(|node| node.position == Some((-122.2727, 37.8716))));
+}
+
+
 #[test]
 fn test_bipartite_graph() {
     let mut graph: RoadNetwork = UnGraphMap::new();
-    graph.add_edge(1, 4, 1.0);
-    graph.add_edge(1, 5, 1.0);
-    graph.add_edge(2, 4, 1.0);
-    graph.add_edge(2, 5, 1.0);
-    graph.add_edge(3, 4, 1.0);
-    graph.add_edge(3, 5, 1.0);
+    graph.add_edge(1, 4, RoadEdge::default());
+    graph.add_edge(1, 5, RoadEdge::default());
+    graph.add_edge(2, 4, RoadEdge::default());
+    graph.add_edge(2, 5, RoadEdge::default());
+    graph.add_edge(3, 4, RoadEdge::default());
+    graph.add_edge(3, 5, RoadEdge::default());
     network_properties(&graph);
-}
\ No newline at end of file
+}
+
+
+#[test]
+fn test_detects_codec_from_magic_bytes() {
+    use std::io::Write;
+
+    let plain = std::fs::read("test_data/test_graph.txt").unwrap();
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&plain).unwrap();
+    let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+    bzip2.write_all(&plain).unwrap();
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+    xz.write_all(&plain).unwrap();
+    let encoded = [
+        (Codec::Gzip, gzip.finish().unwrap()),
+        (Codec::Bzip2, bzip2.finish().unwrap()),
+        (Codec::Xz, xz.finish().unwrap()),
+        (Codec::Zstd, zstd::encode_all(plain.as_slice(), 0).unwrap()),
+    ];
+
+    let expected = read_and_preprocess_data("test_data/test_graph.txt").unwrap();
+    for (codec, bytes) in encoded {
+        assert_eq!(Codec::detect(&bytes), codec);
+
+        // Deliberately give the file a plain-text name so only the content can reveal the codec
+        let path = std::env::temp_dir().join(format!("crnsa_codec_{}_{}.txt", codec, std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, &bytes).unwrap();
+        let graph = read_and_preprocess_data(path).unwrap();
+        assert_eq!(graph.all_edges().count(), expected.all_edges().count());
+        assert!(expected.all_edges().all(|(a, b, _)| graph.contains_edge(a, b)));
+
+        // A truncated stream is reported as a decompression error of the detected codec
+        std::fs::write(path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(matches!(read_and_preprocess_data(path), Err(LoadError::Decompress(found, _)) if found == codec));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    assert_eq!(Codec::detect(&plain), Codec::None);
+    assert_eq!(Codec::strip_extension("roads.osm.bz2"), "roads.osm");
+}
+
+
+#[test]
+fn test_load_directed_edge_list_keeps_one_way_arcs() {
+    let (graph, summary) = load_edge_list_as::<petgraph::Directed>("test_data/one_way_graph.txt", &LoadOptions::default()).unwrap();
+
+    assert_eq!(summary.edges_loaded, 6);
+    assert_eq!(graph.edge_count(), 6);
+    assert!(graph.contains_edge(1, 2) && graph.contains_edge(2, 1));
+    assert!(graph.contains_edge(2, 3) && !graph.contains_edge(3, 2));
+    assert!(!graph.contains_edge(5, 4));
+
+    // The one-way loop is strongly connected together with the two-way street; the spur's end is not
+    let mut components: Vec<Vec<usize>> = kosaraju_scc(&graph)
+        .into_iter()
+        .map(|mut component| {
+            component.sort_unstable();
+            component
+        })
+        .collect();
+    components.sort();
+    assert_eq!(components, vec![vec![1, 2, 3, 4], vec![5]]);
+    let properties = network_properties(&graph);
+    assert_eq!(properties.strongly_connected_components, Some(2));
+    assert_eq!(properties.largest_strongly_connected_component, Some(4));
+}
+
+
+#[test]
+fn test_undirected_network_merges_opposite_arcs() {
+    let mut graph = DirectedRoadNetwork::new();
+    graph.add_edge(1, 2, RoadEdge::from_weight(5.0));
+    graph.add_edge(2, 1, RoadEdge::from_weight(3.0));
+    graph.add_edge(2, 3, RoadEdge::from_weight(1.0));
+    graph.add_node(4);
+
+    let undirected = undirected_network(&graph);
+    assert_eq!(undirected.node_count(), 4);
+    assert_eq!(undirected.edge_count(), 2);
+    assert_eq!(undirected.edge_weight(1, 2).unwrap().weight, 3.0);
+    assert_eq!(undirected.edge_weight(3, 2).unwrap().weight, 1.0);
+}
+
+
+#[test]
+fn test_load_edge_list_applies_preprocess_policy() {
+    use crate::preprocess::{ReciprocalPolicy, SelfLoopPolicy};
+
+    let options = LoadOptions {
+        preprocess: PreprocessPolicy {
+            self_loops: SelfLoopPolicy::Drop,
+            reciprocal_edges: ReciprocalPolicy::Drop,
+            ..PreprocessPolicy::default()
+        },
+        ..LoadOptions::default()
+    };
+    let (graph, summary) = load_edge_list("test_data/duplicate_edges.txt", &options).unwrap();
+
+    assert_eq!(summary.edges_loaded, 8);
+    assert_eq!(summary.preprocess, PreprocessReport { self_loops: 1, parallel_edges: 1, reciprocal_edges: 3 });
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 3);
+}
+
+
+#[test]
+fn test_detects_input_format_from_file_name() {
+    assert_eq!(InputFormat::detect("data/roadNet-CA.txt.gz"), InputFormat::EdgeList);
+    assert_eq!(InputFormat::detect("data/USA-road-d.NY.gr.gz"), InputFormat::Dimacs);
+    assert_eq!(InputFormat::detect("data/california.osm.pbf"), InputFormat::OsmPbf);
+    assert_eq!(InputFormat::detect("data/roadNet-CA.txt.snap"), InputFormat::Snapshot);
+}
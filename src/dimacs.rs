@@ -0,0 +1,335 @@
+// dimacs.rs
+
+use crate::data::{open_input, NodeCoordinates, RoadEdge, RoadNetwork, RoadNetworkOf};
+use crate::preprocess::{EdgeNormalizer, PreprocessPolicy, PreprocessReport};
+use petgraph::{EdgeType, Undirected};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+// DIMACS coordinate files store longitude and latitude as integers in millionths of a degree
+const COORDINATE_SCALE: f64 = 1_000_000.0;
+
+// Errors that can occur while reading or writing DIMACS shortest-path files
+#[derive(Debug)]
+pub enum DimacsError {
+    // The file could not be opened, read or written
+    Io(io::Error),
+    // The problem line (`p sp <n> <m>` or `p aux sp co <n>`) is missing or appears after the data lines
+    MissingHeader,
+    // The given (1-based) line is malformed or of an unexpected type
+    Parse { line: usize, text: String },
+    // A node id on the given line is outside the 1..=n range declared by the header
+    NodeOutOfRange { line: usize, node: usize, nodes: usize },
+    // The number of arcs or coordinates does not match the header
+    CountMismatch { expected: usize, found: usize },
+    // A node has no coordinates, so a coordinate file cannot be written for it
+    MissingCoordinates { node: usize },
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimacsError::Io(err) => write!(f, "I/O error: {}", err),
+            DimacsError::MissingHeader => write!(f, "missing DIMACS problem line before the data lines"),
+            DimacsError::Parse { line, text } => write!(f, "line {}: malformed DIMACS line {:?}", line, text),
+            DimacsError::NodeOutOfRange { line, node, nodes } => {
+                write!(f, "line {}: node {} is outside the declared range 1..={}", line, node, nodes)
+            }
+            DimacsError::CountMismatch { expected, found } => {
+                write!(f, "header declares {} entries, but the file contains {}", expected, found)
+            }
+            DimacsError::MissingCoordinates { node } => write!(f, "node {} has no coordinates", node),
+        }
+    }
+}
+
+impl Error for DimacsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DimacsError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DimacsError {
+    fn from(err: io::Error) -> Self {
+        DimacsError::Io(err)
+    }
+}
+
+// Meaning of the arc weights in a `.gr` file; the USA road graphs ship a distance (`-d`) and a travel time (`-t`) variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DimacsWeight {
+    Distance,
+    TravelTime,
+}
+
+// Problem sizes declared by the `p sp <n> <m>` line of a `.gr` file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrHeader {
+    pub nodes: usize,
+    pub arcs: usize,
+}
+
+// Function to iterate over the non-comment lines of a DIMACS file, yielding each with its (1-based) line number
+fn data_lines(file_path: &str) -> Result<impl Iterator<Item = io::Result<(usize, String)>>, DimacsError> {
+    let (reader, _) = open_input(file_path)?;
+    Ok(reader
+        .lines()
+        .enumerate()
+        .map(|(index, line)| line.map(|line| (index + 1, line)))
+        .filter(|line| match line {
+            Ok((_, text)) => !(text.starts_with('c') || text.trim().is_empty()),
+            Err(_) => true,
+        }))
+}
+
+// Function to parse the columns of a DIMACS line into numbers
+fn parse_fields<T: std::str::FromStr>(fields: &[&str], line: usize, text: &str) -> Result<Vec<T>, DimacsError> {
+    fields
+        .iter()
+        .map(|field| field.parse::<T>())
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| DimacsError::Parse { line, text: text.to_string() })
+}
+
+// Function to check that a node id lies within the range declared by the header
+fn check_node(node: usize, nodes: usize, line: usize) -> Result<usize, DimacsError> {
+    if node == 0 || node > nodes {
+        Err(DimacsError::NodeOutOfRange { line, node, nodes })
+    } else {
+        Ok(node)
+    }
+}
+
+// Function to read a DIMACS `.gr` arc file into a road network keyed by the DIMACS node ids (1..=n).
+// The benchmark graphs list each road in both directions. Like the other library loaders it keeps every arc as
+// read, so the undirected network keeps the first of the two arcs; `read_gr_as` with `PreprocessPolicy::shortest()`
+// keeps the smaller weight instead. Isolated nodes declared by the header are added too.
+pub fn read_gr(file_path: &str, weight: DimacsWeight) -> Result<(RoadNetwork, GrHeader), DimacsError> {
+    read_gr_as::<Undirected>(file_path, weight, PreprocessPolicy::default()).map(|(graph, header, _)| (graph, header))
+}
+
+// Function to read a DIMACS `.gr` arc file into a graph of the given edge type, applying the preprocessing
+// policy to the arcs. With `Directed` every arc keeps its direction, so one-way roads are preserved.
+pub fn read_gr_as<Ty: EdgeType>(
+    file_path: &str,
+    weight: DimacsWeight,
+    policy: PreprocessPolicy,
+) -> Result<(RoadNetworkOf<Ty>, GrHeader, PreprocessReport), DimacsError> {
+    let mut header: Option<GrHeader> = None;
+    let mut graph = RoadNetworkOf::<Ty>::new();
+    let mut normalizer = EdgeNormalizer::new(policy);
+    let mut arcs = 0;
+
+    for line in data_lines(file_path)? {
+        let (line_number, text) = line?;
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        match (fields[0], header) {
+            ("p", None) if fields.len() == 4 && fields[1] == "sp" => {
+                let sizes: Vec<usize> = parse_fields(&fields[2..], line_number, &text)?;
+                header = Some(GrHeader { nodes: sizes[0], arcs: sizes[1] });
+            }
+            ("a", Some(header)) if fields.len() == 4 => {
+                let values: Vec<usize> = parse_fields(&fields[1..], line_number, &text)?;
+                let from = check_node(values[0], header.nodes, line_number)?;
+                let to = check_node(values[1], header.nodes, line_number)?;
+                let arc_weight = values[2] as f64;
+                arcs += 1;
+
+                let mut edge = RoadEdge::from_weight(arc_weight);
+                match weight {
+                    DimacsWeight::Distance => edge.length = Some(arc_weight),
+                    DimacsWeight::TravelTime => edge.travel_time = Some(arc_weight),
+                }
+                normalizer.add_edge(&mut graph, from, to, edge);
+            }
+            ("a", None) => return Err(DimacsError::MissingHeader),
+            _ => return Err(DimacsError::Parse { line: line_number, text }),
+        }
+    }
+
+    let header = header.ok_or(DimacsError::MissingHeader)?;
+    if arcs != header.arcs {
+        return Err(DimacsError::CountMismatch { expected: header.arcs, found: arcs });
+    }
+    for node in 1..=header.nodes {
+        graph.add_node(node);
+    }
+
+    Ok((graph, header, normalizer.report()))
+}
+
+// Function to read a DIMACS `.co` coordinate file, converting the integer millionths of a degree back to degrees
+pub fn read_co(file_path: &str) -> Result<NodeCoordinates, DimacsError> {
+    let mut nodes: Option<usize> = None;
+    let mut coordinates = NodeCoordinates::new();
+    let mut entries = 0;
+
+    for line in data_lines(file_path)? {
+        let (line_number, text) = line?;
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        match (fields[0], nodes) {
+            ("p", None) if fields.len() == 5 && fields[1..4] == ["aux", "sp", "co"] => {
+                nodes = Some(parse_fields::<usize>(&fields[4..], line_number, &text)?[0]);
+            }
+            ("v", Some(nodes)) if fields.len() == 4 => {
+                let node = parse_fields::<usize>(&fields[1..2], line_number, &text)?[0];
+                let node = check_node(node, nodes, line_number)?;
+                let position: Vec<i64> = parse_fields(&fields[2..], line_number, &text)?;
+                coordinates.insert(node, (position[0] as f64 / COORDINATE_SCALE, position[1] as f64 / COORDINATE_SCALE));
+                entries += 1;
+            }
+            ("v", None) => return Err(DimacsError::MissingHeader),
+            _ => return Err(DimacsError::Parse { line: line_number, text }),
+        }
+    }
+
+    let nodes = nodes.ok_or(DimacsError::MissingHeader)?;
+    if entries != nodes {
+        return Err(DimacsError::CountMismatch { expected: nodes, found: entries });
+    }
+
+    Ok(coordinates)
+}
+
+// Function to assign DIMACS ids to the nodes of a network: the i-th smallest node id becomes i + 1.
+// Both writers use this numbering, so a `.gr`/`.co` pair written from the same network stays consistent.
+pub fn dimacs_ids(graph: &RoadNetwork) -> Vec<usize> {
+    let mut nodes: Vec<usize> = graph.nodes().collect();
+    nodes.sort_unstable();
+    nodes
+}
+
+// Function to write a road network as a DIMACS `.gr` file. Every undirected edge becomes two arcs and
+// weights are rounded to the integers the format requires.
+pub fn write_gr(graph: &RoadNetwork, file_path: &str) -> Result<(), DimacsError> {
+    let nodes = dimacs_ids(graph);
+    let index_of: std::collections::HashMap<usize, usize> =
+        nodes.iter().enumerate().map(|(index, &node)| (node, index + 1)).collect();
+
+    let mut arcs = Vec::with_capacity(graph.edge_count() * 2);
+    for (a, b, edge) in graph.all_edges() {
+        let weight = edge.weight.round() as u64;
+        arcs.push((index_of[&a], index_of[&b], weight));
+        if a != b {
+            arcs.push((index_of[&b], index_of[&a], weight));
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    writeln!(writer, "c 9th DIMACS Implementation Challenge: Shortest Paths")?;
+    writeln!(writer, "c written by crnsa from a network with {} nodes and {} edges", nodes.len(), graph.edge_count())?;
+    writeln!(writer, "p sp {} {}", nodes.len(), arcs.len())?;
+    for (from, to, weight) in arcs {
+        writeln!(writer, "a {} {} {}", from, to, weight)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+// Function to write the coordinates of a road network as a DIMACS `.co` file, using the same numbering as write_gr
+pub fn write_co(graph: &RoadNetwork, coordinates: &NodeCoordinates, file_path: &str) -> Result<(), DimacsError> {
+    let nodes = dimacs_ids(graph);
+
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    writeln!(writer, "c 9th DIMACS Implementation Challenge: Shortest Paths")?;
+    writeln!(writer, "c coordinates in millionths of a degree, written by crnsa")?;
+    writeln!(writer, "p aux sp co {}", nodes.len())?;
+    for (index, node) in nodes.iter().enumerate() {
+        let (lon, lat) = coordinates.get(node).ok_or(DimacsError::MissingCoordinates { node: *node })?;
+        let x = (lon * COORDINATE_SCALE).round() as i64;
+        let y = (lat * COORDINATE_SCALE).round() as i64;
+        writeln!(writer, "v {} {} {}", index + 1, x, y)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_gr() {
+        let (graph, header) = read_gr("test_data/sample.gr", DimacsWeight::Distance).unwrap();
+
+        assert_eq!(header, GrHeader { nodes: 5, arcs: 8 });
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(graph.edge_count(), 4);
+        // Node 5 is declared by the header but has no arcs
+        assert_eq!(graph.neighbors(5).count(), 0);
+
+        // The two arcs between 2 and 3 disagree; the first one read is kept
+        let edge = graph.edge_weight(2, 3).unwrap();
+        assert_eq!(edge.weight, 700.0);
+        assert_eq!(edge.length, Some(700.0));
+        assert_eq!(edge.travel_time, None);
+    }
+
+    #[test]
+    fn test_read_gr_directed() {
+        let policy = PreprocessPolicy::shortest();
+        let (graph, _, report) =
+            read_gr_as::<petgraph::Directed>("test_data/sample.gr", DimacsWeight::TravelTime, policy).unwrap();
+
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(graph.edge_count(), 8);
+        // Each direction keeps its own weight
+        assert_eq!(graph.edge_weight(2, 3).unwrap().travel_time, Some(700.0));
+        assert_eq!(graph.edge_weight(3, 2).unwrap().travel_time, Some(750.0));
+        assert_eq!(report.reciprocal_edges, 4);
+    }
+
+    #[test]
+    fn test_read_co() {
+        let coordinates = read_co("test_data/sample.co").unwrap();
+
+        assert_eq!(coordinates.len(), 5);
+        assert_eq!(coordinates[&1], (-73.530767, 41.085396));
+    }
+
+    #[test]
+    fn test_read_gr_rejects_arc_count_mismatch() {
+        let result = read_gr("test_data/sample_bad_count.gr", DimacsWeight::Distance);
+        assert!(matches!(result, Err(DimacsError::CountMismatch { expected: 4, found: 3 })));
+    }
+
+    #[test]
+    fn test_read_gr_rejects_edge_list() {
+        let result = read_gr("test_data/test_graph.txt", DimacsWeight::Distance);
+        assert!(matches!(result, Err(DimacsError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_write_and_read_round_trip() {
+        let (graph, _) = read_gr("test_data/sample.gr", DimacsWeight::Distance).unwrap();
+        let coordinates = read_co("test_data/sample.co").unwrap();
+
+        let directory = std::env::temp_dir();
+        let gr_path = directory.join(format!("crnsa_round_trip_{}.gr", std::process::id()));
+        let co_path = directory.join(format!("crnsa_round_trip_{}.co", std::process::id()));
+        let gr_path = gr_path.to_str().unwrap();
+        let co_path = co_path.to_str().unwrap();
+
+        write_gr(&graph, gr_path).unwrap();
+        write_co(&graph, &coordinates, co_path).unwrap();
+        let (reloaded, header) = read_gr(gr_path, DimacsWeight::Distance).unwrap();
+        let reloaded_coordinates = read_co(co_path).unwrap();
+        std::fs::remove_file(gr_path).unwrap();
+        std::fs::remove_file(co_path).unwrap();
+
+        assert_eq!(header, GrHeader { nodes: 5, arcs: 8 });
+        assert_eq!(reloaded.edge_weight(2, 3).unwrap().weight, 700.0);
+        assert_eq!(reloaded_coordinates, coordinates);
+    }
+}
@@ -1,23 +1,43 @@
 // leiden.rs
 
 use petgraph::graph::Graph;
-use petgraph::visit::{EdgeRef, IntoNodeIdentifiers};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeIdentifiers, NodeIndexable};
 use petgraph::Undirected;
 use std::collections::HashMap;
-use crate::NodeIndex;
+use std::hash::Hash;
+use petgraph::graph::NodeIndex;
+use crate::progress::{NoProgress, Progress};
+
+// The graph the algorithm works on: only the edge weights matter, and the nodes of the aggregated graphs built
+// during refinement stand for whole communities.
+type WorkingGraph = Graph<(), f64, Undirected>;
+
+// Define a public function called `leiden_communities` that takes any petgraph graph reference, such as `&RoadGraph` or `&RoadNetwork`, and an accessor that reads the weight of an edge from its payload, e.g. `|edge: &RoadEdge| edge.weight`. It returns a `HashMap` that maps the identifier of each node to its community assignment. Arcs of a directed graph are treated as undirected edges.
+pub fn leiden_communities<G, F>(input: G, edge_weight: F) -> HashMap<G::NodeId, usize>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+    G::NodeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    leiden_communities_with_progress(input, edge_weight, &NoProgress)
+}
 
-// Define a public function called `leiden_communities` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges, and returns a `HashMap` that maps each node to its community assignment.
-pub fn leiden_communities(
-    graph: &Graph<(f64, f64), f64, Undirected>,
-) -> HashMap<usize, usize> {
-    // Create a mutable copy of the input graph.
-    let mut graph = graph.clone();
+// Leiden communities as above, reporting every pass of local moving and refinement to `progress`
+pub fn leiden_communities_with_progress<G, F>(input: G, edge_weight: F, progress: &dyn Progress) -> HashMap<G::NodeId, usize>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+    G::NodeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    // Create a working copy of the input graph that only keeps the edge weights.
+    let mut graph = working_graph(input, edge_weight);
     // Initialize the community assignments using the `initial_community_assignments` function. The `&graph` argument is a reference to the input graph.
     let mut community_assignments = initial_community_assignments(&graph);
     // Initialize the iteration counter to zero.
     let mut iterations = 0;
 
     // Start a while loop that runs until either convergence is achieved or the maximum number of iterations is reached. In this case, the loop will run for 10 iterations.
+    progress.start("Leiden", Some(10));
     while iterations < 10 {
         // Call the `local_moving` function, passing in references to the input graph and the community assignments.
         local_moving(&graph, &mut community_assignments);
@@ -31,16 +51,40 @@ pub fn leiden_communities(
 
         // Increment the iteration counter at the end of each loop iteration.
         iterations += 1;
+        progress.advance(1);
     }
+    progress.finish();
 
-    // Return the community assignments as a `HashMap`.
-    community_assignments
+    // Return the community assignments as a `HashMap`, keyed by the node identifiers of the input graph.
+    input
+        .node_identifiers()
+        .map(|node| (node, community_assignments[&input.to_index(node)]))
+        .collect()
 }
 
 
-// Define a function called `initial_community_assignments` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges, and returns a `HashMap` that maps each node to its initial community assignment.
+// Define a function called `working_graph` that copies the structure and edge weights of the input graph into a `WorkingGraph`, where every node sits at the index `to_index` gives it.
+fn working_graph<G, F>(input: G, edge_weight: F) -> WorkingGraph
+where
+    G: IntoEdgeReferences + NodeIndexable,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    let mut graph = WorkingGraph::with_capacity(input.node_bound(), 0);
+    for _ in 0..input.node_bound() {
+        graph.add_node(());
+    }
+    for edge in input.edge_references() {
+        let source = NodeIndex::new(input.to_index(edge.source()));
+        let target = NodeIndex::new(input.to_index(edge.target()));
+        graph.add_edge(source, target, edge_weight(edge.weight()));
+    }
+    graph
+}
+
+
+// Define a function called `initial_community_assignments` that takes a reference to a `WorkingGraph`, and returns a `HashMap` that maps each node to its initial community assignment.
 fn initial_community_assignments(
-    graph: &Graph<(f64, f64), f64, Undirected>,
+    graph: &WorkingGraph,
 ) -> HashMap<usize, usize> {
     // Create a new, empty `HashMap` to store the community assignments.
     let mut community_assignments = HashMap::new();
@@ -58,8 +102,8 @@ fn initial_community_assignments(
 }
 
 
-// Define a function called `local_moving` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges, and a mutable reference to a `HashMap` that maps each node to its community assignment. The function performs local moving, which is a step of the Leiden algorithm.
-fn local_moving(graph: &Graph<(f64, f64), f64, Undirected>, community_assignments: &mut HashMap<usize, usize>) {
+// Define a function called `local_moving` that takes a reference to a `WorkingGraph`, and a mutable reference to a `HashMap` that maps each node to its community assignment. The function performs local moving, which is a step of the Leiden algorithm.
+fn local_moving(graph: &WorkingGraph, community_assignments: &mut HashMap<usize, usize>) {
     // Initialize a flag that indicates whether any improvement has been made.
     let mut improvement = true;
 
@@ -85,8 +129,8 @@ fn local_moving(graph: &Graph<(f64, f64), f64, Undirected>, community_assignment
                 if neighbor_community != node_community {
                     // Compute the increase in modularity that would result from moving the current node to the neighbor's community.
                     let delta_modularity = modularity_delta(
-                        &graph,
-                        &community_assignments,
+                        graph,
+                        community_assignments,
                         node.index(),
                         node_community,
                         neighbor_community,
@@ -110,9 +154,9 @@ fn local_moving(graph: &Graph<(f64, f64), f64, Undirected>, community_assignment
 }
 
 
-// Define a function called `modularity_delta` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges, a `HashMap` that maps each node to its community assignment, the index of the node to be moved, the index of the community from which the node will be moved, and the index of the community to which the node will be moved. The function computes the increase in modularity that would result from moving the node from its current community to the new community.
+// Define a function called `modularity_delta` that takes a reference to a `WorkingGraph`, a `HashMap` that maps each node to its community assignment, the index of the node to be moved, the index of the community from which the node will be moved, and the index of the community to which the node will be moved. The function computes the increase in modularity that would result from moving the node from its current community to the new community.
 fn modularity_delta(
-    graph: &Graph<(f64, f64), f64, Undirected>,
+    graph: &WorkingGraph,
     community_assignments: &HashMap<usize, usize>,
     node: usize,
     from_community: usize,
@@ -153,21 +197,24 @@ fn modularity_delta(
 
 
 
-// Define a function called `refinement` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges, and a `HashMap` that maps each node to its community assignment. The function performs refinement, which is a step of the Leiden algorithm that aggregates nodes that belong to the same community and constructs a new graph where the communities are nodes.
+// The refined graph, the updated community assignments, and the mapping between old communities and new nodes.
+type RefinementResult = (WorkingGraph, HashMap<usize, usize>, HashMap<usize, usize>);
+
+// Define a function called `refinement` that takes a reference to a `WorkingGraph`, and a `HashMap` that maps each node to its community assignment. The function performs refinement, which is a step of the Leiden algorithm that aggregates nodes that belong to the same community and constructs a new graph where the communities are nodes.
 fn refinement(
-    graph: &Graph<(f64, f64), f64, Undirected>,
+    graph: &WorkingGraph,
     community_assignments: &HashMap<usize, usize>,
-) -> (Graph<(f64, f64), f64, Undirected>, HashMap<usize, usize>, HashMap<usize, usize>) {
+) -> RefinementResult {
     // Create a new empty graph to store the communities as nodes.
-    let mut new_graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
+    let mut new_graph = WorkingGraph::new_undirected();
     // Create a new empty hash map to store the mapping between original communities and new nodes.
     let mut mapping = HashMap::new();
 
     // Iterate over all nodes and their community assignments.
-    for (node, community) in community_assignments.iter() {
+    for community in community_assignments.values() {
         // If the community has not already been added to the new graph, add it and create a mapping between the community and the new node index.
         mapping.entry(*community).or_insert_with(|| {
-            new_graph.add_node((*community as f64, 0.0)).index()
+            new_graph.add_node(()).index()
         });
     }
 
@@ -211,26 +258,27 @@ fn refinement(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::{RoadEdge, RoadGraph, RoadNetwork, RoadNode};
 
-    fn create_test_graph() -> Graph<(f64, f64), f64, Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 0.0));
-        let c = graph.add_node((1.0, 1.0));
-        let d = graph.add_node((0.0, 1.0));
+    fn create_test_graph() -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let a = graph.add_node(RoadNode { id: 100, position: Some((0.0, 0.0)) });
+        let b = graph.add_node(RoadNode { id: 101, position: Some((1.0, 0.0)) });
+        let c = graph.add_node(RoadNode { id: 102, position: Some((1.0, 1.0)) });
+        let d = graph.add_node(RoadNode { id: 103, position: Some((0.0, 1.0)) });
 
-        graph.add_edge(a, b, 1.0);
-        graph.add_edge(b, c, 1.0);
-        graph.add_edge(c, d, 1.0);
-        graph.add_edge(d, a, 1.0);
-        graph.add_edge(a, c, 1.0);
+        graph.add_edge(a, b, RoadEdge::default());
+        graph.add_edge(b, c, RoadEdge::default());
+        graph.add_edge(c, d, RoadEdge::default());
+        graph.add_edge(d, a, RoadEdge::default());
+        graph.add_edge(a, c, RoadEdge::default());
 
         graph
     }
 
     #[test]
     fn test_initial_community_assignments() {
-        let graph = create_test_graph();
+        let graph = working_graph(&create_test_graph(), |edge: &RoadEdge| edge.weight);
         let community_assignments = initial_community_assignments(&graph);
 
         assert_eq!(
@@ -242,7 +290,7 @@ mod tests {
     #[test]
     fn test_leiden_communities() {
         let graph = create_test_graph();
-        let community_assignments = leiden_communities(&graph);
+        let community_assignments = leiden_communities(&graph, |edge: &RoadEdge| edge.weight);
 
         // Check if all nodes belong to the same community
         let unique_communities: std::collections::HashSet<usize> =
@@ -255,6 +303,29 @@ mod tests {
             assert_eq!(*assignment, first_community);
         }
     }
-}
 
+    #[test]
+    fn test_leiden_communities_on_graph_map() {
+        // On a GraphMap with plain f64 weights the assignments are keyed by the original ids
+        let graph = RoadNetwork::from_edges([(100, 101), (101, 102), (102, 103), (103, 100), (100, 102)]);
+        let community_assignments = leiden_communities(&graph, |edge: &RoadEdge| edge.weight);
+
+        let mut ids: Vec<usize> = community_assignments.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![100, 101, 102, 103]);
+
+        let weighted = petgraph::graphmap::UnGraphMap::<u32, f64>::from_edges([(1, 2, 2.0), (2, 3, 0.5)]);
+        assert_eq!(leiden_communities(&weighted, |weight: &f64| *weight).len(), 3);
+    }
+
+    #[test]
+    fn test_leiden_communities_reports_every_pass() {
+        let graph = create_test_graph();
+        let progress = crate::progress::tests::CountingProgress::default();
+        let community_assignments = leiden_communities_with_progress(&graph, |edge: &RoadEdge| edge.weight, &progress);
 
+        assert_eq!(community_assignments.len(), graph.node_count());
+        assert_eq!(progress.tasks(), vec![("Leiden".to_string(), Some(10))]);
+        assert_eq!(progress.steps(), 10);
+    }
+}
@@ -0,0 +1,42 @@
+// lib.rs
+
+// Structure analysis of road networks. The library loads road networks from SNAP edge lists, DIMACS files and
+// OpenStreetMap extracts into a `RoadGraph` whose nodes keep their dataset ids, and runs the analyses on it:
+// centrality and betweenness, PageRank, clustering and assortativity, Leiden communities, bridges, connected
+// components, core-periphery structure and landmark routing. The `crnsa` binary is a command line front end to it.
+//
+//   let (network, _) = data::load_edge_list("test_data/test_graph.txt", &data::LoadOptions::default())?;
+//   let (graph, _) = data::build_road_graph(network, &data::NodeCoordinates::new());
+//   let ranks = pagerank::pagerank(&graph, 0.85, 100);
+//
+// The analyses are generic over petgraph's visit traits, so they also run on a `GraphMap`, a `StableGraph` or
+// the `CsrGraph` of csr.rs.
+
+pub mod assort;
+pub mod bed;
+pub mod betweenness;
+pub mod cca;
+pub mod centrality;
+pub mod centrality_analysis;
+pub mod cpa;
+pub mod csr;
+pub mod data;
+pub mod dimacs;
+pub mod leiden;
+pub mod loader;
+pub mod network_analysis;
+pub mod osm;
+pub mod pagerank;
+pub mod parallel_load;
+pub mod pipeline;
+pub mod preprocess;
+pub mod progress;
+pub mod report;
+pub mod route;
+pub mod shortest_path;
+pub mod snapshot;
+pub mod subgraph;
+
+pub use csr::CsrGraph;
+pub use data::{RoadEdge, RoadGraph, RoadNetwork, RoadNode};
+pub use pipeline::PipelineConfig;
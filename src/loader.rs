@@ -0,0 +1,305 @@
+// loader.rs
+
+use crate::data::{InputFormat, LoadError};
+use crate::parallel_load::LoadThroughput;
+use crate::preprocess::{PreprocessPolicy, PreprocessReport};
+use crate::progress::Progress;
+use crate::{data, dimacs, osm, parallel_load, snapshot, subgraph};
+use petgraph::{Directed, EdgeType};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+// Everything between an input file and the road graphs the analyses run on: format detection, the snapshot
+// cache, preprocessing, coordinates and the largest component restriction. What happened on the way, including
+// anything the loaders had to skip, is returned in a `LoadReport` for the caller to show; the parsing of edge lists
+// also reports to a `Progress`.
+
+// The undirected road graph, and the directed one when one-way streets are kept
+pub type RoadGraphs = (data::RoadGraph, Option<data::RoadGraph<Directed>>);
+
+// The input to load and how to turn it into road graphs. It is also the `[input]` table of a pipeline
+// configuration, where `path` is the only required field and the format is detected from it when not given.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "InputTable")]
+pub struct InputOptions {
+    pub path: String,
+    pub format: InputFormat,
+    // `node_id lon lat` file whose coordinates are joined onto the graph
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<String>,
+    // Keep one-way streets as arcs in a second, directed graph
+    pub directed: bool,
+    // Restrict both graphs to the largest weakly connected component
+    pub largest_component: bool,
+    // Snapshot file that caches the parsed network between runs; no cache when None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<String>,
+}
+
+// The `[input]` table as written, before the format is detected
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputTable {
+    path: String,
+    #[serde(default)]
+    format: Option<InputFormat>,
+    #[serde(default)]
+    coordinates: Option<String>,
+    #[serde(default)]
+    directed: bool,
+    #[serde(default)]
+    largest_component: bool,
+    #[serde(default)]
+    cache: Option<String>,
+}
+
+impl From<InputTable> for InputOptions {
+    fn from(table: InputTable) -> Self {
+        InputOptions {
+            format: table.format.unwrap_or_else(|| InputFormat::detect(&table.path)),
+            path: table.path,
+            coordinates: table.coordinates,
+            directed: table.directed,
+            largest_component: table.largest_component,
+            cache: table.cache,
+        }
+    }
+}
+
+impl InputOptions {
+    // Load `path` in the format its name suggests, undirected and whole, without coordinates or a cache
+    pub fn new(path: &str) -> Self {
+        InputOptions {
+            path: path.to_string(),
+            format: InputFormat::detect(path),
+            coordinates: None,
+            directed: false,
+            largest_component: false,
+            cache: None,
+        }
+    }
+}
+
+// What the loader did on the way from the input file to the road graphs
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    // What became of the snapshot cache, when one was consulted
+    pub cache: Option<CacheReport>,
+    // How the input was parsed; None when the network came from a snapshot
+    pub parse: Option<ParseReport>,
+    // Self-loops, parallel edges and reciprocal edges met while building the network; None for snapshots
+    pub preprocess: Option<PreprocessReport>,
+    // Size of the largest weakly connected component the graphs were restricted to
+    pub component: Option<ComponentRestriction>,
+    // How well the coordinates covered the nodes, when the input or a coordinate file provided any
+    pub coordinates: Option<data::CoordinateReport>,
+}
+
+// What the parser of each input format has to say about it
+#[derive(Debug)]
+pub enum ParseReport {
+    Dimacs(dimacs::GrHeader),
+    Osm {
+        // Number of highway ways that contributed at least one edge
+        ways_imported: usize,
+        // Number of distinct node references whose coordinates are not part of the extract
+        missing_nodes: usize,
+    },
+    EdgeList {
+        throughput: LoadThroughput,
+        // Number of malformed lines skipped, and the errors behind the first of them
+        skipped_lines: usize,
+        bad_lines: Vec<LoadError>,
+    },
+}
+
+// The snapshot cache of one input: whether it could be used, and whether rebuilding it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheReport {
+    pub path: String,
+    pub lookup: CacheLookup,
+    pub write_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheLookup {
+    // The snapshot was current and loaded instead of the input
+    Hit,
+    // There was no snapshot yet
+    Missing,
+    // The snapshot belongs to an older version of the input, or was read in another format
+    Stale,
+    // The snapshot could not be read
+    Unreadable(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentRestriction {
+    pub nodes: usize,
+    pub total_nodes: usize,
+}
+
+
+// Load the road network, reusing the binary snapshot at `cache_path` when it is still current and writing it
+// otherwise. Without a cache path the input is always parsed. Snapshot files (`.snap`) can also be given directly
+// as the input. Snapshots hold networks preprocessed with the `shortest` policy, so any other policy always parses
+// the input.
+pub fn load_network(
+    file_path: &str,
+    format: InputFormat,
+    policy: PreprocessPolicy,
+    cache_path: Option<&str>,
+    progress: &dyn Progress,
+) -> Result<(data::RoadNetwork, data::NodeCoordinates, LoadReport), Box<dyn Error>> {
+    if format == InputFormat::Snapshot {
+        let snapshot = snapshot::read_snapshot(file_path)?;
+        return Ok((snapshot.network, snapshot.coordinates, LoadReport::default()));
+    }
+    let snapshot_path = match cache_path {
+        Some(cache_path) if policy == PreprocessPolicy::shortest() => cache_path.to_string(),
+        _ => return parse_network(file_path, format, policy, progress),
+    };
+    let source = snapshot::SourceFingerprint::of(file_path, format)?;
+    let lookup = if Path::new(&snapshot_path).exists() {
+        match snapshot::read_snapshot(&snapshot_path) {
+            Ok(cached) if cached.source == Some(source) => {
+                let cache = CacheReport { path: snapshot_path, lookup: CacheLookup::Hit, write_error: None };
+                let report = LoadReport { cache: Some(cache), ..LoadReport::default() };
+                return Ok((cached.network, cached.coordinates, report));
+            }
+            Ok(_) => CacheLookup::Stale,
+            Err(err) => CacheLookup::Unreadable(err.to_string()),
+        }
+    } else {
+        CacheLookup::Missing
+    };
+
+    let (graph, coordinates, mut report) = parse_network(file_path, format, policy, progress)?;
+    // A missing cache only costs time on the next run, so a failed write is not fatal
+    let write_error =
+        snapshot::write_snapshot(&graph, &coordinates, Some(source), &snapshot_path).err().map(|err| err.to_string());
+    report.cache = Some(CacheReport { path: snapshot_path, lookup, write_error });
+    Ok((graph, coordinates, report))
+}
+
+
+// Parse the road network from an edge list, a DIMACS `.gr` file or an OpenStreetMap extract, together with
+// whatever node coordinates the input provides. A directed network keeps the arcs of the `.gr` file,
+// the one-way tags of the OSM ways, or the source-to-target direction of the edge list.
+pub fn parse_network<Ty: EdgeType>(
+    file_path: &str,
+    format: InputFormat,
+    policy: PreprocessPolicy,
+    progress: &dyn Progress,
+) -> Result<(data::RoadNetworkOf<Ty>, data::NodeCoordinates, LoadReport), Box<dyn Error>> {
+    let osm_format = match format {
+        InputFormat::Snapshot => {
+            return Err("snapshots store undirected networks; pass the original input instead".into());
+        }
+        InputFormat::Dimacs => {
+            let (graph, header, preprocess) = dimacs::read_gr_as(file_path, dimacs::DimacsWeight::Distance, policy)?;
+            let report =
+                LoadReport { parse: Some(ParseReport::Dimacs(header)), preprocess: Some(preprocess), ..LoadReport::default() };
+
+            // The benchmark instances ship their coordinates in a `.co` file next to the `.gr` file
+            let stem = data::Codec::strip_extension(file_path);
+            let coordinates_path = format!("{}.co", stem.strip_suffix(".gr").unwrap_or(stem));
+            if Path::new(&coordinates_path).exists() {
+                return Ok((graph, dimacs::read_co(&coordinates_path)?, report));
+            }
+            return Ok((graph, data::NodeCoordinates::new(), report));
+        }
+        InputFormat::OsmXml => Some(osm::OsmFormat::Xml),
+        InputFormat::OsmPbf => Some(osm::OsmFormat::Pbf),
+        InputFormat::EdgeList => None,
+    };
+
+    if let Some(osm_format) = osm_format {
+        let import = osm::import_osm_with_format(file_path, osm_format, policy)?;
+        let parse = ParseReport::Osm { ways_imported: import.ways_imported, missing_nodes: import.missing_nodes };
+        let report = LoadReport { parse: Some(parse), preprocess: Some(import.preprocess), ..LoadReport::default() };
+        return Ok((import.network, import.coordinates, report));
+    }
+
+    // Load the edge list, skipping malformed lines instead of aborting the whole run
+    let load_options = data::LoadOptions { lenient: true, preprocess: policy, ..data::LoadOptions::default() };
+    let (graph, load_summary, throughput) =
+        parallel_load::load_edge_list_parallel_with_progress(file_path, &load_options, progress)?;
+    let parse = ParseReport::EdgeList {
+        throughput,
+        skipped_lines: load_summary.skipped_lines,
+        bad_lines: load_summary.bad_lines,
+    };
+    let report = LoadReport { parse: Some(parse), preprocess: Some(load_summary.preprocess), ..LoadReport::default() };
+
+    // Edge lists carry no geometry
+    Ok((graph, data::NodeCoordinates::new(), report))
+}
+
+
+// Convert the loaded networks into the road graphs every analysis runs on. The networks and coordinates are
+// consumed, so a large graph is only held once; the nodes of the road graphs carry their original ids and
+// positions. With `largest_component_only` both graphs are restricted to the largest weakly connected component.
+// The restriction and the coverage of the coordinates are recorded in `report`.
+pub fn build_road_graphs(
+    graph: data::RoadNetwork,
+    directed_graph: Option<data::DirectedRoadNetwork>,
+    coordinates: data::NodeCoordinates,
+    largest_component_only: bool,
+    report: &mut LoadReport,
+) -> RoadGraphs {
+    let (graph, directed_graph, coordinate_report) = if largest_component_only {
+        // Both views are induced on the same nodes
+        let component = subgraph::largest_component(&graph, &coordinates);
+        report.component =
+            Some(ComponentRestriction { nodes: component.network.node_count(), total_nodes: graph.node_count() });
+        let directed_graph = directed_graph.map(|directed_graph| {
+            let nodes = component.original_ids.iter().copied();
+            subgraph::induce_subgraph(&directed_graph, &coordinates, nodes).into_road_graph().0
+        });
+        let (graph, coordinate_report) = component.into_road_graph();
+        (graph, directed_graph, coordinate_report)
+    } else {
+        let directed_graph =
+            directed_graph.map(|directed_graph| data::build_road_graph(directed_graph, &coordinates).0);
+        let (graph, coordinate_report) = data::build_road_graph(graph, &coordinates);
+        (graph, directed_graph, coordinate_report)
+    };
+
+    if !coordinates.is_empty() {
+        report.coordinates = Some(coordinate_report);
+    }
+
+    (graph, directed_graph)
+}
+
+
+// Load the input and build the road graphs from it, joining the coordinate file when one is given. In directed
+// mode the second graph keeps the arcs.
+pub fn load_road_graphs(
+    input: &InputOptions,
+    policy: PreprocessPolicy,
+    progress: &dyn Progress,
+) -> Result<(RoadGraphs, LoadReport), Box<dyn Error>> {
+    let format = input.format;
+
+    // Snapshots only hold undirected networks, so the directed mode always parses the input. The analyses that
+    // ignore direction run on the undirected view of the directed network.
+    let (graph, directed_graph, mut coordinates, mut report) = if input.directed {
+        let (directed_graph, coordinates, report) = parse_network::<Directed>(&input.path, format, policy, progress)?;
+        (data::undirected_network(&directed_graph), Some(directed_graph), coordinates, report)
+    } else {
+        let (graph, coordinates, report) = load_network(&input.path, format, policy, input.cache.as_deref(), progress)?;
+        (graph, None, coordinates, report)
+    };
+
+    if let Some(coordinates_path) = &input.coordinates {
+        let sidecar = data::load_coordinates(coordinates_path, data::Delimiter::Whitespace)
+            .map_err(|err| format!("failed to load coordinates from {}: {}", coordinates_path, err))?;
+        coordinates.extend(sidecar);
+    }
+
+    let graphs = build_road_graphs(graph, directed_graph, coordinates, input.largest_component, &mut report);
+    Ok((graphs, report))
+}
@@ -1,347 +1,550 @@
 // main.rs
 
-mod data;
-mod centrality;
-mod centrality_analysis;
-mod shortest_path;
-mod pagerank;
-mod network_analysis;
-mod leiden;
-mod assort;
-mod cpa;
-mod bed;
-mod cca;
-
-use petgraph::graphmap::GraphMap;
-use petgraph::graph::Graph;
-use petgraph::Undirected;
-use crate::centrality::{degree_centrality};
-use crate::centrality_analysis::analyze_centrality;
-use petgraph::graph::NodeIndex;
-use crate::shortest_path::find_shortest_paths;
-use crate::pagerank::pagerank;
-use crate::network_analysis::{degree_distribution, clustering_coefficient, network_density};
+// Command line front end of the crnsa library: parses the flags or a pipeline configuration, loads the road
+// network, and runs the analyses one after another, collecting their summaries into one report that is written
+// as JSON and Markdown.
+
+mod cli;
+
+use clap::Parser;
+use cli::Cli;
+use crnsa::assort::calculate_assortativity_coefficient;
+use crnsa::betweenness::{
+    betweenness_centrality_with_progress, select_pivots, weighted_betweenness_centrality_with_progress, Betweenness,
+};
+use crnsa::centrality::{
+    closeness_centrality_with_progress, degree_centrality, eigenvector_centrality_with_progress, in_degree_centrality,
+    katz_centrality_with_progress, normalized_degree_centrality, out_degree_centrality, weighted_degree_centrality,
+    EigenvectorOptions, KatzOptions,
+};
+use crnsa::centrality_analysis::{
+    analyze_centrality_with_progress, compare_centrality, CentralityOptions, ComparisonOptions,
+};
+use crnsa::csr::CsrGraph;
+use crnsa::network_analysis::{clustering_coefficient, degree_distribution, network_density};
+use crnsa::pagerank::pagerank_with_progress;
+use crnsa::pipeline::{self, AnalysisConfig, DegreeMeasure, OutputFormat, PipelineConfig, Provenance};
+use crnsa::progress::{measure_stage, NoProgress, Progress, ProgressBars};
+use crnsa::report::{
+    BetweennessSection, BridgeSection, CentralityComparisonSection, ClosenessSection, CommunitySection,
+    ComponentSection, CorePeripherySection, GraphSummary, RankingSection, Report, RouteSection, StatsSection, StrongComponents,
+};
+use crnsa::route::find_routes;
+use crnsa::{bed, cca, cpa, data, leiden, loader};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::{Directed, EdgeType};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
-use serde_json;
-use std::io::{BufRead, BufReader};
-use std::collections::HashMap;
-use crate::shortest_path::select_landmarks;
-use crate::shortest_path::precompute_landmark_distances;
-use crate::assort::calculate_assortativity_coefficient;
-use petgraph::algo::connected_components;
-use petgraph::visit::FilterNode;
-use std::fs;
-use std::collections::HashSet;
-use rand::Rng;
-use rand::seq::SliceRandom;
-use serde::{Serialize, Deserialize};
-
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ShortestPath {
-    index: usize,
-    weight: f64,
-    path: Vec<usize>,
-}
-
-
-// This function loads a graph from a file in text format
-fn load_graph_from_txt(file_path: &str) -> Graph<(f64, f64), f64, petgraph::Undirected> {
-    // Open the file and create a buffered reader
-    let file = File::open(file_path).expect("Unable to open the file");
-    let reader = BufReader::new(file);
 
-    // Create an empty graph
-    let mut graph = Graph::<(f64, f64), f64, petgraph::Undirected>::new_undirected();
 
-    // Create a HashMap to keep track of node indices
-    let mut node_indices = HashMap::new();
-
-    // Iterate over the lines in the file
-    for line in reader.lines() {
-        // Parse the line and split it into two node identifiers
-        let line = line.expect("Unable to read line");
-        let nodes: Vec<&str> = line.trim().split_whitespace().collect();
-
-        // If the line contains two nodes, add them to the graph
-        if nodes.len() == 2 {
-            // Parse the node identifiers into integers
-            let from_node: usize = nodes[0].parse().unwrap();
-            let to_node: usize = nodes[1].parse().unwrap();
+// The road graphs every analysis runs on, and the pipeline that describes the run. The analyses report node
+// indices of the road graph; every output translates them into the dataset ids.
+struct Analysis {
+    graph: data::RoadGraph,
+    directed_graph: Option<data::RoadGraph<Directed>>,
+    config: PipelineConfig,
+    progress: Box<dyn Progress>,
+}
 
-            // Get the indices of the nodes, adding them to the graph if necessary
-            let from_node_index = *node_indices.entry(from_node).or_insert_with(|| graph.add_node((from_node as f64, 0.0)));
-            let to_node_index = *node_indices.entry(to_node).or_insert_with(|| graph.add_node((to_node as f64, 0.0)));
+impl Analysis {
+    fn id(&self, node: NodeIndex) -> usize {
+        self.graph[node].id
+    }
 
-            // Add an edge between the nodes with weight 1.0
-            graph.add_edge(from_node_index, to_node_index, 1.0);
+    // Write the result of an analysis to the output directory, together with the configuration of the run
+    fn write_json<T: Serialize>(&self, file_name: &str, result: &T) {
+        let output = Provenance { config: &self.config, result };
+        let json_output = match self.config.output.format {
+            OutputFormat::Json => serde_json::to_string(&output),
+            OutputFormat::PrettyJson => serde_json::to_string_pretty(&output),
         }
+        .expect("Failed to serialize output to JSON");
+        self.write_file(file_name, &json_output);
     }
 
-    // Return the loaded graph
-    graph
-}
-
-
-fn get_start_end_nodes(graph: &Graph<(f64, f64), f64, Undirected>) -> (NodeIndex, NodeIndex) {
-    // Get the number of nodes in the graph
-    let num_nodes = graph.node_count();
-
-    // Choose a random start node
-    let start_node = rand::thread_rng().gen_range(0..num_nodes);
-
-    // Convert the start node index to a `NodeIndex`
-    let start_node_index = NodeIndex::new(start_node);
-
-    // Perform a breadth-first search to find all nodes connected to the start node
-    let mut connected_component = Vec::new();
-    let mut bfs = petgraph::visit::Bfs::new(graph, start_node_index);
-
-    while let Some(node) = bfs.next(graph) {
-        connected_component.push(node);
+    fn write_file(&self, file_name: &str, contents: &str) {
+        let path = self.config.output.dir.join(file_name);
+        let mut file = File::create(&path).expect("Failed to create output file");
+        file.write_all(contents.as_bytes()).expect("Failed to write output file");
+        println!("Output written to {}", path.display());
     }
 
-    // Choose a random node from the connected component as the end node
-    let end_node_index = connected_component.choose(&mut rand::thread_rng()).unwrap().to_owned();
-
-    // Return the start and end node indices as a tuple
-    (start_node_index, end_node_index)
-}
-
-
-
-fn main() {
-    let graph = data::read_and_preprocess_data("data/roadNet-CA.txt");
-
-    // data.rs, printing number of nodes and edges
-    println!("Number of nodes: {}", graph.node_count());
-    println!("Number of edges: {}", graph.edge_count());
-
-    // Centrality Analysis
-    let degree_centrality = analyze_centrality(&graph);
-
-    // Serialize the degree_centrality to a JSON string
-    let json_output = serde_json::to_string_pretty(&degree_centrality)
-        .expect("Failed to serialize degree centrality to JSON");
+    // Run one analysis and fill in its section of the report
+    fn run(&self, analysis: &AnalysisConfig, report: &mut Report) {
+        match analysis {
+            AnalysisConfig::Stats => report.stats = Some(self.stats()),
+            AnalysisConfig::Centrality(args) => {
+                let (degree_centrality, eigenvector_centrality, katz_centrality) = self.centrality(args);
+                report.degree_centrality = Some(degree_centrality);
+                report.eigenvector_centrality = eigenvector_centrality;
+                report.katz_centrality = katz_centrality;
+            }
+            AnalysisConfig::Betweenness(args) => report.betweenness = Some(self.betweenness(args)),
+            AnalysisConfig::Closeness(args) => report.closeness = Some(self.closeness(args)),
+            AnalysisConfig::Pagerank(args) => report.pagerank = Some(self.pagerank(args)),
+            AnalysisConfig::CompareCentrality(args) => report.centrality_comparison = self.compare_centrality(args),
+            AnalysisConfig::Communities(args) => report.communities = Some(self.communities(args)),
+            AnalysisConfig::Bridges(args) => report.bridges = Some(self.bridges(args)),
+            AnalysisConfig::Components => report.components = Some(self.components()),
+            AnalysisConfig::CorePeriphery(args) => report.core_periphery = Some(self.core_periphery(args)),
+            AnalysisConfig::Route(args) => report.routes = self.route(args),
+        }
+    }
 
-    // Write the JSON output to a file
-    let mut file = File::create("sample_degree_centrality.json")
-        .expect("Failed to create output file");
-    file.write_all(json_output.as_bytes())
-        .expect("Failed to write JSON to output file");
+    fn stats(&self) -> StatsSection {
+        let properties = match &self.directed_graph {
+            Some(directed_graph) => data::network_properties(directed_graph),
+            None => data::network_properties(&self.graph),
+        };
 
-    println!("Degree centrality written to sample_degree_centrality.json");
+        // The whole-graph analyses walk every adjacency row many times, so they run on the CSR form of the graph
+        let csr_graph = CsrGraph::from_graph(&self.graph, |edge: &data::RoadEdge| edge.weight);
 
-    // Deserialize the JSON file back into a HashMap
-    let json_data = fs::read_to_string("sample_degree_centrality.json")
-        .expect("Unable to read the file");
-    let degree_centrality: HashMap<usize, f64> = serde_json::from_str(&json_data)
-        .expect("Unable to deserialize JSON data");
+        // Network analysis
+        let distribution = degree_distribution(&self.graph);
+        let coefficient = clustering_coefficient(&csr_graph);
+        let density = network_density(&self.graph);
 
-    // Find the top nodes with the highest degree centrality
-    let mut top_nodes: Vec<(&usize, &f64)> = degree_centrality.iter().collect();
-    top_nodes.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap());
-    top_nodes.truncate(10);
+        // Assortativity
+        let assortativity_coefficient = calculate_assortativity_coefficient(&csr_graph);
 
-    println!("Top nodes with the highest degree centrality:");
-    for (i, (node, centrality)) in top_nodes.iter().enumerate() {
-        println!("Node {}: id={}, degree centrality={}", i + 1, node, centrality);
+        StatsSection::new(properties, distribution.to_vec(), coefficient, density, assortativity_coefficient)
     }
 
-    // Convert the UnGraphMap<usize, f64> to Graph<(f64, f64), f64, Undirected>
-    let mut graph_with_coordinates: Graph<(f64, f64), f64, Undirected> = Graph::default();
-    let node_map: std::collections::HashMap<usize, _> = graph.nodes().map(|n| {
-        (n, graph_with_coordinates.add_node((n as f64, n as f64)))
-    }).collect();
+    // Degree centrality, then eigenvector and Katz centrality, each unless its iteration fails to converge or, for
+    // Katz, the attenuation is too large for the graph
+    fn centrality(
+        &self,
+        args: &pipeline::CentralityConfig,
+    ) -> (RankingSection, Option<RankingSection>, Option<RankingSection>) {
+        // In- and out-degrees count arcs in directed mode
+        let degree_centrality = match (args.degree, &self.directed_graph) {
+            (DegreeMeasure::Raw, _) => degree_centrality(&self.graph),
+            (DegreeMeasure::Normalized, _) => normalized_degree_centrality(&self.graph),
+            (DegreeMeasure::Weighted, _) => {
+                weighted_degree_centrality(&self.graph, |edge: &data::RoadEdge| edge.weight)
+            }
+            (DegreeMeasure::In, Some(directed_graph)) => in_degree_centrality(directed_graph),
+            (DegreeMeasure::In, None) => in_degree_centrality(&self.graph),
+            (DegreeMeasure::Out, Some(directed_graph)) => out_degree_centrality(directed_graph),
+            (DegreeMeasure::Out, None) => out_degree_centrality(&self.graph),
+        };
+        let degree_centrality: HashMap<usize, f64> =
+            degree_centrality.into_iter().map(|(node, centrality)| (self.id(node), centrality)).collect();
+        // The report keeps the top nodes; the scores of every node go to their own file
+        self.write_json("sample_degree_centrality.json", &degree_centrality);
+
+        let options = EigenvectorOptions {
+            tolerance: args.tolerance,
+            max_iterations: args.max_iterations,
+            ..EigenvectorOptions::default()
+        };
+        let eigenvector_centrality =
+            match eigenvector_centrality_with_progress(&self.graph, &options, self.progress.as_ref()) {
+                Ok(scores) => {
+                    let scores: HashMap<usize, f64> =
+                        scores.into_iter().map(|(node, score)| (self.id(node), score)).collect();
+                    self.write_json("sample_eigenvector_centrality.json", &scores);
+                    Some(RankingSection::top_k(scores, args.top))
+                }
+                Err(err) => {
+                    eprintln!("Eigenvector centrality failed: {}", err);
+                    None
+                }
+            };
+
+        // Walks follow the arcs in directed mode
+        let katz_options = KatzOptions {
+            attenuation: args.attenuation,
+            tolerance: args.tolerance,
+            max_iterations: args.max_iterations,
+            ..KatzOptions::default()
+        };
+        let progress = self.progress.as_ref();
+        let katz_centrality = match &self.directed_graph {
+            Some(directed_graph) => katz_centrality_with_progress(directed_graph, &katz_options, progress),
+            None => katz_centrality_with_progress(&self.graph, &katz_options, progress),
+        };
+        let katz_centrality = match katz_centrality {
+            Ok(scores) => {
+                let scores: HashMap<usize, f64> =
+                    scores.into_iter().map(|(node, score)| (self.id(node), score)).collect();
+                self.write_json("sample_katz_centrality.json", &scores);
+                Some(RankingSection::top_k(scores, args.top))
+            }
+            Err(err) => {
+                eprintln!("Katz centrality failed: {}", err);
+                None
+            }
+        };
 
-    for (a, b, weight) in graph.all_edges() {
-        let a_node = node_map[&a];
-        let b_node = node_map[&b];
-        graph_with_coordinates.add_edge(a_node, b_node, *weight);
+        (RankingSection::top_k(degree_centrality, args.top), eigenvector_centrality, katz_centrality)
     }
 
-    // Network analysis
-    let distribution = degree_distribution(&graph_with_coordinates);
-    let coefficient = clustering_coefficient(&graph_with_coordinates);
-    let density = network_density(&graph_with_coordinates);
-
-    println!("Degree distribution: {:?}", distribution);
-    println!("Clustering coefficient: {:?}", coefficient);
-    println!("Network density: {:?}", density);
-
-    // PageRank
-    let pagerank_results = pagerank(&graph_with_coordinates, 0.85, 100);
-    println!("PageRank results:");
-    for (node, rank) in pagerank_results.iter().take(10) {
-        println!("Node: {:?}, Rank: {}", node, rank);
+    // `k` random sources for the sampled centralities, or None to search from every node when `exact`. They draw
+    // from a generator of their own, so they are the same whether an analysis runs alone or in a pipeline.
+    fn sample_sources<Ty: EdgeType>(
+        &self,
+        graph: &data::RoadGraph<Ty>,
+        k: usize,
+        exact: bool,
+    ) -> Option<Vec<NodeIndex>> {
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        (!exact).then(|| select_pivots(graph, k, &mut rng))
     }
 
-    // Assortativity
-    let assortativity_coefficient = calculate_assortativity_coefficient(&graph_with_coordinates);
-    println!("Assortativity Coefficient: {}", assortativity_coefficient);
-
-    // Leiden Implementation
-    let community_assignments = leiden::leiden_communities(&graph_with_coordinates);
-
-    // Serialize the community_assignments to a JSON string
-    let json_output = serde_json::to_string_pretty(&community_assignments)
-        .expect("Failed to serialize community assignments to JSON");
-
-    // Write the JSON output to a file
-    let mut file = File::create("sample_leiden_output.json")
-        .expect("Failed to create output file");
-    file.write_all(json_output.as_bytes())
-        .expect("Failed to write JSON to output file");
-
-    println!("Leiden community assignments written to sample_leiden_output.json");
-
-    // Deserialize the JSON file back into a HashMap
-    let json_data = fs::read_to_string("sample_leiden_output.json")
-        .expect("Unable to read the file");
-    let community_assignments: HashMap<usize, usize> = serde_json::from_str(&json_data)
-        .expect("Unable to deserialize JSON data");
-
-    // Create a new HashMap to store community members
-    let mut community_members: HashMap<usize, Vec<usize>> = HashMap::new();
-
-    for (node, community) in community_assignments.iter() {
-        community_members.entry(*community).or_insert_with(Vec::new).push(*node);
+    fn betweenness(&self, args: &pipeline::BetweennessConfig) -> BetweennessSection {
+        // Paths follow the arcs in directed mode
+        match &self.directed_graph {
+            Some(directed_graph) => self.betweenness_of(directed_graph, args),
+            None => self.betweenness_of(&self.graph, args),
+        }
     }
 
-    // Sort the community_members by community size
-    let mut communities_sorted: Vec<(usize, Vec<usize>)> = community_members.into_iter().collect();
-    communities_sorted.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
-
-    // Print a summary of communities and their sizes
-    println!("Number of communities: {}", communities_sorted.len());
-    println!("Top communities and their sizes:");
-
-    // If you want to limit the number of communities displayed in the summary, you can change the loop like this:
-    let num_top_communities = 10; // Change this to the number of top communities you want to display
-    for (i, (community_id, members)) in communities_sorted.iter().take(num_top_communities).enumerate() {
-        println!("Community {}: id={}, size={}", i + 1, community_id, members.len());
+    fn betweenness_of<Ty: EdgeType>(
+        &self,
+        graph: &data::RoadGraph<Ty>,
+        args: &pipeline::BetweennessConfig,
+    ) -> BetweennessSection {
+        let pivots = self.sample_sources(graph, args.pivots, args.exact);
+        let progress = self.progress.as_ref();
+        let betweenness: Betweenness<NodeIndex, EdgeIndex> = if args.weighted {
+            let edge_weight = |edge: &data::RoadEdge| edge.weight;
+            weighted_betweenness_centrality_with_progress(graph, edge_weight, pivots.as_deref(), true, progress)
+        } else {
+            betweenness_centrality_with_progress(graph, pivots.as_deref(), true, progress)
+        };
+
+        let node_scores: HashMap<usize, f64> =
+            betweenness.nodes.iter().map(|(&node, &score)| (self.id(node), score)).collect();
+        self.write_json("sample_betweenness_centrality.json", &node_scores);
+        // An undirected edge is given by its endpoints with the smaller id first, an arc from source to target
+        let edge_scores = betweenness.edges.iter().map(|(&edge, &score)| {
+            let (source, target) = graph.edge_endpoints(edge).expect("scored edges are in the graph");
+            let (source, target) = (self.id(source), self.id(target));
+            let endpoints = if Ty::is_directed() { (source, target) } else { (source.min(target), source.max(target)) };
+            (endpoints, score)
+        });
+
+        BetweennessSection {
+            weighted: args.weighted,
+            sources: betweenness.sources,
+            exact: betweenness.is_exact(),
+            confidence: args.confidence,
+            node_error_bound: betweenness.node_error_bound(args.confidence),
+            edge_error_bound: betweenness.edge_error_bound(args.confidence),
+            top: RankingSection::top_k(node_scores, args.top).top,
+            top_edges: BetweennessSection::top_edges(edge_scores, args.top),
+        }
     }
 
-    // Core-Periphery Analysis
-    let degree_threshold = 10; // You can adjust this threshold based on your analysis
-    let (core_nodes, periphery_nodes) = cpa::core_periphery_analysis(&graph_with_coordinates, degree_threshold);
-
-    // Print the results of the core-periphery analysis
-    println!("Number of core nodes: {}", core_nodes.len());
-    println!("Number of periphery nodes: {}", periphery_nodes.len());
-
-    // Sort the core and periphery nodes by degree and take the top ten
-    let mut sorted_core_nodes: Vec<_> = core_nodes.iter().collect();
-    sorted_core_nodes.sort_by_key(|node| graph_with_coordinates.neighbors(**node).count());
-    let top_core_nodes = sorted_core_nodes.iter().rev().take(10).collect::<Vec<_>>();
-
-    let mut sorted_periphery_nodes: Vec<_> = periphery_nodes.iter().collect();
-    sorted_periphery_nodes.sort_by_key(|node| graph_with_coordinates.neighbors(**node).count());
-    let top_periphery_nodes = sorted_periphery_nodes.iter().rev().take(10).collect::<Vec<_>>();
+    fn closeness(&self, args: &pipeline::ClosenessConfig) -> ClosenessSection {
+        // Distances follow the arcs in directed mode
+        match &self.directed_graph {
+            Some(directed_graph) => self.closeness_of(directed_graph, args),
+            None => self.closeness_of(&self.graph, args),
+        }
+    }
 
-    // Print the top ten core and periphery nodes
-    println!("Top 10 core nodes: {:?}", top_core_nodes);
-    println!("Top 10 periphery nodes: {:?}", top_periphery_nodes);
+    fn closeness_of<Ty: EdgeType + Sync>(
+        &self,
+        graph: &data::RoadGraph<Ty>,
+        args: &pipeline::ClosenessConfig,
+    ) -> ClosenessSection {
+        let sources = self.sample_sources(graph, args.sources, args.exact);
+        let weighted = args.weighted;
+        let edge_weight = |edge: &data::RoadEdge| if weighted { edge.weight } else { 1.0 };
+        let progress = self.progress.as_ref();
+        let centrality = closeness_centrality_with_progress(graph, edge_weight, sources.as_deref(), progress);
+
+        let closeness: HashMap<usize, f64> =
+            centrality.closeness.iter().map(|(&node, &score)| (self.id(node), score)).collect();
+        let harmonic: HashMap<usize, f64> =
+            centrality.harmonic.iter().map(|(&node, &score)| (self.id(node), score)).collect();
+        self.write_json("sample_closeness_centrality.json", &closeness);
+        self.write_json("sample_harmonic_centrality.json", &harmonic);
+
+        ClosenessSection {
+            weighted,
+            sources: centrality.sources,
+            exact: centrality.is_exact(),
+            closeness: RankingSection::top_k(closeness, args.top).top,
+            harmonic: RankingSection::top_k(harmonic, args.top).top,
+        }
+    }
 
-    // Define a custom threshold value for the number of bridge edges to display
-    let bridge_edges_threshold: usize = 10; // You can change this value later
+    fn compare_centrality(&self, args: &pipeline::CompareCentralityConfig) -> Option<CentralityComparisonSection> {
+        // Paths, walks and rank follow the arcs in directed mode
+        match &self.directed_graph {
+            Some(directed_graph) => self.compare_centrality_of(directed_graph, args),
+            None => self.compare_centrality_of(&self.graph, args),
+        }
+    }
 
-    // Find bridge edges
-    let bridge_edges = bed::find_bridge_edges(&graph_with_coordinates);
-    println!("Number of bridge edges: {}", bridge_edges.len());
+    fn compare_centrality_of<Ty: EdgeType + Sync>(
+        &self,
+        graph: &data::RoadGraph<Ty>,
+        args: &pipeline::CompareCentralityConfig,
+    ) -> Option<CentralityComparisonSection> {
+        let sources = self.sample_sources(graph, args.sources, args.exact);
+        let options = CentralityOptions {
+            measures: args.measures.clone(),
+            katz: KatzOptions { attenuation: args.attenuation, ..KatzOptions::default() },
+            ..CentralityOptions::default()
+        };
+        let table =
+            match analyze_centrality_with_progress(graph, &options, sources.as_deref(), self.progress.as_ref()) {
+                Ok(table) => table.map_nodes(|node| self.id(node)),
+                Err(err) => {
+                    eprintln!("Centrality comparison failed: {}", err);
+                    return None;
+                }
+            };
+        self.write_json("sample_centrality_table.json", &table);
+
+        let options = ComparisonOptions {
+            top_k: args.top_k,
+            high_percentile: args.high_percentile,
+            low_percentile: args.low_percentile,
+        };
+        let comparison = compare_centrality(&table, &options);
+        let source_count = sources.map_or(graph.node_count(), |sources| sources.len());
+        Some(CentralityComparisonSection {
+            measures: table.measures,
+            sources: source_count,
+            exact: source_count >= graph.node_count(),
+            top_k: args.top_k,
+            high_percentile: args.high_percentile,
+            low_percentile: args.low_percentile,
+            pairs: comparison.pairs,
+            outlier_count: comparison.outliers.len(),
+            outliers: comparison.outliers.into_iter().take(args.top).collect(),
+        })
+    }
 
-    // Convert the HashSet to a Vec
-    let bridge_edges_vec: Vec<_> = bridge_edges.into_iter().collect();
+    fn pagerank(&self, args: &pipeline::PagerankConfig) -> RankingSection {
+        // Rank follows the arcs in directed mode. The CSR keeps the node indices of the road graph, and both
+        // road graphs number the nodes alike, so `id` translates the results.
+        let pagerank_results = match &self.directed_graph {
+            Some(directed_graph) => {
+                let csr_graph = CsrGraph::from_graph(directed_graph, |edge: &data::RoadEdge| edge.weight);
+                pagerank_with_progress(&csr_graph, args.damping, args.iterations, self.progress.as_ref())
+            }
+            None => {
+                let csr_graph = CsrGraph::from_graph(&self.graph, |edge: &data::RoadEdge| edge.weight);
+                pagerank_with_progress(&csr_graph, args.damping, args.iterations, self.progress.as_ref())
+            }
+        };
+        RankingSection::top_k(pagerank_results.into_iter().map(|(node, rank)| (self.id(node), rank)), args.top)
+    }
 
-    // Print bridge edges up to the threshold value
-    println!("Bridge edges (up to {}):", bridge_edges_threshold);
-    for (i, edge) in bridge_edges_vec.iter().take(bridge_edges_threshold).enumerate() {
-        println!("Bridge edge {}: {:?}", i + 1, edge);
+    fn communities(&self, args: &pipeline::CommunitiesConfig) -> CommunitySection {
+        // Leiden Implementation
+        let community_assignments: HashMap<usize, usize> =
+            leiden::leiden_communities_with_progress(&self.graph, |edge: &data::RoadEdge| edge.weight, self.progress.as_ref())
+                .into_iter()
+                .map(|(node, community)| (self.id(node), community))
+                .collect();
+        self.write_json("sample_leiden_output.json", &community_assignments);
+        CommunitySection::from_assignments(&community_assignments, args.top)
     }
 
-    // Call the analyze_connected_components function with the graph_with_coordinates
-    let (num_components, _) = cca::analyze_connected_components(&graph_with_coordinates);
+    fn core_periphery(&self, args: &pipeline::CorePeripheryConfig) -> CorePeripherySection {
+        let (core_nodes, periphery_nodes) = cpa::core_periphery_analysis(&self.graph, args.degree_threshold);
+        let (core_count, periphery_count) = (core_nodes.len(), periphery_nodes.len());
+
+        // Sort the core and periphery nodes by degree and take the top ones, the smaller id first among equals
+        let top_by_degree = |nodes: HashSet<NodeIndex>| {
+            let mut sorted_nodes: Vec<NodeIndex> = nodes.into_iter().collect();
+            sorted_nodes.sort_by_key(|&node| (std::cmp::Reverse(self.graph.neighbors(node).count()), self.id(node)));
+            sorted_nodes.into_iter().take(args.top).map(|node| self.id(node)).collect::<Vec<_>>()
+        };
+
+        CorePeripherySection {
+            degree_threshold: args.degree_threshold,
+            core_count,
+            periphery_count,
+            top_core: top_by_degree(core_nodes),
+            top_periphery: top_by_degree(periphery_nodes),
+        }
+    }
 
-    // Print the number of connected components
-    println!("Number of connected components: {}", num_components);
+    fn bridges(&self, args: &pipeline::BridgesConfig) -> BridgeSection {
+        // Find bridge edges
+        let bridge_edges = bed::find_bridge_edges_with_progress(&self.graph, self.progress.as_ref());
 
-    // Select landmarks
-    let num_landmarks = 10; // You can adjust this number based on your graph size and desired performance
-    let landmarks = select_landmarks(&graph_with_coordinates, num_landmarks);
+        // Convert the HashSet to a sorted Vec of dataset id pairs, smaller id first, and keep up to the limit
+        let mut bridge_edges_vec: Vec<_> = bridge_edges
+            .iter()
+            .map(|&(a, b)| (self.id(a).min(self.id(b)), self.id(a).max(self.id(b))))
+            .collect();
+        bridge_edges_vec.sort_unstable();
+        bridge_edges_vec.truncate(args.limit);
 
-    // Precompute landmark distances
-    let landmark_distances = precompute_landmark_distances(&graph_with_coordinates, &landmarks);
+        BridgeSection { count: bridge_edges.len(), sample: bridge_edges_vec }
+    }
 
-    // Get starting and ending nodes
-    let (start_node, end_node) = get_start_end_nodes(&graph_with_coordinates);
+    // None when the given endpoints are not nodes of the graph, or the graph has no nodes to route between
+    fn route(&self, args: &pipeline::RouteConfig) -> Option<RouteSection> {
+        // The route draws from its own generator, so it is the same whether it runs alone or in a pipeline
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        let options = args.options();
+
+        // Routes respect one-way streets in directed mode
+        let shortest_paths = match &self.directed_graph {
+            Some(directed_graph) => find_routes(directed_graph, &options, &mut rng, self.progress.as_ref()),
+            None => find_routes(&self.graph, &options, &mut rng, self.progress.as_ref()),
+        };
+        if let (None, Some((from, to))) = (&shortest_paths, options.endpoints) {
+            eprintln!("Route endpoints {} and {} must both be nodes of the network", from, to);
+        }
+        shortest_paths.map(|paths| RouteSection { paths })
+    }
 
-    // Select landmarks
-    let num_landmarks = 10; // You can adjust this number based on your graph size and desired performance
-    let landmarks = select_landmarks(&graph_with_coordinates, num_landmarks);
+    fn components(&self) -> ComponentSection {
+        // Call the analyze_connected_components function with the graph
+        let (num_components, components) = cca::analyze_connected_components(&self.graph);
+
+        // Nodes outside the largest strongly connected component can be entered or left, but not both
+        let strongly_connected = self.directed_graph.as_ref().map(|directed_graph| {
+            let (num_strong_components, strong_components) =
+                cca::analyze_strongly_connected_components(directed_graph);
+            let largest = strong_components.first().map_or(0, Vec::len);
+            StrongComponents { count: num_strong_components, outside_largest: directed_graph.node_count() - largest }
+        });
+
+        ComponentSection {
+            count: num_components,
+            largest_size: components.first().map_or(0, Vec::len),
+            strongly_connected,
+        }
+    }
+}
 
-    // Precompute landmark distances
-    let landmark_distances = precompute_landmark_distances(&graph_with_coordinates, &landmarks);
 
-    // Get starting and ending nodes
-    let (start_node, end_node) = get_start_end_nodes(&graph_with_coordinates);
+// Show what the loader did: where the network came from, what it had to skip and how the graphs were restricted
+fn print_load_report(input: &str, report: &loader::LoadReport) {
+    if let Some(cache) = &report.cache {
+        match &cache.lookup {
+            loader::CacheLookup::Hit => println!("Loaded cached snapshot {}", cache.path),
+            loader::CacheLookup::Missing => {}
+            loader::CacheLookup::Stale => eprintln!("Snapshot {} is stale, rebuilding it", cache.path),
+            loader::CacheLookup::Unreadable(err) => eprintln!("Ignoring snapshot {}: {}", cache.path, err),
+        }
+        if let Some(err) = &cache.write_error {
+            eprintln!("Failed to write snapshot {}: {}", cache.path, err);
+        }
+    }
 
-    // Shortest Path
-    let shortest_paths = find_shortest_paths(&graph_with_coordinates, start_node, end_node, &landmark_distances, 10);
-    match shortest_paths.len() {
-        0 => {
-            println!("No path found between the starting and ending nodes.");
+    match &report.parse {
+        Some(loader::ParseReport::Dimacs(header)) => {
+            println!("Read DIMACS graph with {} nodes and {} arcs", header.nodes, header.arcs);
         }
-        _ => {
-            let mut shortest_paths_output = Vec::new();
-            for (i, (path_weight, path)) in shortest_paths.iter().enumerate() {
-                let mut current_node = start_node;
-                let mut path_indices = vec![current_node.index()];
-                while current_node != end_node {
-                    match path.get(&current_node) {
-                        Some(next_node) => {
-                            current_node = *next_node;
-                            path_indices.push(current_node.index());
-                        }
-                        None => {
-                            break;
-                        }
-                    }
-                }
-                shortest_paths_output.push(ShortestPath {
-                    index: i + 1,
-                    weight: *path_weight,
-                    path: path_indices,
-                });
+        Some(loader::ParseReport::Osm { ways_imported, .. }) => {
+            println!("Imported {} highway ways from {}", ways_imported, input);
+        }
+        Some(loader::ParseReport::EdgeList { throughput, .. }) => println!(
+            "Parsed {} lines in {} chunks on {} threads in {:.2?} ({:.1} MB/s, {:.0} lines/s)",
+            throughput.lines,
+            throughput.chunks,
+            throughput.threads,
+            throughput.elapsed,
+            throughput.megabytes_per_second(),
+            throughput.lines_per_second()
+        ),
+        None => {}
+    }
+    if let Some(preprocess) = &report.preprocess {
+        println!("Preprocessing found {}", preprocess);
+    }
+    match &report.parse {
+        Some(loader::ParseReport::Osm { missing_nodes, .. }) if *missing_nodes > 0 => {
+            eprintln!("{} referenced nodes were not part of the extract", missing_nodes);
+        }
+        // Report any lines the loader had to skip
+        Some(loader::ParseReport::EdgeList { skipped_lines, bad_lines, .. }) if *skipped_lines > 0 => {
+            eprintln!("Skipped {} malformed lines while loading:", skipped_lines);
+            for err in bad_lines {
+                eprintln!("  {}", err);
             }
-
-            let json_output = serde_json::to_string_pretty(&shortest_paths_output).unwrap();
-
-            let mut file = File::create("sample_shortest_paths.json").expect("Unable to create file");
-            file.write_all(json_output.as_bytes()).expect("Unable to write data");
-            println!("Shortest paths saved to sample_shortest_paths.json");
         }
+        _ => {}
+    }
+
+    if let Some(component) = &report.component {
+        println!(
+            "Restricted to the largest weakly connected component: {} of {} nodes",
+            component.nodes, component.total_nodes
+        );
+    }
+    if let Some(coordinates) = &report.coordinates {
+        println!(
+            "Nodes with coordinates: {} ({} missing, {} unused coordinate entries)",
+            coordinates.nodes_with_coordinates,
+            coordinates.missing_nodes.len(),
+            coordinates.unused_coordinates
+        );
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_load_graph_from_txt() {
-        let graph = load_graph_from_txt("test_data/test_graph.txt");
+fn main() {
+    let cli = Cli::parse();
+
+    // A configuration file describes the whole run; otherwise a subcommand runs one analysis and `--all` runs them all
+    let config = match &cli.config {
+        Some(config_path) => {
+            cli.commands().unwrap_or_else(|err| err.exit());
+            PipelineConfig::load(config_path).unwrap_or_else(|err| {
+                eprintln!("Failed to load pipeline configuration {}: {}", config_path.display(), err);
+                std::process::exit(1);
+            })
+        }
+        None => cli.pipeline_config().unwrap_or_else(|err| err.exit()),
+    };
 
-        assert_eq!(graph.node_count(), 4);
-        assert_eq!(graph.edge_count(), 3);
-    }
+    let progress: Box<dyn Progress> = if config.output.progress { Box::new(ProgressBars::new()) } else { Box::new(NoProgress) };
 
-    #[test]
-    fn test_get_start_end_nodes() {
-        let graph = load_graph_from_txt("test_data/test_graph.txt");
-        let (start_node, end_node) = get_start_end_nodes(&graph);
+    let (graphs, load_timing) =
+        measure_stage("load", || loader::load_road_graphs(&config.input, config.preprocess, progress.as_ref()));
+    let (graph, directed_graph) = match graphs {
+        Ok((graphs, load_report)) => {
+            print_load_report(&config.input.path, &load_report);
+            graphs
+        }
+        Err(err) => {
+            eprintln!("Failed to load road network: {}", err);
+            std::process::exit(1);
+        }
+    };
 
-        assert!(graph.node_indices().any(|n| n == start_node));
-        assert!(graph.node_indices().any(|n| n == end_node));
+    if let Err(err) = std::fs::create_dir_all(&config.output.dir) {
+        eprintln!("Failed to create output directory {}: {}", config.output.dir.display(), err);
+        std::process::exit(1);
     }
+
+    let mut report = Report::new(GraphSummary {
+        nodes: graph.node_count(),
+        edges: graph.edge_count(),
+        arcs: directed_graph.as_ref().map(|directed_graph| directed_graph.edge_count()),
+    });
+    report.stages.push(load_timing);
+
+    // Every analysis is a stage of its own, timed and measured for the report
+    let analysis = Analysis { graph, directed_graph, config, progress };
+    for step in &analysis.config.analyses {
+        let ((), timing) = measure_stage(step.name(), || analysis.run(step, &mut report));
+        report.stages.push(timing);
+    }
+
+    // The report goes to the output directory as JSON and Markdown, and the Markdown to the console
+    analysis.write_json("report.json", &report);
+    let markdown = report.to_markdown();
+    analysis.write_file("report.md", &markdown);
+    println!();
+    print!("{}", markdown);
 }
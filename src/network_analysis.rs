@@ -1,16 +1,19 @@
 // network_analysis.rs
 
-use petgraph::graph::NodeIndex;
-use petgraph::{Graph, Undirected};
-use ndarray::{Array1, Axis};
-use ndarray_stats::QuantileExt;
-
-// Define a function called `degree_distribution` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges. The function computes the degree distribution of the nodes in the graph and returns it as a one-dimensional `Array1` object.
-pub fn degree_distribution(graph: &Graph<(f64, f64), f64, Undirected>) -> Array1<usize> {
+use petgraph::visit::{EdgeCount, IntoNeighbors, IntoNodeIdentifiers, NodeCount};
+use ndarray::Array1;
+
+// Define a function called `degree_distribution` that takes any undirected petgraph graph reference that can list its nodes and their neighbors, such as `&RoadGraph` or `&RoadNetwork`. The function computes the degree distribution of the nodes in the graph and returns it as a one-dimensional `Array1` object.
+pub fn degree_distribution<G>(graph: G) -> Array1<usize>
+where
+    G: IntoNodeIdentifiers + IntoNeighbors,
+{
     // Compute the degree of each node and store it in a vector.
-    let degrees: Vec<usize> = graph.node_indices().map(|n| graph.neighbors(n).count()).collect();
-    // Compute the maximum degree in the graph.
-    let max_degree = *degrees.iter().max().unwrap();
+    let degrees: Vec<usize> = graph.node_identifiers().map(|n| graph.neighbors(n).count()).collect();
+    // Compute the maximum degree in the graph. A graph without nodes has an empty distribution.
+    let Some(&max_degree) = degrees.iter().max() else {
+        return Array1::zeros(0);
+    };
     // Initialize an array of zeros with a length of `max_degree + 1` to store the degree distribution.
     let mut distribution = Array1::zeros(max_degree + 1);
 
@@ -24,17 +27,20 @@ pub fn degree_distribution(graph: &Graph<(f64, f64), f64, Undirected>) -> Array1
 }
 
 
-// Define a function called `clustering_coefficient` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges. The function computes the clustering coefficient of the graph and returns it as a `f64` value.
-pub fn clustering_coefficient(graph: &Graph<(f64, f64), f64, Undirected>) -> f64 {
-    // Get the node indices of the graph.
-    let nodes = graph.node_indices();
+// Define a function called `clustering_coefficient` that takes any undirected petgraph graph reference that can list its nodes and their neighbors, such as `&RoadGraph` or `&RoadNetwork`. The function computes the clustering coefficient of the graph and returns it as a `f64` value.
+pub fn clustering_coefficient<G>(graph: G) -> f64
+where
+    G: IntoNodeIdentifiers + IntoNeighbors + NodeCount,
+{
+    // Get the node identifiers of the graph.
+    let nodes = graph.node_identifiers();
     // Initialize a variable to store the total clustering coefficient.
     let mut total_coefficient = 0.0;
 
     // Iterate over all nodes.
     for node in nodes {
         // Get the neighbors of the current node and store them in a vector.
-        let neighbors: Vec<NodeIndex> = graph.neighbors(node).collect();
+        let neighbors: Vec<G::NodeId> = graph.neighbors(node).collect();
         // Get the degree of the current node.
         let k = neighbors.len();
 
@@ -46,7 +52,7 @@ pub fn clustering_coefficient(graph: &Graph<(f64, f64), f64, Undirected>) -> f64
             // Iterate over all pairs of neighbors and count the number of pairs that are connected by an edge.
             for i in 0..k {
                 for j in (i + 1)..k {
-                    if graph.contains_edge(neighbors[i], neighbors[j]) {
+                    if graph.neighbors(neighbors[i]).any(|neighbor| neighbor == neighbors[j]) {
                         connected_neighbors += 1;
                     }
                 }
@@ -58,17 +64,29 @@ pub fn clustering_coefficient(graph: &Graph<(f64, f64), f64, Undirected>) -> f64
         }
     }
 
-    // Compute the average clustering coefficient of the graph and return it.
+    // Compute the average clustering coefficient of the graph and return it. A graph without nodes has no
+    // triangles, so its coefficient is 0.
+    if graph.node_count() == 0 {
+        return 0.0;
+    }
     total_coefficient / (graph.node_count() as f64)
 }
 
 
-// Define a function called `network_density` that takes a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges. The function computes the network density of the graph and returns it as a `f64` value.
-pub fn network_density(graph: &Graph<(f64, f64), f64, Undirected>) -> f64 {
+// Define a function called `network_density` that takes any undirected petgraph graph reference that can count its nodes and edges. The function computes the network density of the graph and returns it as a `f64` value.
+pub fn network_density<G>(graph: G) -> f64
+where
+    G: NodeCount + EdgeCount,
+{
     // Get the number of nodes and edges in the graph and convert them to `f64` values.
     let node_count = graph.node_count() as f64;
     let edge_count = graph.edge_count() as f64;
 
+    // A graph with fewer than two nodes has no possible edges, so its density is taken as 0.
+    if graph.node_count() < 2 {
+        return 0.0;
+    }
+
     // Compute the network density of the graph using the formula: (2 * E) / (N * (N - 1)).
     (2.0 * edge_count) / (node_count * (node_count - 1.0))
 }
@@ -79,15 +97,16 @@ pub fn network_density(graph: &Graph<(f64, f64), f64, Undirected>) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::{build_road_graph, NodeCoordinates, RoadEdge, RoadGraph, RoadNetwork, RoadNode};
 
-    fn create_test_graph() -> Graph<(f64, f64), f64, Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 0.0));
-        let c = graph.add_node((1.0, 1.0));
+    fn create_test_graph() -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let a = graph.add_node(RoadNode { id: 0, position: Some((0.0, 0.0)) });
+        let b = graph.add_node(RoadNode { id: 1, position: Some((1.0, 0.0)) });
+        let c = graph.add_node(RoadNode { id: 2, position: Some((1.0, 1.0)) });
 
-        graph.add_edge(a, b, 1.0);
-        graph.add_edge(b, c, 1.0);
+        graph.add_edge(a, b, RoadEdge::default());
+        graph.add_edge(b, c, RoadEdge::default());
 
         graph
     }
@@ -99,6 +118,31 @@ mod tests {
 
         assert_eq!(coefficient, 0.0);
     }
+
+    #[test]
+    fn test_measures_of_tiny_graphs() {
+        let mut graph = RoadGraph::new_undirected();
+        assert_eq!(degree_distribution(&graph).len(), 0);
+        assert_eq!(clustering_coefficient(&graph), 0.0);
+        assert_eq!(network_density(&graph), 0.0);
+
+        graph.add_node(RoadNode { id: 0, position: None });
+        assert_eq!(degree_distribution(&graph).to_vec(), vec![1]);
+        assert_eq!(clustering_coefficient(&graph), 0.0);
+        assert_eq!(network_density(&graph), 0.0);
+    }
+
+    #[test]
+    fn test_measures_agree_across_graph_types() {
+        // A triangle with a pendant node, once as a GraphMap keyed by id and once as a road graph
+        let network = RoadNetwork::from_edges([(1, 2), (2, 3), (3, 1), (3, 4)]);
+        let (graph, _) = build_road_graph(network.clone(), &NodeCoordinates::new());
+
+        assert_eq!(clustering_coefficient(&network), clustering_coefficient(&graph));
+        assert!((clustering_coefficient(&network) - 7.0 / 12.0).abs() < 1e-12);
+        assert_eq!(degree_distribution(&network), degree_distribution(&graph));
+        assert_eq!(network_density(&network), network_density(&graph));
+    }
 }
 
 
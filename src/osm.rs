@@ -0,0 +1,508 @@
+// osm.rs
+
+use crate::data::{open_input, Codec, HighwayClass, NodeCoordinates, RoadEdge, RoadNetwork, RoadNetworkOf};
+use crate::preprocess::{EdgeNormalizer, PreprocessPolicy, PreprocessReport};
+use osmpbf::{Element, ElementReader};
+use petgraph::{EdgeType, Undirected};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+// Mean Earth radius in meters, used by the haversine formula
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+// Errors that can occur while importing an OpenStreetMap extract
+#[derive(Debug)]
+pub enum OsmError {
+    // The extract could not be opened or read
+    Io(io::Error),
+    // The XML extract is not well-formed
+    Xml(quick_xml::Error),
+    // The PBF extract could not be decoded
+    Pbf(osmpbf::Error),
+    // An element is missing a required attribute or the attribute is malformed
+    InvalidElement(String),
+}
+
+impl fmt::Display for OsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OsmError::Io(err) => write!(f, "I/O error: {}", err),
+            OsmError::Xml(err) => write!(f, "OSM XML error: {}", err),
+            OsmError::Pbf(err) => write!(f, "OSM PBF error: {}", err),
+            OsmError::InvalidElement(message) => write!(f, "invalid OSM element: {}", message),
+        }
+    }
+}
+
+impl Error for OsmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OsmError::Io(err) => Some(err),
+            OsmError::Xml(err) => Some(err),
+            OsmError::Pbf(err) => Some(err),
+            OsmError::InvalidElement(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for OsmError {
+    fn from(err: io::Error) -> Self {
+        OsmError::Io(err)
+    }
+}
+
+impl From<quick_xml::Error> for OsmError {
+    fn from(err: quick_xml::Error) -> Self {
+        OsmError::Xml(err)
+    }
+}
+
+impl From<osmpbf::Error> for OsmError {
+    fn from(err: osmpbf::Error) -> Self {
+        OsmError::Pbf(err)
+    }
+}
+
+// Encodings of OpenStreetMap extracts supported by the importer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsmFormat {
+    Xml,
+    Pbf,
+}
+
+impl OsmFormat {
+    // Guess the format from the file name, returning None for files that do not look like OSM extracts.
+    // XML extracts may carry any compression extension; the codec itself is detected from the content.
+    pub fn from_path(file_path: &str) -> Option<Self> {
+        if file_path.ends_with(".pbf") {
+            Some(OsmFormat::Pbf)
+        } else if Codec::strip_extension(file_path).ends_with(".osm") {
+            Some(OsmFormat::Xml)
+        } else {
+            None
+        }
+    }
+}
+
+// The road network built from an OpenStreetMap extract. The network is undirected unless the import was
+// asked for a directed graph, in which case one-way streets only get an arc in their direction of travel.
+#[derive(Debug)]
+pub struct OsmImport<N = RoadNetwork> {
+    // Intersections and way endpoints as nodes, keyed by their OSM node id
+    pub network: N,
+    // Position of every node in the network
+    pub coordinates: NodeCoordinates,
+    // Number of highway ways that contributed at least one edge
+    pub ways_imported: usize,
+    // Number of distinct node references whose coordinates are not part of the extract
+    pub missing_nodes: usize,
+    // Self-loops, parallel segments and reciprocal segments met while building the network
+    pub preprocess: PreprocessReport,
+}
+
+// Directions in which a way may be travelled, relative to the order of its node references
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Oneway {
+    Both,
+    Forward,
+    Backward,
+}
+
+impl Oneway {
+    // Interpret an explicit `oneway=*` value; unknown values such as `reversible` leave the default in place
+    fn from_osm_tag(value: &str) -> Option<Self> {
+        match value {
+            "yes" | "true" | "1" => Some(Oneway::Forward),
+            "-1" | "reverse" => Some(Oneway::Backward),
+            "no" | "false" | "0" => Some(Oneway::Both),
+            _ => None,
+        }
+    }
+}
+
+// A drivable way as read from the extract, before it is split at intersections
+struct OsmWay {
+    refs: Vec<i64>,
+    highway: HighwayClass,
+    lanes: Option<u32>,
+    oneway: Oneway,
+}
+
+impl OsmWay {
+    // Build a way from its node references and tags, returning None for ways that are not drivable roads
+    fn from_tags<'a>(refs: Vec<i64>, tags: impl Iterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut highway = None;
+        let mut lanes = None;
+        let mut is_area = false;
+        let mut oneway = None;
+        let mut is_roundabout = false;
+
+        for (key, value) in tags {
+            match key {
+                "highway" => highway = HighwayClass::from_osm_tag(value),
+                "lanes" => lanes = value.parse::<u32>().ok(),
+                "area" => is_area = value == "yes",
+                "oneway" => oneway = Oneway::from_osm_tag(value),
+                "junction" => is_roundabout = value == "roundabout" || value == "circular",
+                _ => {}
+            }
+        }
+
+        // Closed areas such as parking lots are tagged as highways but are not road segments
+        if is_area || refs.len() < 2 {
+            return None;
+        }
+        let highway = highway?;
+
+        // Motorways and roundabouts are one-way unless tagged otherwise
+        let implied_oneway = highway == HighwayClass::Motorway || is_roundabout;
+        let oneway = oneway.unwrap_or(if implied_oneway { Oneway::Forward } else { Oneway::Both });
+        Some(OsmWay { refs, highway, lanes, oneway })
+    }
+}
+
+// Function to compute the great-circle distance in meters between two (longitude, latitude) points
+pub fn haversine_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (a.0.to_radians(), a.1.to_radians());
+    let (lon2, lat2) = (b.0.to_radians(), b.1.to_radians());
+
+    let d_lat = lat2 - lat1;
+    let d_lon = lon2 - lon1;
+    let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_METERS * h.sqrt().asin()
+}
+
+// Function to import the drivable road network from a local OSM extract, picking the format from the file name
+// (`.pbf` for PBF, anything else is read as XML). Like the other library loaders it keeps every road segment as
+// read; `import_osm_as` takes a preprocessing policy.
+pub fn import_osm(file_path: &str) -> Result<OsmImport, OsmError> {
+    import_osm_as::<Undirected>(file_path, PreprocessPolicy::default())
+}
+
+// Function to import the drivable road network into a graph of the given edge type, applying the preprocessing
+// policy to the road segments. With `Directed`, `oneway=*` tags (and the one-way default of motorways and
+// roundabouts) decide which arcs are added.
+pub fn import_osm_as<Ty: EdgeType>(
+    file_path: &str,
+    policy: PreprocessPolicy,
+) -> Result<OsmImport<RoadNetworkOf<Ty>>, OsmError> {
+    let format = OsmFormat::from_path(file_path).unwrap_or(OsmFormat::Xml);
+    import_osm_with_format(file_path, format, policy)
+}
+
+// Function to import the drivable road network from a local OSM extract in the given format.
+// The extract is read twice: once to collect the highway ways, and once to collect the
+// coordinates of only those nodes the ways reference.
+pub fn import_osm_with_format<Ty: EdgeType>(
+    file_path: &str,
+    format: OsmFormat,
+    policy: PreprocessPolicy,
+) -> Result<OsmImport<RoadNetworkOf<Ty>>, OsmError> {
+    let ways = match format {
+        OsmFormat::Xml => read_xml_ways(file_path)?,
+        OsmFormat::Pbf => read_pbf_ways(file_path)?,
+    };
+
+    let needed: HashSet<i64> = ways.iter().flat_map(|way| way.refs.iter().copied()).collect();
+    let locations = match format {
+        OsmFormat::Xml => read_xml_locations(file_path, &needed)?,
+        OsmFormat::Pbf => read_pbf_locations(file_path, &needed)?,
+    };
+
+    let mut import = build_network(&ways, &locations, policy);
+    import.missing_nodes = needed.len() - locations.len();
+    Ok(import)
+}
+
+// Function to split the ways at intersections and turn every piece into an edge whose weight is its length in meters
+fn build_network<Ty: EdgeType>(
+    ways: &[OsmWay],
+    locations: &HashMap<i64, (f64, f64)>,
+    policy: PreprocessPolicy,
+) -> OsmImport<RoadNetworkOf<Ty>> {
+    // Count how many times each node is referenced; nodes shared between ways are intersections
+    let mut reference_counts: HashMap<i64, usize> = HashMap::new();
+    for way in ways {
+        for &node in &way.refs {
+            *reference_counts.entry(node).or_insert(0) += 1;
+        }
+    }
+
+    let mut network = RoadNetworkOf::new();
+    let mut normalizer = EdgeNormalizer::new(policy);
+    let mut coordinates = NodeCoordinates::new();
+    let mut ways_imported = 0;
+
+    for way in ways {
+        let mut contributed = false;
+
+        // Extracts clipped to a bounding box reference nodes outside of it, so only runs of located nodes are used
+        for run in way.refs.split(|node| !locations.contains_key(node)) {
+            let mut segment_start = match run.first() {
+                Some(&node) => node,
+                None => continue,
+            };
+            let mut length = 0.0;
+
+            for i in 1..run.len() {
+                length += haversine_distance(locations[&run[i - 1]], locations[&run[i]]);
+
+                // Close the segment at the end of the run or whenever another way joins here
+                if i == run.len() - 1 || reference_counts[&run[i]] > 1 {
+                    let edge = RoadEdge {
+                        weight: length,
+                        length: Some(length),
+                        travel_time: None,
+                        lanes: way.lanes,
+                        highway: Some(way.highway),
+                    };
+                    // An undirected network ignores the travel direction, so one arc per segment is enough
+                    let (from, to) = (segment_start, run[i]);
+                    let arcs = match way.oneway {
+                        Oneway::Both if network.is_directed() => vec![(from, to), (to, from)],
+                        Oneway::Both | Oneway::Forward => vec![(from, to)],
+                        Oneway::Backward => vec![(to, from)],
+                    };
+                    for (from, to) in arcs {
+                        if add_segment(&mut network, &mut normalizer, &mut coordinates, locations, from, to, edge) {
+                            contributed = true;
+                        }
+                    }
+                    segment_start = run[i];
+                    length = 0.0;
+                }
+            }
+        }
+
+        if contributed {
+            ways_imported += 1;
+        }
+    }
+
+    OsmImport {
+        network,
+        coordinates,
+        ways_imported,
+        missing_nodes: 0,
+        preprocess: normalizer.report(),
+    }
+}
+
+// Function to add a single segment to the network through the preprocessing stage, which decides what happens
+// when two ways connect the same intersections. Returns whether the segment changed the network.
+fn add_segment<Ty: EdgeType>(
+    network: &mut RoadNetworkOf<Ty>,
+    normalizer: &mut EdgeNormalizer,
+    coordinates: &mut NodeCoordinates,
+    locations: &HashMap<i64, (f64, f64)>,
+    from: i64,
+    to: i64,
+    edge: RoadEdge,
+) -> bool {
+    // Negative ids belong to unsaved edits
+    let (node_a, node_b) = match (usize::try_from(from), usize::try_from(to)) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return false,
+    };
+
+    // Closed ways without intersections collapse into self-loops, which the policy usually drops
+    if !normalizer.add_edge(network, node_a, node_b, edge) {
+        return false;
+    }
+    coordinates.insert(node_a, locations[&from]);
+    coordinates.insert(node_b, locations[&to]);
+    true
+}
+
+// Function to look up an attribute on an XML element and parse it into the requested type
+fn parse_attribute<T: FromStr>(element: &BytesStart, name: &str) -> Result<T, OsmError> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        if attribute.key.as_ref() == name.as_bytes() {
+            let value = attribute.unescape_value()?;
+            return value.parse::<T>().map_err(|_| {
+                OsmError::InvalidElement(format!("attribute {}={:?} could not be parsed", name, value))
+            });
+        }
+    }
+
+    Err(OsmError::InvalidElement(format!(
+        "<{}> element is missing the {} attribute",
+        String::from_utf8_lossy(element.name().as_ref()),
+        name
+    )))
+}
+
+// Function to collect the drivable ways from an XML extract
+fn read_xml_ways(file_path: &str) -> Result<Vec<OsmWay>, OsmError> {
+    let (input, _) = open_input(file_path)?;
+    let mut reader = Reader::from_reader(input);
+    let mut buffer = Vec::new();
+
+    let mut ways = Vec::new();
+    // Node references and tags of the way currently being read
+    let mut in_way = false;
+    let mut refs: Vec<i64> = Vec::new();
+    let mut tags: Vec<(String, String)> = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buffer)? {
+            Event::Start(element) if element.name().as_ref() == b"way" => {
+                in_way = true;
+                refs.clear();
+                tags.clear();
+            }
+            Event::Empty(element) if in_way => match element.name().as_ref() {
+                b"nd" => refs.push(parse_attribute(&element, "ref")?),
+                b"tag" => tags.push((parse_attribute(&element, "k")?, parse_attribute(&element, "v")?)),
+                _ => {}
+            },
+            Event::End(element) if element.name().as_ref() == b"way" => {
+                in_way = false;
+                let way_tags = tags.iter().map(|(key, value)| (key.as_str(), value.as_str()));
+                ways.extend(OsmWay::from_tags(std::mem::take(&mut refs), way_tags));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    Ok(ways)
+}
+
+// Function to collect the coordinates of the needed nodes from an XML extract
+fn read_xml_locations(file_path: &str, needed: &HashSet<i64>) -> Result<HashMap<i64, (f64, f64)>, OsmError> {
+    let (input, _) = open_input(file_path)?;
+    let mut reader = Reader::from_reader(input);
+    let mut buffer = Vec::new();
+    let mut locations = HashMap::new();
+
+    loop {
+        match reader.read_event_into(&mut buffer)? {
+            Event::Start(element) | Event::Empty(element) if element.name().as_ref() == b"node" => {
+                let id: i64 = parse_attribute(&element, "id")?;
+                if needed.contains(&id) {
+                    let lon: f64 = parse_attribute(&element, "lon")?;
+                    let lat: f64 = parse_attribute(&element, "lat")?;
+                    locations.insert(id, (lon, lat));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buffer.clear();
+    }
+
+    Ok(locations)
+}
+
+// Function to collect the drivable ways from a PBF extract
+fn read_pbf_ways(file_path: &str) -> Result<Vec<OsmWay>, OsmError> {
+    let mut ways = Vec::new();
+
+    ElementReader::from_path(file_path)?.for_each(|element| {
+        if let Element::Way(way) = element {
+            ways.extend(OsmWay::from_tags(way.refs().collect(), way.tags()));
+        }
+    })?;
+
+    Ok(ways)
+}
+
+// Function to collect the coordinates of the needed nodes from a PBF extract
+fn read_pbf_locations(file_path: &str, needed: &HashSet<i64>) -> Result<HashMap<i64, (f64, f64)>, OsmError> {
+    let mut locations = HashMap::new();
+
+    ElementReader::from_path(file_path)?.for_each(|element| {
+        let (id, lon, lat) = match element {
+            Element::Node(node) => (node.id(), node.lon(), node.lat()),
+            Element::DenseNode(node) => (node.id(), node.lon(), node.lat()),
+            _ => return,
+        };
+        if needed.contains(&id) {
+            locations.insert(id, (lon, lat));
+        }
+    })?;
+
+    Ok(locations)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversine_distance() {
+        // One degree of latitude is roughly 111.2 km
+        let distance = haversine_distance((0.0, 0.0), (0.0, 1.0));
+        assert!((distance - 111_195.0).abs() < 10.0);
+        assert_eq!(haversine_distance((-122.4, 37.8), (-122.4, 37.8)), 0.0);
+    }
+
+    #[test]
+    fn test_osm_format_from_path() {
+        assert_eq!(OsmFormat::from_path("california-latest.osm.pbf"), Some(OsmFormat::Pbf));
+        assert_eq!(OsmFormat::from_path("berkeley.osm"), Some(OsmFormat::Xml));
+        assert_eq!(OsmFormat::from_path("roadNet-CA.txt"), None);
+    }
+
+    #[test]
+    fn test_import_osm_xml() {
+        let import = import_osm("test_data/small_network.osm").unwrap();
+        let network = &import.network;
+
+        // The footway is dropped, the residential and primary ways are split at node 2,
+        // and the service way only references one located node
+        assert_eq!(network.node_count(), 5);
+        assert_eq!(network.edge_count(), 4);
+        assert!(!network.contains_node(6));
+        assert_eq!(import.ways_imported, 2);
+        assert_eq!(import.missing_nodes, 1);
+
+        let edge = network.edge_weight(1, 2).unwrap();
+        assert_eq!(edge.highway, Some(HighwayClass::Residential));
+        assert!((edge.weight - 111.195).abs() < 0.01);
+        assert_eq!(edge.length, Some(edge.weight));
+
+        let edge = network.edge_weight(4, 2).unwrap();
+        assert_eq!(edge.highway, Some(HighwayClass::Primary));
+        assert_eq!(edge.lanes, Some(4));
+
+        assert_eq!(import.coordinates[&2], (-122.0, 37.001));
+    }
+
+    #[test]
+    fn test_import_osm_directed_respects_oneway() {
+        let import = import_osm_as::<petgraph::Directed>("test_data/small_network.osm", PreprocessPolicy::shortest()).unwrap();
+        let network = &import.network;
+
+        // The residential street is two-way, the primary road is tagged oneway=yes from 4 over 2 to 5
+        assert_eq!(network.node_count(), 5);
+        assert_eq!(network.edge_count(), 6);
+        assert!(network.contains_edge(1, 2) && network.contains_edge(2, 1));
+        assert!(network.contains_edge(4, 2) && !network.contains_edge(2, 4));
+        assert!(network.contains_edge(2, 5) && !network.contains_edge(5, 2));
+    }
+
+    #[test]
+    fn test_oneway_tags() {
+        let tags = |pairs: &[(&'static str, &'static str)]| {
+            OsmWay::from_tags(vec![1, 2], pairs.iter().copied()).map(|way| way.oneway)
+        };
+
+        assert_eq!(tags(&[("highway", "residential")]), Some(Oneway::Both));
+        assert_eq!(tags(&[("highway", "residential"), ("oneway", "-1")]), Some(Oneway::Backward));
+        assert_eq!(tags(&[("highway", "motorway")]), Some(Oneway::Forward));
+        assert_eq!(tags(&[("highway", "motorway"), ("oneway", "no")]), Some(Oneway::Both));
+        assert_eq!(tags(&[("highway", "tertiary"), ("junction", "roundabout")]), Some(Oneway::Forward));
+    }
+}
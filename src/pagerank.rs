@@ -1,42 +1,65 @@
 // pagerank.rs
 
-use petgraph::graph::NodeIndex;
-use petgraph::Graph;
-use petgraph::Undirected;
+use petgraph::visit::{IntoNeighborsDirected, IntoNodeIdentifiers, NodeCount, NodeIndexable};
+use petgraph::Direction::{Incoming, Outgoing};
+use crate::progress::{NoProgress, Progress};
 use std::iter::repeat_with;
 
-// Take a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges; a damping factor `f64`; and the number of iterations to run the PageRank algorithm as a `usize` value. The function computes the PageRank scores of the nodes in the graph and returns them as a vector of `(NodeIndex, f64)` tuples.
-pub fn pagerank(
-    graph: &Graph<(f64, f64), f64, Undirected>,
+// Take any petgraph graph reference that can list its nodes and the neighbors on either side of them, such as `&RoadGraph` or `&RoadNetwork`; a damping factor `f64`; and the number of iterations to run the PageRank algorithm as a `usize` value. The function computes the PageRank scores of the nodes in the graph and returns them as a vector of `(node identifier, f64)` tuples, highest score first. On a directed graph rank flows along the arcs only, so a node's score is fed by its incoming arcs and split over its outgoing ones; an undirected edge counts in both directions.
+pub fn pagerank<G>(
+    graph: G,
     damping_factor: f64,
     iterations: usize,
-) -> Vec<(NodeIndex, f64)> {
+) -> Vec<(G::NodeId, f64)>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + NodeCount,
+{
+    pagerank_with_progress(graph, damping_factor, iterations, &NoProgress)
+}
+
+// PageRank as above, reporting every iteration to `progress`
+pub fn pagerank_with_progress<G>(
+    graph: G,
+    damping_factor: f64,
+    iterations: usize,
+    progress: &dyn Progress,
+) -> Vec<(G::NodeId, f64)>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + NodeCount,
+{
     // Compute the number of nodes in the graph and the initial value for each node.
     let node_count = graph.node_count() as f64;
     let initial_value = 1.0 / node_count;
 
-    // Initialize a vector to store the PageRank scores for each node.
-    let mut ranks: Vec<f64> = repeat_with(|| initial_value).take(graph.node_count()).collect();
-    let mut new_ranks: Vec<f64> = vec![0.0; graph.node_count()];
+    // Initialize a vector to store the PageRank scores for each node, indexed by `to_index`.
+    let mut ranks: Vec<f64> = repeat_with(|| initial_value).take(graph.node_bound()).collect();
+    let mut new_ranks: Vec<f64> = vec![0.0; graph.node_bound()];
 
     // Compute the sum of the damping factor for use in the PageRank algorithm.
     let damping_factor_sum = (1.0 - damping_factor) / node_count;
 
+    // Count the outgoing edges of every node once, rather than once per incoming edge in every iteration.
+    let mut out_degrees: Vec<usize> = vec![0; graph.node_bound()];
+    for node in graph.node_identifiers() {
+        out_degrees[graph.to_index(node)] = graph.neighbors_directed(node, Outgoing).count();
+    }
+
     // Iterate over the specified number of iterations for the PageRank algorithm.
+    progress.start("PageRank", Some(iterations as u64));
     for _ in 0..iterations {
-        // Compute the sum of the PageRank scores for dangling nodes.
-        let dangling_nodes = graph.node_indices().filter(|&node| graph.neighbors(node).count() == 0);
-        let dangling_sum: f64 = dangling_nodes.map(|node| ranks[node.index()]).sum();
+        // Compute the sum of the PageRank scores for dangling nodes, which have no outgoing edges.
+        let dangling_nodes = graph.node_identifiers().filter(|&node| out_degrees[graph.to_index(node)] == 0);
+        let dangling_sum: f64 = dangling_nodes.map(|node| ranks[graph.to_index(node)]).sum();
 
         // Iterate over all nodes in the graph.
-        for node in graph.node_indices() {
-            // Compute the sum of the PageRank scores for neighbors of the current node.
+        for node in graph.node_identifiers() {
+            // Compute the sum of the PageRank scores flowing in from the neighbors that link to the current node.
             let sum: f64 = graph
-                .neighbors(node)
+                .neighbors_directed(node, Incoming)
                 .map(|neighbor| {
-                    let degree = graph.neighbors(neighbor).count();
+                    let degree = out_degrees[graph.to_index(neighbor)];
                     if degree > 0 {
-                        ranks[neighbor.index()] / degree as f64
+                        ranks[graph.to_index(neighbor)] / degree as f64
                     } else {
                         0.0
                     }
@@ -44,18 +67,20 @@ pub fn pagerank(
                 .sum();
 
             // Compute the new PageRank score for the current node and store it in the `new_ranks` vector.
-            new_ranks[node.index()] =
+            new_ranks[graph.to_index(node)] =
                 damping_factor_sum + damping_factor * (sum + dangling_sum / node_count);
         }
 
         // Update the `ranks` vector with the new PageRank scores.
         ranks.clone_from_slice(&new_ranks);
+        progress.advance(1);
     }
+    progress.finish();
 
-    // Sort the nodes by their PageRank scores and return the result as a vector of `(NodeIndex, f64)` tuples.
-    let mut result: Vec<(NodeIndex, f64)> = graph
-        .node_indices()
-        .map(|n| (n, ranks[n.index()]))
+    // Sort the nodes by their PageRank scores and return the result as a vector of `(node identifier, f64)` tuples.
+    let mut result: Vec<(G::NodeId, f64)> = graph
+        .node_identifiers()
+        .map(|n| (n, ranks[graph.to_index(n)]))
         .collect();
     result.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
@@ -67,25 +92,25 @@ pub fn pagerank(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::{RoadEdge, RoadGraph, RoadNetwork};
     use petgraph::graph::NodeIndex;
-    use petgraph::{Graph, Undirected};
+    use petgraph::{Directed, EdgeType, Undirected};
+
+    // Create a graph whose nodes have the indices and ids 0..node_count, joined by unit-weight edges
+    fn create_graph<Ty: EdgeType>(node_count: usize, edges: &[(usize, usize)]) -> RoadGraph<Ty> {
+        let mut graph = RoadGraph::<Ty>::default();
+        for id in 0..node_count {
+            graph.add_node(crate::data::RoadNode::new(id));
+        }
+        for &(a, b) in edges {
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), RoadEdge::default());
+        }
+        graph
+    }
 
     #[test]
     fn test_pagerank() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((0.0, 0.0));
-        let c = graph.add_node((0.0, 0.0));
-        let d = graph.add_node((0.0, 0.0));
-
-        graph.extend_with_edges(&[
-            (a, b, 1.0),
-            (b, c, 1.0),
-            (c, d, 1.0),
-            (d, a, 1.0),
-            (a, c, 1.0),
-            (b, d, 1.0),
-        ]);
+        let graph = create_graph::<Undirected>(4, &[(0, 1), (1, 2), (2, 3), (3, 0), (0, 2), (1, 3)]);
 
         let damping_factor = 0.85;
         let iterations = 100;
@@ -94,40 +119,30 @@ mod tests {
         assert_eq!(result.len(), 4);
         assert!(result.iter().all(|(_, rank)| *rank > 0.0));
 
-        let expected_result = vec![
-            (NodeIndex::new(0), 0.25),
-            (NodeIndex::new(1), 0.25),
-            (NodeIndex::new(2), 0.25),
-            (NodeIndex::new(3), 0.25),
-        ];
+        let expected_result = [(0, 0.25), (1, 0.25), (2, 0.25), (3, 0.25)];
 
         for (i, (node, rank)) in result.iter().enumerate() {
-            assert_eq!(*node, expected_result[i].0);
+            assert_eq!(node.index(), expected_result[i].0);
             assert!((rank - expected_result[i].1).abs() < 1e-6);
         }
     }
 
     #[test]
     fn test_pagerank_single_node() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
+        let graph = create_graph::<Undirected>(1, &[]);
 
         let damping_factor = 0.85;
         let iterations = 100;
         let result = pagerank(&graph, damping_factor, iterations);
 
         assert_eq!(result.len(), 1);
-        assert_eq!(result[0].0, a);
+        assert_eq!(result[0].0.index(), 0);
         assert!((result[0].1 - 1.0).abs() < 1e-4);
     }
 
     #[test]
     fn test_pagerank_disconnected_graph() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((0.0, 0.0));
-        let c = graph.add_node((0.0, 0.0));
-        let d = graph.add_node((0.0, 0.0));
+        let graph = create_graph::<Undirected>(4, &[]);
 
         let damping_factor = 0.85;
         let iterations = 100;
@@ -144,16 +159,7 @@ mod tests {
 
     #[test]
     fn test_pagerank_directed_cycle() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((0.0, 0.0));
-        let c = graph.add_node((0.0, 0.0));
-
-        graph.extend_with_edges(&[
-            (a, b, 1.0),
-            (b, c, 1.0),
-            (c, a, 1.0),
-        ]);
+        let graph = create_graph::<Undirected>(3, &[(0, 1), (1, 2), (2, 0)]);
 
         let damping_factor = 0.85;
         let iterations = 100;
@@ -168,17 +174,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pagerank_directed_graph() {
+        // Node 2 is linked from both other nodes, while 1 only receives half of 0's rank
+        let graph = create_graph::<Directed>(3, &[(0, 1), (0, 2), (1, 2), (2, 0)]);
+
+        let result = pagerank(&graph, 0.85, 100);
+        let order: Vec<usize> = result.iter().map(|(node, _)| node.index()).collect();
+        assert_eq!(order, vec![2, 0, 1]);
+
+        let total: f64 = result.iter().map(|(_, rank)| rank).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pagerank_directed_dangling_node() {
+        let graph = create_graph::<Directed>(2, &[(0, 1)]);
+
+        // The dead end 1 redistributes its rank evenly, so the scores still sum to one
+        let result = pagerank(&graph, 0.85, 100);
+        assert_eq!(result[0].0.index(), 1);
+        let total: f64 = result.iter().map(|(_, rank)| rank).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+    }
+
     #[test]
     fn test_pagerank_star_graph() {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let center = graph.add_node((0.0, 0.0));
-        let mut nodes = Vec::new();
-
-        for _ in 0..4 {
-            let node = graph.add_node((0.0, 0.0));
-            graph.add_edge(center, node, 1.0);
-            nodes.push(node);
-        }
+        let graph = create_graph::<Undirected>(5, &[(0, 1), (0, 2), (0, 3), (0, 4)]);
 
         let damping_factor = 0.85;
         let iterations = 100;
@@ -187,16 +209,34 @@ mod tests {
         assert_eq!(result.len(), 5);
         assert!(result.iter().all(|(_, rank)| *rank > 0.0));
 
-        let center_rank = result.iter().find(|(n, _)| *n == center).unwrap().1;
-        for node in nodes {
-            let node_rank = result.iter().find(|(n, _)| *n == node).unwrap().1;
+        let center_rank = result.iter().find(|(n, _)| n.index() == 0).unwrap().1;
+        for node in 1..5 {
+            let node_rank = result.iter().find(|(n, _)| n.index() == node).unwrap().1;
             assert!(center_rank > node_rank);
         }
     }
-}
-
-
-
-
 
+    #[test]
+    fn test_pagerank_on_graph_map() {
+        // On a GraphMap the results are keyed by the original ids, and agree with the same graph as a road graph
+        let network = RoadNetwork::from_edges([(7001, 42), (42, 5), (5, 7001), (5, 9)]);
+        let by_id = pagerank(&network, 0.85, 100);
+        assert_eq!(by_id[0].0, 5);
+
+        let graph = create_graph::<Undirected>(4, &[(0, 1), (1, 2), (2, 0), (2, 3)]);
+        let by_index = pagerank(&graph, 0.85, 100);
+        assert_eq!(by_index[0].0, NodeIndex::new(2));
+        assert!((by_id[0].1 - by_index[0].1).abs() < 1e-12);
+    }
 
+    #[test]
+    fn test_pagerank_reports_every_iteration() {
+        let network = RoadNetwork::from_edges([(1, 2), (2, 3)]);
+        let progress = crate::progress::tests::CountingProgress::default();
+        let result = pagerank_with_progress(&network, 0.85, 7, &progress);
+
+        assert_eq!(result, pagerank(&network, 0.85, 7));
+        assert_eq!(progress.tasks(), vec![("PageRank".to_string(), Some(7))]);
+        assert_eq!(progress.steps(), 7);
+    }
+}
@@ -0,0 +1,397 @@
+// parallel_load.rs
+
+use crate::data::{open_input, Codec, parse_edge_line, LoadError, LoadOptions, LoadSummary, RoadEdge, RoadNetwork, RoadNetworkOf};
+use crate::preprocess::EdgeNormalizer;
+use crate::progress::{NoProgress, Progress};
+use petgraph::{EdgeType, Undirected};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+
+// Target size of the byte ranges handed to the worker threads
+const CHUNK_BYTES: u64 = 1 << 20;
+
+// Throughput statistics of a parallel load
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadThroughput {
+    // Number of (decompressed) bytes parsed
+    pub bytes: u64,
+    // Number of lines parsed, including comments and blank lines
+    pub lines: usize,
+    // Number of chunks the input was split into
+    pub chunks: usize,
+    // Number of worker threads available to the parser
+    pub threads: usize,
+    // Wall time of the whole load, including building the graph
+    pub elapsed: Duration,
+}
+
+impl LoadThroughput {
+    // Parsing speed in mebibytes per second
+    pub fn megabytes_per_second(&self) -> f64 {
+        self.bytes as f64 / (1024.0 * 1024.0) / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    // Parsing speed in lines per second
+    pub fn lines_per_second(&self) -> f64 {
+        self.lines as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+// What a single non-comment line turned into
+enum LineOutcome {
+    Edge(usize, usize, RoadEdge),
+    // A malformed line, which lenient mode skips
+    Bad(LoadError),
+    // A line that is not valid UTF-8, which aborts the load in either mode just like BufRead::lines does
+    Fatal,
+}
+
+// The parsed lines of one chunk. Line numbers are relative to the start of the chunk and fixed up when
+// the chunks are merged in file order.
+struct ChunkResult {
+    bytes: usize,
+    lines: usize,
+    outcomes: Vec<LineOutcome>,
+}
+
+// Function to parse a block of complete lines
+fn parse_chunk(block: &[u8], options: &LoadOptions) -> ChunkResult {
+    let mut pieces: Vec<&[u8]> = block.split(|byte| *byte == b'\n').collect();
+    // A trailing newline leaves an empty piece that is not a line of its own
+    if pieces.last().is_some_and(|piece| piece.is_empty()) {
+        pieces.pop();
+    }
+
+    let mut outcomes = Vec::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        let piece = piece.strip_suffix(b"\r").unwrap_or(piece);
+        let line = match std::str::from_utf8(piece) {
+            Ok(line) => line,
+            Err(_) => {
+                outcomes.push(LineOutcome::Fatal);
+                break;
+            }
+        };
+
+        // Ignore comment lines starting with '#' and blank lines
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let outcome = match parse_edge_line(line, index + 1, options) {
+            Ok((node_a, node_b, edge)) => LineOutcome::Edge(node_a, node_b, edge),
+            Err(err) => LineOutcome::Bad(err),
+        };
+        outcomes.push(outcome);
+    }
+
+    ChunkResult {
+        bytes: block.len(),
+        lines: pieces.len(),
+        outcomes,
+    }
+}
+
+// Function to move the line number of a parse error from chunk-relative to file-relative
+fn offset_line(err: LoadError, offset: usize) -> LoadError {
+    match err {
+        LoadError::Parse { line, column, text } => LoadError::Parse { line: line + offset, column, text },
+        LoadError::TooFewColumns { line, text, expected, found } => {
+            LoadError::TooFewColumns { line: line + offset, text, expected, found }
+        }
+        other => other,
+    }
+}
+
+// Sequential merge of chunk results into the graph, applying exactly the rules of data::load_edge_list
+struct Merger<'a, Ty: EdgeType> {
+    options: &'a LoadOptions,
+    codec: Codec,
+    graph: RoadNetworkOf<Ty>,
+    normalizer: EdgeNormalizer,
+    summary: LoadSummary,
+    throughput: LoadThroughput,
+    header_pending: bool,
+    progress: &'a dyn Progress,
+}
+
+impl<Ty: EdgeType> Merger<'_, Ty> {
+    fn merge(&mut self, chunk: ChunkResult) -> Result<(), LoadError> {
+        let line_offset = self.summary.lines_read;
+        self.throughput.bytes += chunk.bytes as u64;
+        self.throughput.chunks += 1;
+
+        for outcome in chunk.outcomes {
+            if let LineOutcome::Fatal = outcome {
+                let err = io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+                return Err(LoadError::read(self.codec, err));
+            }
+
+            // Skip the header row of delimited files
+            if self.header_pending {
+                self.header_pending = false;
+                continue;
+            }
+
+            match outcome {
+                LineOutcome::Edge(node_a, node_b, edge) => {
+                    self.normalizer.add_edge(&mut self.graph, node_a, node_b, edge);
+                    self.summary.edges_loaded += 1;
+                }
+                LineOutcome::Bad(err) if self.options.lenient => {
+                    self.summary.record_bad_line(offset_line(err, line_offset))
+                }
+                LineOutcome::Bad(err) => return Err(offset_line(err, line_offset)),
+                LineOutcome::Fatal => unreachable!("fatal outcomes are handled above"),
+            }
+        }
+
+        self.summary.lines_read += chunk.lines;
+        self.progress.advance(chunk.bytes as u64);
+        Ok(())
+    }
+}
+
+// Function to read the lines that start inside [start, end) of an uncompressed file. A line belongs to the
+// range its first byte falls in, so the ranges can be cut anywhere and still cover every line exactly once.
+fn read_range(file_path: &str, start: u64, end: u64) -> io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    let mut position = start;
+
+    // Unless the byte before the range is a newline, the first partial line belongs to the previous range
+    if start > 0 {
+        reader.seek(SeekFrom::Start(start - 1))?;
+        let mut skipped = Vec::new();
+        position = start - 1 + reader.read_until(b'\n', &mut skipped)? as u64;
+    }
+
+    let mut block = Vec::new();
+    if position < end {
+        (&mut reader).take(end - position).read_to_end(&mut block)?;
+        if !block.ends_with(b"\n") {
+            reader.read_until(b'\n', &mut block)?;
+        }
+    }
+    Ok(block)
+}
+
+// Function to read the next block of complete lines from a stream, returning an empty block at the end
+fn read_block(reader: &mut dyn BufRead, chunk_bytes: u64) -> io::Result<Vec<u8>> {
+    let mut block = Vec::new();
+    reader.take(chunk_bytes).read_to_end(&mut block)?;
+    if !block.is_empty() && !block.ends_with(b"\n") {
+        reader.read_until(b'\n', &mut block)?;
+    }
+    Ok(block)
+}
+
+// Function to load an edge list using all rayon worker threads. Uncompressed files are split into byte
+// ranges that the workers read and parse independently; compressed files are decompressed as a stream
+// and the decompressed blocks are parsed in parallel. The graph, summary and errors are identical to
+// those of data::load_edge_list.
+pub fn load_edge_list_parallel(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(RoadNetwork, LoadSummary, LoadThroughput), LoadError> {
+    load_with_chunk_size::<Undirected>(file_path, options, CHUNK_BYTES, &NoProgress)
+}
+
+// Function to load an edge list in parallel into a graph of the given edge type, matching data::load_edge_list_as
+pub fn load_edge_list_parallel_as<Ty: EdgeType>(
+    file_path: &str,
+    options: &LoadOptions,
+) -> Result<(RoadNetworkOf<Ty>, LoadSummary, LoadThroughput), LoadError> {
+    load_with_chunk_size(file_path, options, CHUNK_BYTES, &NoProgress)
+}
+
+// Function to load an edge list in parallel like load_edge_list_parallel_as, reporting the bytes parsed to
+// `progress`. The total is the file size of uncompressed files and unknown for compressed ones.
+pub fn load_edge_list_parallel_with_progress<Ty: EdgeType>(
+    file_path: &str,
+    options: &LoadOptions,
+    progress: &dyn Progress,
+) -> Result<(RoadNetworkOf<Ty>, LoadSummary, LoadThroughput), LoadError> {
+    let result = load_with_chunk_size(file_path, options, CHUNK_BYTES, progress);
+    progress.finish();
+    result
+}
+
+fn load_with_chunk_size<Ty: EdgeType>(
+    file_path: &str,
+    options: &LoadOptions,
+    chunk_bytes: u64,
+    progress: &dyn Progress,
+) -> Result<(RoadNetworkOf<Ty>, LoadSummary, LoadThroughput), LoadError> {
+    let started = Instant::now();
+    options.validate()?;
+    let (mut reader, codec) = open_input(file_path)?;
+
+    let threads = rayon::current_num_threads();
+    // Parse a bounded window of chunks at a time so memory use does not grow with the input size
+    let window = threads * 2;
+    let mut merger = Merger {
+        options,
+        codec,
+        graph: RoadNetworkOf::new(),
+        normalizer: EdgeNormalizer::new(options.preprocess),
+        summary: LoadSummary::default(),
+        throughput: LoadThroughput { threads, ..LoadThroughput::default() },
+        header_pending: options.has_header,
+        progress,
+    };
+
+    if codec != Codec::None {
+        progress.start("Loading", None);
+        let read_error = |err: io::Error| LoadError::read(codec, err);
+        loop {
+            let mut blocks = Vec::with_capacity(window);
+            while blocks.len() < window {
+                let block = read_block(&mut reader, chunk_bytes).map_err(read_error)?;
+                if block.is_empty() {
+                    break;
+                }
+                blocks.push(block);
+            }
+            if blocks.is_empty() {
+                break;
+            }
+
+            let chunks: Vec<ChunkResult> = blocks.par_iter().map(|block| parse_chunk(block, options)).collect();
+            for chunk in chunks {
+                merger.merge(chunk)?;
+            }
+        }
+    } else {
+        drop(reader);
+        let file_len = std::fs::metadata(file_path)?.len();
+        progress.start("Loading", Some(file_len));
+        let ranges: Vec<(u64, u64)> = (0..file_len)
+            .step_by(chunk_bytes.max(1) as usize)
+            .map(|start| (start, (start + chunk_bytes).min(file_len)))
+            .collect();
+
+        for batch in ranges.chunks(window) {
+            let chunks: Vec<io::Result<ChunkResult>> = batch
+                .par_iter()
+                .map(|&(start, end)| read_range(file_path, start, end).map(|block| parse_chunk(&block, options)))
+                .collect();
+            for chunk in chunks {
+                merger.merge(chunk?)?;
+            }
+        }
+    }
+
+    merger.summary.preprocess = merger.normalizer.report();
+    merger.throughput.lines = merger.summary.lines_read;
+    merger.throughput.elapsed = started.elapsed();
+    Ok((merger.graph, merger.summary, merger.throughput))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{load_edge_list, load_edge_list_as, ColumnMapping, Delimiter, WeightSource};
+    use crate::preprocess::{ParallelEdgePolicy, PreprocessPolicy};
+    use petgraph::Directed;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // Assert that the parallel loader reproduces the sequential loader exactly, for several chunk sizes
+    fn assert_same_as_sequential(file_path: &str, options: &LoadOptions) {
+        let (expected_graph, expected_summary) = load_edge_list(file_path, options).unwrap();
+        let expected_edges: Vec<(usize, usize, RoadEdge)> =
+            expected_graph.all_edges().map(|(a, b, edge)| (a, b, *edge)).collect();
+
+        for chunk_bytes in [1, 3, 7, 64, CHUNK_BYTES] {
+            let (graph, summary, throughput) =
+                load_with_chunk_size::<Undirected>(file_path, options, chunk_bytes, &NoProgress).unwrap();
+            let edges: Vec<(usize, usize, RoadEdge)> = graph.all_edges().map(|(a, b, edge)| (a, b, *edge)).collect();
+
+            assert_eq!(graph.nodes().collect::<Vec<_>>(), expected_graph.nodes().collect::<Vec<_>>());
+            assert_eq!(edges, expected_edges);
+            assert_eq!(summary.lines_read, expected_summary.lines_read);
+            assert_eq!(summary.edges_loaded, expected_summary.edges_loaded);
+            assert_eq!(summary.skipped_lines, expected_summary.skipped_lines);
+            assert_eq!(summary.preprocess, expected_summary.preprocess);
+            let messages: Vec<String> = summary.bad_lines.iter().map(|err| err.to_string()).collect();
+            let expected_messages: Vec<String> = expected_summary.bad_lines.iter().map(|err| err.to_string()).collect();
+            assert_eq!(messages, expected_messages);
+            assert_eq!(throughput.lines, expected_summary.lines_read);
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        assert_same_as_sequential("test_data/test_graph.txt", &LoadOptions::default());
+        assert_same_as_sequential(
+            "test_data/malformed_graph.txt",
+            &LoadOptions { lenient: true, ..LoadOptions::default() },
+        );
+
+        let summed = PreprocessPolicy { parallel_edges: ParallelEdgePolicy::Sum, ..PreprocessPolicy::default() };
+        assert_same_as_sequential(
+            "test_data/duplicate_edges.txt",
+            &LoadOptions { preprocess: summed, ..LoadOptions::default() },
+        );
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential_with_header() {
+        let options = LoadOptions {
+            delimiter: Delimiter::Comma,
+            columns: ColumnMapping { length: Some(2), travel_time: Some(3), lanes: Some(4), ..ColumnMapping::default() },
+            weight: WeightSource::Length,
+            has_header: true,
+            ..LoadOptions::default()
+        };
+        assert_same_as_sequential("test_data/weighted_graph.csv", &options);
+    }
+
+    #[test]
+    fn test_parallel_loads_directed_arcs() {
+        let options = LoadOptions::default();
+        let (expected, _) = load_edge_list_as::<Directed>("test_data/one_way_graph.txt", &options).unwrap();
+        let (graph, _, _) = load_with_chunk_size::<Directed>("test_data/one_way_graph.txt", &options, 3, &NoProgress).unwrap();
+
+        let arcs: Vec<(usize, usize)> = graph.all_edges().map(|(a, b, _)| (a, b)).collect();
+        let expected_arcs: Vec<(usize, usize)> = expected.all_edges().map(|(a, b, _)| (a, b)).collect();
+        assert_eq!(arcs, expected_arcs);
+        assert!(!graph.contains_edge(3, 2));
+    }
+
+    #[test]
+    fn test_parallel_strict_reports_first_bad_line() {
+        for chunk_bytes in [1, 5, CHUNK_BYTES] {
+            let result =
+                load_with_chunk_size::<Undirected>("test_data/malformed_graph.txt", &LoadOptions::default(), chunk_bytes, &NoProgress);
+            assert!(matches!(result, Err(LoadError::TooFewColumns { line: 4, found: 1, .. })));
+        }
+    }
+
+    #[test]
+    fn test_parallel_streams_gzip() {
+        let path = std::env::temp_dir().join(format!("crnsa_parallel_{}.txt.gz", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(&std::fs::read("test_data/malformed_graph.txt").unwrap()).unwrap();
+        encoder.finish().unwrap();
+
+        let options = LoadOptions { lenient: true, ..LoadOptions::default() };
+        assert_same_as_sequential(path, &options);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_reports_bytes_parsed() {
+        let progress = crate::progress::tests::CountingProgress::default();
+        load_edge_list_parallel_with_progress::<Undirected>("test_data/test_graph.txt", &LoadOptions::default(), &progress)
+            .unwrap();
+
+        let file_len = std::fs::metadata("test_data/test_graph.txt").unwrap().len();
+        assert_eq!(progress.tasks(), vec![("Loading".to_string(), Some(file_len))]);
+        assert_eq!(progress.steps(), file_len);
+    }
+}
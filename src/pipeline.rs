@@ -0,0 +1,571 @@
+// pipeline.rs
+
+use crate::centrality_analysis::Measure;
+use crate::loader::InputOptions;
+use crate::preprocess::PreprocessPolicy;
+use crate::route::RouteOptions;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A complete description of an analysis run: where the network comes from, how its edges are preprocessed,
+// which analyses run with which parameters, and where their outputs go. It is read from a TOML or JSON file,
+// or put together from the command line, and is embedded in every output so a result can be reproduced.
+//
+//   seed = 42
+//   analyses = [
+//       { analysis = "stats" },
+//       { analysis = "pagerank", damping = 0.9, iterations = 50 },
+//       { analysis = "route", landmarks = 16 },
+//   ]
+//
+//   [input]
+//   path = "data/roadNet-CA.txt"
+//   directed = true
+//
+//   [preprocess]
+//   parallel_edges = "sum"
+//
+//   [output]
+//   dir = "results"
+//   format = "json"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    pub input: InputOptions,
+    #[serde(default = "PreprocessPolicy::shortest")]
+    pub preprocess: PreprocessPolicy,
+    #[serde(default)]
+    pub output: OutputConfig,
+    // Seed of every random choice of the run. A configuration without one draws a fresh seed, which the embedded
+    // copy records so the run can still be repeated.
+    #[serde(default = "random_seed")]
+    pub seed: u64,
+    // Analyses in the order they run
+    pub analyses: Vec<AnalysisConfig>,
+}
+
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+// Where and how the outputs are written; the `[output]` table of a pipeline configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    // Directory the JSON outputs are written to; created if it does not exist
+    pub dir: PathBuf,
+    // Layout of the JSON outputs
+    pub format: OutputFormat,
+    // Draw live progress bars of the long-running stages on stderr
+    pub progress: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig { dir: PathBuf::from("."), format: OutputFormat::PrettyJson, progress: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    // One line of JSON per file
+    Json,
+    // Indented JSON, easier to read and diff
+    PrettyJson,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 2] = [OutputFormat::Json, OutputFormat::PrettyJson];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::PrettyJson => "pretty-json",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// The analyses. In a pipeline configuration each entry of `analyses` names one in its `analysis` field, next to
+// the parameters that differ from the defaults, e.g. `{ analysis = "pagerank", damping = 0.9 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "analysis", rename_all = "kebab-case")]
+pub enum AnalysisConfig {
+    // Node and edge counts, connectivity, degree distribution, clustering, density and assortativity
+    Stats,
+    // Degree, eigenvector and Katz centrality of every node
+    Centrality(CentralityConfig),
+    // Betweenness of nodes and edges, exact or estimated from sampled pivot sources
+    Betweenness(BetweennessConfig),
+    // Closeness and harmonic centrality, exact or estimated from sampled sources
+    Closeness(ClosenessConfig),
+    // PageRank scores of the highest ranked nodes
+    Pagerank(PagerankConfig),
+    // Rank correlations and top-k overlap between centrality measures, and the nodes ranking high on one only
+    CompareCentrality(CompareCentralityConfig),
+    // Leiden communities
+    Communities(CommunitiesConfig),
+    // Bridge edges, whose removal disconnects the network
+    Bridges(BridgesConfig),
+    // Connected components, and strongly connected components in directed mode
+    Components,
+    // Split of the nodes into core and periphery by degree
+    CorePeriphery(CorePeripheryConfig),
+    // Landmark-guided shortest paths
+    Route(RouteConfig),
+}
+
+impl AnalysisConfig {
+    // The analyses of the original pipeline in its order, each with its default parameters. Betweenness,
+    // closeness and the centrality comparison are left out, as they are costly on a whole road network; they only
+    // run when named.
+    pub fn pipeline() -> Vec<AnalysisConfig> {
+        vec![
+            AnalysisConfig::Stats,
+            AnalysisConfig::Centrality(CentralityConfig::default()),
+            AnalysisConfig::Pagerank(PagerankConfig::default()),
+            AnalysisConfig::Communities(CommunitiesConfig::default()),
+            AnalysisConfig::CorePeriphery(CorePeripheryConfig::default()),
+            AnalysisConfig::Bridges(BridgesConfig::default()),
+            AnalysisConfig::Components,
+            AnalysisConfig::Route(RouteConfig::default()),
+        ]
+    }
+
+    // Name of the analysis, as on the command line and in the `analysis` field of a configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnalysisConfig::Stats => "stats",
+            AnalysisConfig::Centrality(_) => "centrality",
+            AnalysisConfig::Betweenness(_) => "betweenness",
+            AnalysisConfig::Closeness(_) => "closeness",
+            AnalysisConfig::Pagerank(_) => "pagerank",
+            AnalysisConfig::CompareCentrality(_) => "compare-centrality",
+            AnalysisConfig::Communities(_) => "communities",
+            AnalysisConfig::Bridges(_) => "bridges",
+            AnalysisConfig::Components => "components",
+            AnalysisConfig::CorePeriphery(_) => "core-periphery",
+            AnalysisConfig::Route(_) => "route",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CentralityConfig {
+    // Number of nodes to print
+    pub top: usize,
+    // Which degree to score nodes by
+    pub degree: DegreeMeasure,
+    // Relative residual at which the eigenvector centrality iteration stops, and change per node at which the
+    // Katz iteration stops
+    pub tolerance: f64,
+    // Matrix-vector products allowed per connected component for eigenvector centrality, and iterations for
+    // Katz centrality
+    pub max_iterations: usize,
+    // Katz attenuation, the weight of every further step of a walk; it must stay below one over the spectral
+    // radius of the graph
+    pub attenuation: f64,
+}
+
+impl Default for CentralityConfig {
+    fn default() -> Self {
+        CentralityConfig {
+            top: 10,
+            degree: DegreeMeasure::Raw,
+            tolerance: 1e-6,
+            max_iterations: 10_000,
+            attenuation: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DegreeMeasure {
+    // Number of neighbors
+    Raw,
+    // Number of neighbors divided by n - 1
+    Normalized,
+    // Total weight of the edges at a node
+    Weighted,
+    // Arcs arriving at a node in directed mode; the degree otherwise
+    In,
+    // Arcs leaving a node in directed mode; the degree otherwise
+    Out,
+}
+
+impl DegreeMeasure {
+    pub const ALL: [DegreeMeasure; 5] =
+        [DegreeMeasure::Raw, DegreeMeasure::Normalized, DegreeMeasure::Weighted, DegreeMeasure::In, DegreeMeasure::Out];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DegreeMeasure::Raw => "raw",
+            DegreeMeasure::Normalized => "normalized",
+            DegreeMeasure::Weighted => "weighted",
+            DegreeMeasure::In => "in",
+            DegreeMeasure::Out => "out",
+        }
+    }
+}
+
+impl fmt::Display for DegreeMeasure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BetweennessConfig {
+    // Number of pivot sources to sample; the scores are extrapolated from their shortest paths
+    pub pivots: usize,
+    // Search from every node instead of sampling pivots
+    pub exact: bool,
+    // Measure paths by edge weight instead of counting hops
+    pub weighted: bool,
+    // Confidence of the error bounds of sampled scores
+    pub confidence: f64,
+    // Number of nodes and edges to print
+    pub top: usize,
+}
+
+impl Default for BetweennessConfig {
+    fn default() -> Self {
+        BetweennessConfig { pivots: 500, exact: false, weighted: false, confidence: 0.95, top: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClosenessConfig {
+    // Number of source nodes to sample; the scores are estimated from their distances
+    pub sources: usize,
+    // Search from every node instead of sampling sources
+    pub exact: bool,
+    // Measure distances by edge weight instead of counting hops
+    pub weighted: bool,
+    // Number of nodes to print
+    pub top: usize,
+}
+
+impl Default for ClosenessConfig {
+    fn default() -> Self {
+        ClosenessConfig { sources: 200, exact: false, weighted: false, top: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PagerankConfig {
+    // Damping factor
+    pub damping: f64,
+    // Number of power iterations
+    pub iterations: usize,
+    // Number of nodes to print
+    pub top: usize,
+}
+
+impl Default for PagerankConfig {
+    fn default() -> Self {
+        PagerankConfig { damping: 0.85, iterations: 100, top: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompareCentralityConfig {
+    // Measures to compare
+    pub measures: Vec<Measure>,
+    // Number of source nodes to sample for betweenness and closeness
+    pub sources: usize,
+    // Search from every node instead of sampling sources
+    pub exact: bool,
+    // Katz attenuation; it must stay below one over the spectral radius of the graph
+    pub attenuation: f64,
+    // Number of top nodes of every measure whose overlap is measured
+    pub top_k: usize,
+    // Percentile rank, from 0 to 1, at or above which a node ranks high on a measure
+    pub high_percentile: f64,
+    // Percentile rank at or below which a node ranks low on a measure
+    pub low_percentile: f64,
+    // Number of nodes ranking high on one measure only to print
+    pub top: usize,
+}
+
+impl Default for CompareCentralityConfig {
+    fn default() -> Self {
+        CompareCentralityConfig {
+            measures: Measure::ALL.to_vec(),
+            sources: 200,
+            exact: false,
+            attenuation: 0.1,
+            top_k: 100,
+            high_percentile: 0.99,
+            low_percentile: 0.5,
+            top: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommunitiesConfig {
+    // Number of communities to print
+    pub top: usize,
+}
+
+impl Default for CommunitiesConfig {
+    fn default() -> Self {
+        CommunitiesConfig { top: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BridgesConfig {
+    // Number of bridge edges to print
+    pub limit: usize,
+}
+
+impl Default for BridgesConfig {
+    fn default() -> Self {
+        BridgesConfig { limit: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorePeripheryConfig {
+    // Nodes with at least this degree belong to the core
+    pub degree_threshold: usize,
+    // Number of core and periphery nodes to print
+    pub top: usize,
+}
+
+impl Default for CorePeripheryConfig {
+    fn default() -> Self {
+        CorePeripheryConfig { degree_threshold: 10, top: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RouteConfig {
+    // Number of landmarks that guide the search
+    pub landmarks: usize,
+    // Number of paths to find
+    pub paths: usize,
+    // Original id of the start node; a random node by default
+    pub from: Option<usize>,
+    // Original id of the end node; a random node reachable from the start by default
+    pub to: Option<usize>,
+}
+
+impl Default for RouteConfig {
+    fn default() -> Self {
+        let options = RouteOptions::default();
+        RouteConfig { landmarks: options.landmarks, paths: options.paths, from: None, to: None }
+    }
+}
+
+impl RouteConfig {
+    pub fn options(&self) -> RouteOptions {
+        RouteOptions { landmarks: self.landmarks, paths: self.paths, endpoints: self.from.zip(self.to) }
+    }
+}
+
+// Errors that can occur while reading a pipeline configuration
+#[derive(Debug)]
+pub enum PipelineError {
+    // The configuration file could not be read
+    Io(io::Error),
+    // The file is not a valid TOML configuration
+    Toml(toml::de::Error),
+    // The file is not a valid JSON configuration
+    Json(serde_json::Error),
+    // The configuration parses but cannot be run, e.g. it lists no analyses
+    Invalid(String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Io(err) => write!(f, "I/O error: {}", err),
+            PipelineError::Toml(err) => write!(f, "invalid TOML configuration: {}", err),
+            PipelineError::Json(err) => write!(f, "invalid JSON configuration: {}", err),
+            PipelineError::Invalid(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Io(err) => Some(err),
+            PipelineError::Toml(err) => Some(err),
+            PipelineError::Json(err) => Some(err),
+            PipelineError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for PipelineError {
+    fn from(err: io::Error) -> Self {
+        PipelineError::Io(err)
+    }
+}
+
+impl PipelineConfig {
+    // Read a configuration file; `.json` files are parsed as JSON, everything else as TOML. Parameters that
+    // are not given take their defaults, which are also those of the command line flags.
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, PipelineError> {
+        let file_path = file_path.as_ref();
+        let text = std::fs::read_to_string(file_path)?;
+        if file_path.extension().is_some_and(|extension| extension == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, PipelineError> {
+        toml::from_str::<PipelineConfig>(text).map_err(PipelineError::Toml)?.validate()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, PipelineError> {
+        serde_json::from_str::<PipelineConfig>(text).map_err(PipelineError::Json)?.validate()
+    }
+
+    // Check that the pipeline can run
+    pub fn validate(self) -> Result<Self, PipelineError> {
+        if self.analyses.is_empty() {
+            return Err(PipelineError::Invalid("no analyses to run".to_string()));
+        }
+        for analysis in &self.analyses {
+            if let AnalysisConfig::Route(route) = analysis {
+                if route.from.is_some() != route.to.is_some() {
+                    return Err(PipelineError::Invalid("a route needs both `from` and `to`, or neither".to_string()));
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+// An output file: the result of one analysis together with the configuration that produced it
+#[derive(Debug, Serialize)]
+pub struct Provenance<'a, T> {
+    pub config: &'a PipelineConfig,
+    pub result: T,
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::InputFormat;
+    use crate::preprocess::{ParallelEdgePolicy, SelfLoopPolicy};
+
+    #[test]
+    fn test_toml_config_fills_in_defaults() {
+        let config = PipelineConfig::from_toml(
+            r#"
+            seed = 42
+            analyses = [
+                { analysis = "stats" },
+                { analysis = "pagerank", damping = 0.9 },
+                { analysis = "core-periphery", degree_threshold = 4 },
+            ]
+
+            [input]
+            path = "test_data/sample.gr"
+            directed = true
+
+            [preprocess]
+            parallel_edges = "sum"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.input.format, InputFormat::Dimacs);
+        assert!(config.input.directed && !config.input.largest_component);
+        assert_eq!(config.input.cache, None);
+        assert_eq!(config.preprocess.parallel_edges, ParallelEdgePolicy::Sum);
+        assert_eq!(config.preprocess.self_loops, SelfLoopPolicy::Drop);
+        assert_eq!(config.output.format, OutputFormat::PrettyJson);
+        assert_eq!(config.seed, 42);
+        match &config.analyses[1] {
+            AnalysisConfig::Pagerank(args) => assert_eq!((args.damping, args.iterations), (0.9, 100)),
+            other => panic!("expected pagerank, got {:?}", other),
+        }
+        match &config.analyses[2] {
+            AnalysisConfig::CorePeriphery(args) => assert_eq!((args.degree_threshold, args.top), (4, 10)),
+            other => panic!("expected core-periphery, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_config_round_trip() {
+        let config = PipelineConfig {
+            input: InputOptions::new("test_data/test_graph.txt"),
+            preprocess: PreprocessPolicy::shortest(),
+            output: OutputConfig { format: OutputFormat::Json, ..OutputConfig::default() },
+            seed: random_seed(),
+            analyses: AnalysisConfig::pipeline(),
+        };
+
+        // The embedded configuration runs the same pipeline again
+        let embedded = serde_json::to_string(&config).unwrap();
+        let reloaded = PipelineConfig::from_json(&embedded).unwrap();
+        // A seed drawn for the run is recorded, so the reloaded pipeline makes the same random choices
+        assert_eq!(reloaded.seed, config.seed);
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), embedded);
+        assert_eq!(reloaded.input, config.input);
+        assert_eq!(reloaded.input.format, InputFormat::EdgeList);
+        assert_eq!(reloaded.output.format, OutputFormat::Json);
+        assert_eq!(reloaded.analyses, config.analyses);
+    }
+
+    #[test]
+    fn test_analysis_names_match_configuration() {
+        for analysis in AnalysisConfig::pipeline() {
+            assert_eq!(serde_json::to_value(&analysis).unwrap()["analysis"], analysis.name());
+        }
+        for format in OutputFormat::ALL {
+            assert_eq!(serde_json::to_value(format).unwrap(), format.name());
+        }
+        for degree in DegreeMeasure::ALL {
+            assert_eq!(serde_json::to_value(degree).unwrap(), degree.name());
+        }
+        for format in InputFormat::ALL {
+            assert_eq!(serde_json::to_value(format).unwrap(), format.name());
+        }
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let parse = |analyses: &str| PipelineConfig::from_toml(&format!("analyses = {}\n[input]\npath = \"a.txt\"", analyses));
+
+        assert!(matches!(parse("[]"), Err(PipelineError::Invalid(_))));
+        assert!(matches!(parse("[{ analysis = \"route\", from = 1 }]"), Err(PipelineError::Invalid(_))));
+        assert!(matches!(parse("[{ analysis = \"pagerank\", dampnig = 0.9 }]"), Err(PipelineError::Toml(_))));
+        assert!(matches!(parse("[{ analysis = \"diameter\" }]"), Err(PipelineError::Toml(_))));
+        assert!(parse("[{ analysis = \"route\", from = 1, to = 2 }]").is_ok());
+        let input = |table: &str| PipelineConfig::from_toml(&format!("analyses = [{{ analysis = \"stats\" }}]\n[input]\n{}", table));
+        assert!(matches!(input("path = \"a.txt\"\nformat = \"shapefile\""), Err(PipelineError::Toml(_))));
+        assert!(matches!(input("path = \"a.txt\"\ndirectd = true"), Err(PipelineError::Toml(_))));
+        assert!(matches!(input("format = \"dimacs\""), Err(PipelineError::Toml(_))));
+    }
+}
@@ -0,0 +1,275 @@
+// preprocess.rs
+
+use crate::data::{RoadEdge, RoadNetworkOf};
+use petgraph::EdgeType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+// What to do with edges that start and end at the same node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelfLoopPolicy {
+    #[default]
+    Keep,
+    Drop,
+}
+
+// How to combine an edge with an earlier edge between the same nodes in the same direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParallelEdgePolicy {
+    // Keep the first edge read and ignore the later ones
+    #[default]
+    Keep,
+    // Keep the edge with the smallest weight, e.g. the shortest of several roads between two intersections
+    Min,
+    // Add up the weights, and the length, travel time and lane count where both edges carry them
+    Sum,
+    // Keep the edge with the largest weight
+    Max,
+}
+
+// What to do with an edge whose reverse was read earlier, such as `2 1` after `1 2`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReciprocalPolicy {
+    // Keep both directions: a directed network gets both arcs of the two-way street, while an undirected
+    // network combines the two like parallel edges
+    #[default]
+    Keep,
+    // Treat the reverse as a second listing of the same road and ignore it, whatever the parallel-edge policy.
+    // This is what the SNAP edge lists need, as they list every undirected edge once in each direction.
+    Drop,
+}
+
+// Policies applied by every loader while the edges are added to the graph. The `Default` policy keeps every
+// edge as read, which is what the library loaders do unless told otherwise. In a pipeline configuration the
+// policies that are not given default to those of `shortest` instead, as road network analyses expect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default = "PreprocessPolicy::shortest", deny_unknown_fields)]
+pub struct PreprocessPolicy {
+    pub self_loops: SelfLoopPolicy,
+    pub parallel_edges: ParallelEdgePolicy,
+    pub reciprocal_edges: ReciprocalPolicy,
+}
+
+impl PreprocessPolicy {
+    // The usual policy for road networks: self-loops carry no route and the shortest of several roads
+    // between the same intersections is the one any route takes
+    pub fn shortest() -> Self {
+        PreprocessPolicy {
+            self_loops: SelfLoopPolicy::Drop,
+            parallel_edges: ParallelEdgePolicy::Min,
+            reciprocal_edges: ReciprocalPolicy::Keep,
+        }
+    }
+}
+
+// Number of self-loops, parallel edges and reciprocal edges met during preprocessing, whatever the policy did
+// with them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PreprocessReport {
+    pub self_loops: usize,
+    pub parallel_edges: usize,
+    pub reciprocal_edges: usize,
+}
+
+impl fmt::Display for PreprocessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} self-loops, {} parallel edges, {} reciprocal edges",
+            self.self_loops, self.parallel_edges, self.reciprocal_edges
+        )
+    }
+}
+
+// Adds the edges of a loader to a graph one by one, applying a PreprocessPolicy and counting what it met
+#[derive(Debug, Default)]
+pub struct EdgeNormalizer {
+    policy: PreprocessPolicy,
+    // Node pairs of an undirected graph whose first edge was read from the larger to the smaller id, stored
+    // as (smaller, larger). An undirected graph forgets the direction, but a reverse listing has to be told
+    // apart from a repeated one.
+    reversed: HashSet<(usize, usize)>,
+    report: PreprocessReport,
+}
+
+impl EdgeNormalizer {
+    pub fn new(policy: PreprocessPolicy) -> Self {
+        EdgeNormalizer {
+            policy,
+            ..EdgeNormalizer::default()
+        }
+    }
+
+    // The counts gathered so far
+    pub fn report(&self) -> PreprocessReport {
+        self.report
+    }
+
+    // Add an edge read from the input to the graph according to the policy. Returns whether the graph changed.
+    pub fn add_edge<Ty: EdgeType>(&mut self, graph: &mut RoadNetworkOf<Ty>, a: usize, b: usize, edge: RoadEdge) -> bool {
+        if a == b {
+            self.report.self_loops += 1;
+            if self.policy.self_loops == SelfLoopPolicy::Drop {
+                return false;
+            }
+        }
+
+        // Whether an earlier edge joins the same nodes, and if so whether it was read in the opposite direction
+        let earlier = if graph.is_directed() {
+            if graph.contains_edge(a, b) {
+                Some(false)
+            } else if graph.contains_edge(b, a) {
+                Some(true)
+            } else {
+                None
+            }
+        } else if graph.contains_edge(a, b) {
+            Some(self.reversed.contains(&(a.min(b), a.max(b))) != (a > b))
+        } else {
+            None
+        };
+
+        match earlier {
+            None => {
+                if !graph.is_directed() && a > b {
+                    self.reversed.insert((b, a));
+                }
+                graph.add_edge(a, b, edge);
+                true
+            }
+            Some(true) => {
+                self.report.reciprocal_edges += 1;
+                match self.policy.reciprocal_edges {
+                    ReciprocalPolicy::Drop => false,
+                    ReciprocalPolicy::Keep if graph.is_directed() => {
+                        graph.add_edge(a, b, edge);
+                        true
+                    }
+                    ReciprocalPolicy::Keep => self.combine(graph, a, b, edge),
+                }
+            }
+            Some(false) => {
+                self.report.parallel_edges += 1;
+                self.combine(graph, a, b, edge)
+            }
+        }
+    }
+
+    // Combine an edge with the edge the graph already holds between the same nodes
+    fn combine<Ty: EdgeType>(&self, graph: &mut RoadNetworkOf<Ty>, a: usize, b: usize, edge: RoadEdge) -> bool {
+        let existing = match graph.edge_weight_mut(a, b) {
+            Some(existing) => existing,
+            None => return false,
+        };
+
+        match self.policy.parallel_edges {
+            ParallelEdgePolicy::Keep => false,
+            ParallelEdgePolicy::Min if edge.weight < existing.weight => {
+                *existing = edge;
+                true
+            }
+            ParallelEdgePolicy::Max if edge.weight > existing.weight => {
+                *existing = edge;
+                true
+            }
+            ParallelEdgePolicy::Min | ParallelEdgePolicy::Max => false,
+            ParallelEdgePolicy::Sum => {
+                existing.weight += edge.weight;
+                existing.length = existing.length.zip(edge.length).map(|(x, y)| x + y);
+                existing.travel_time = existing.travel_time.zip(edge.travel_time).map(|(x, y)| x + y);
+                existing.lanes = existing.lanes.zip(edge.lanes).map(|(x, y)| x + y);
+                true
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DirectedRoadNetwork, RoadNetwork};
+
+    // Feed the same edges to a fresh undirected graph under the given policy
+    fn normalize(policy: PreprocessPolicy, edges: &[(usize, usize, f64)]) -> (RoadNetwork, PreprocessReport) {
+        let mut graph = RoadNetwork::new();
+        let mut normalizer = EdgeNormalizer::new(policy);
+        for &(a, b, weight) in edges {
+            normalizer.add_edge(&mut graph, a, b, RoadEdge::from_weight(weight));
+        }
+        (graph, normalizer.report())
+    }
+
+    #[test]
+    fn test_parallel_edge_policies() {
+        let edges = [(1, 2, 4.0), (1, 2, 1.0), (1, 2, 6.0)];
+        let weight = |policy: ParallelEdgePolicy| {
+            let (graph, report) = normalize(PreprocessPolicy { parallel_edges: policy, ..PreprocessPolicy::default() }, &edges);
+            assert_eq!(graph.edge_count(), 1);
+            assert_eq!(report, PreprocessReport { self_loops: 0, parallel_edges: 2, reciprocal_edges: 0 });
+            graph.edge_weight(1, 2).unwrap().weight
+        };
+
+        assert_eq!(weight(ParallelEdgePolicy::Keep), 4.0);
+        assert_eq!(weight(ParallelEdgePolicy::Min), 1.0);
+        assert_eq!(weight(ParallelEdgePolicy::Sum), 11.0);
+        assert_eq!(weight(ParallelEdgePolicy::Max), 6.0);
+    }
+
+    #[test]
+    fn test_reciprocal_edges_are_told_apart_from_repeats() {
+        // The SNAP layout: every edge in both directions, plus one genuine repeat and a self-loop
+        let edges = [(1, 2, 1.0), (2, 1, 1.0), (3, 2, 1.0), (2, 3, 1.0), (3, 2, 1.0), (4, 4, 1.0)];
+
+        let sum = PreprocessPolicy { parallel_edges: ParallelEdgePolicy::Sum, ..PreprocessPolicy::default() };
+        let (graph, report) = normalize(sum, &edges);
+        assert_eq!(report, PreprocessReport { self_loops: 1, parallel_edges: 1, reciprocal_edges: 2 });
+        assert_eq!(graph.edge_weight(1, 2).unwrap().weight, 2.0);
+        assert_eq!(graph.edge_weight(2, 3).unwrap().weight, 3.0);
+        assert!(graph.contains_edge(4, 4));
+
+        // Dropping the reverse listings leaves only the repeat to be summed
+        let deduplicated = PreprocessPolicy { reciprocal_edges: ReciprocalPolicy::Drop, self_loops: SelfLoopPolicy::Drop, ..sum };
+        let (graph, report) = normalize(deduplicated, &edges);
+        assert_eq!(report, PreprocessReport { self_loops: 1, parallel_edges: 1, reciprocal_edges: 2 });
+        assert_eq!(graph.edge_weight(1, 2).unwrap().weight, 1.0);
+        assert_eq!(graph.edge_weight(2, 3).unwrap().weight, 2.0);
+        assert!(!graph.contains_node(4));
+    }
+
+    #[test]
+    fn test_default_policy_differs_from_configured_default() {
+        let keep = PreprocessPolicy {
+            self_loops: SelfLoopPolicy::Keep,
+            parallel_edges: ParallelEdgePolicy::Keep,
+            reciprocal_edges: ReciprocalPolicy::Keep,
+        };
+        assert_eq!(PreprocessPolicy::default(), keep);
+
+        // A configuration fills in what it leaves out from `shortest`, not from `Default`
+        let configured: PreprocessPolicy = serde_json::from_str("{}").unwrap();
+        assert_eq!(configured, PreprocessPolicy::shortest());
+        let configured: PreprocessPolicy = serde_json::from_str(r#"{ "parallel_edges": "sum" }"#).unwrap();
+        assert_eq!(configured, PreprocessPolicy { parallel_edges: ParallelEdgePolicy::Sum, ..PreprocessPolicy::shortest() });
+    }
+
+    #[test]
+    fn test_reciprocal_arcs_in_directed_graph() {
+        let mut graph = DirectedRoadNetwork::new();
+        let mut normalizer = EdgeNormalizer::new(PreprocessPolicy::shortest());
+        normalizer.add_edge(&mut graph, 1, 2, RoadEdge::from_weight(3.0));
+        normalizer.add_edge(&mut graph, 2, 1, RoadEdge::from_weight(5.0));
+        normalizer.add_edge(&mut graph, 2, 1, RoadEdge::from_weight(4.0));
+
+        // The reverse arc is the other direction of a two-way street and keeps its own weight
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.edge_weight(1, 2).unwrap().weight, 3.0);
+        assert_eq!(graph.edge_weight(2, 1).unwrap().weight, 4.0);
+        assert_eq!(normalizer.report(), PreprocessReport { self_loops: 0, parallel_edges: 1, reciprocal_edges: 1 });
+    }
+}
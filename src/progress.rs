@@ -0,0 +1,187 @@
+// progress.rs
+
+// Progress reporting and per-stage measurements. The long-running parts of the library (loading, PageRank
+// iterations, Leiden passes, the landmark Dijkstra runs and the bridge search) report their steps to a
+// `Progress`, which draws live progress bars or ignores them. `measure_stage` records the wall time and peak
+// memory of a stage of a run for the report.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Receiver of the progress of a long-running task. Tasks run one after another: `start` announces a task,
+// `advance` reports its steps, from any thread, and `finish` ends it.
+pub trait Progress: Sync {
+    // A task of `total` steps starts, or of an unknown number of steps when `total` is None
+    fn start(&self, task: &str, total: Option<u64>);
+    // `steps` more steps of the current task are done
+    fn advance(&self, steps: u64);
+    // The current task is done
+    fn finish(&self);
+}
+
+// Progress that is not reported anywhere
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn start(&self, _task: &str, _total: Option<u64>) {}
+    fn advance(&self, _steps: u64) {}
+    fn finish(&self) {}
+}
+
+// Live progress bars on the terminal, one per task. Bars are drawn on stderr, so they stay out of redirected
+// output, and are hidden when stderr is not a terminal.
+#[derive(Debug, Default)]
+pub struct ProgressBars {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl ProgressBars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Progress for ProgressBars {
+    fn start(&self, task: &str, total: Option<u64>) {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total).with_style(
+                ProgressStyle::with_template("{msg:>12} [{bar:40}] {pos}/{len} ({elapsed}, eta {eta})")
+                    .expect("valid progress template")
+                    .progress_chars("=> "),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template("{msg:>12} {spinner} {pos} ({elapsed})").expect("valid progress template"),
+            ),
+        };
+        bar.set_message(task.to_string());
+        if let Some(previous) = self.bar.lock().unwrap().replace(bar) {
+            previous.finish_and_clear();
+        }
+    }
+
+    fn advance(&self, steps: u64) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.inc(steps);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+// Wall time and peak memory of one stage of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub seconds: f64,
+    // Peak resident memory of the process during the stage, in bytes. Only measured on Linux; elsewhere, or
+    // when the kernel does not allow resetting the peak, it is left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_memory_bytes: Option<u64>,
+}
+
+// Run one stage of a run and measure it
+pub fn measure_stage<T>(stage: &str, run: impl FnOnce() -> T) -> (T, StageTiming) {
+    let peak_was_reset = reset_peak_memory();
+    let started = Instant::now();
+    let result = run();
+    let seconds = started.elapsed().as_secs_f64();
+    let peak_memory_bytes = if peak_was_reset { peak_memory() } else { None };
+    (result, StageTiming { stage: stage.to_string(), seconds, peak_memory_bytes })
+}
+
+// Reset the peak resident memory the kernel records for the process to its current size, so the next reading
+// covers only what happens after the reset. Returns whether the reset worked.
+#[cfg(target_os = "linux")]
+fn reset_peak_memory() -> bool {
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reset_peak_memory() -> bool {
+    false
+}
+
+// Peak resident memory of the process since it started or since the last reset, in bytes
+#[cfg(target_os = "linux")]
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_memory() -> Option<u64> {
+    None
+}
+
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // Records the tasks and counts the steps it is told about, for the tests of the reporting analyses
+    #[derive(Default)]
+    pub(crate) struct CountingProgress {
+        tasks: Mutex<Vec<(String, Option<u64>)>>,
+        steps: AtomicU64,
+    }
+
+    impl CountingProgress {
+        pub(crate) fn tasks(&self) -> Vec<(String, Option<u64>)> {
+            self.tasks.lock().unwrap().clone()
+        }
+
+        pub(crate) fn steps(&self) -> u64 {
+            self.steps.load(Ordering::Relaxed)
+        }
+    }
+
+    impl Progress for CountingProgress {
+        fn start(&self, task: &str, total: Option<u64>) {
+            self.tasks.lock().unwrap().push((task.to_string(), total));
+        }
+        fn advance(&self, steps: u64) {
+            self.steps.fetch_add(steps, Ordering::Relaxed);
+        }
+        fn finish(&self) {}
+    }
+
+    #[test]
+    fn test_measure_stage_records_time_and_memory() {
+        let (sum, timing) = measure_stage("sum", || {
+            // Touch a few megabytes so the peak is clearly above zero
+            let values = vec![1u64; 4 << 20];
+            values.iter().sum::<u64>()
+        });
+
+        assert_eq!(sum, 4 << 20);
+        assert_eq!(timing.stage, "sum");
+        assert!(timing.seconds >= 0.0);
+        // Where the peak can be measured, it covers the vector
+        if let Some(peak_memory_bytes) = timing.peak_memory_bytes {
+            assert!(peak_memory_bytes >= 32 << 20);
+        }
+    }
+
+    #[test]
+    fn test_progress_bars_survive_unstarted_tasks() {
+        // Steps and finishes outside a task are ignored rather than panicking
+        let bars = ProgressBars::new();
+        bars.advance(3);
+        bars.finish();
+        bars.start("test", Some(2));
+        bars.advance(2);
+        bars.start("next", None);
+        bars.finish();
+    }
+}
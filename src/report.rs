@@ -0,0 +1,600 @@
+// report.rs
+
+// The report of an analysis run: one structured object that every analysis of the pipeline fills in with its
+// summary, serialized as JSON (see "Report schema" in the README) or rendered as Markdown in the layout of the
+// README's results tables. Nodes are always given by their dataset ids, and the sections of analyses that did
+// not run are left out.
+
+use crate::centrality_analysis::{Measure, MeasurePair, RankOutlier};
+use crate::data::NetworkProperties;
+use crate::progress::StageTiming;
+use crate::route::ShortestPath;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Version of the report schema, raised whenever a field changes meaning or is removed
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub graph: GraphSummary,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<StatsSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degree_centrality: Option<RankingSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eigenvector_centrality: Option<RankingSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub katz_centrality: Option<RankingSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub betweenness: Option<BetweennessSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closeness: Option<ClosenessSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagerank: Option<RankingSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub centrality_comparison: Option<CentralityComparisonSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub communities: Option<CommunitySection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub core_periphery: Option<CorePeripherySection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridges: Option<BridgeSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<ComponentSection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routes: Option<RouteSection>,
+    // Wall time and peak memory of loading and of every analysis, in the order they ran
+    #[serde(default)]
+    pub stages: Vec<StageTiming>,
+}
+
+// Size of the analysed network; `arcs` is only present in directed mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphSummary {
+    pub nodes: usize,
+    pub edges: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arcs: Option<usize>,
+}
+
+// Whole-graph measures of the `stats` analysis. `degree_histogram[d]` is the number of nodes of degree d.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsSection {
+    pub weakly_connected_components: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strongly_connected_components: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub largest_strongly_connected_component: Option<usize>,
+    pub degree_histogram: Vec<usize>,
+    pub clustering_coefficient: f64,
+    pub density: f64,
+    pub assortativity: f64,
+}
+
+impl StatsSection {
+    pub fn new(
+        properties: NetworkProperties,
+        degree_histogram: Vec<usize>,
+        clustering_coefficient: f64,
+        density: f64,
+        assortativity: f64,
+    ) -> Self {
+        StatsSection {
+            weakly_connected_components: properties.weakly_connected_components,
+            strongly_connected_components: properties.strongly_connected_components,
+            largest_strongly_connected_component: properties.largest_strongly_connected_component,
+            degree_histogram,
+            clustering_coefficient,
+            density,
+            assortativity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RankedNode {
+    pub node: usize,
+    pub score: f64,
+}
+
+// The top nodes of a node ranking, highest score first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankingSection {
+    pub top: Vec<RankedNode>,
+}
+
+impl RankingSection {
+    // Keep the `k` highest scores. Ties go to the smaller id, so the section does not depend on the order the
+    // scores come in.
+    pub fn top_k(scores: impl IntoIterator<Item = (usize, f64)>, k: usize) -> Self {
+        let mut top: Vec<RankedNode> = scores.into_iter().map(|(node, score)| RankedNode { node, score }).collect();
+        top.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.node.cmp(&b.node)));
+        top.truncate(k);
+        RankingSection { top }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RankedEdge {
+    pub edge: (usize, usize),
+    pub score: f64,
+}
+
+// Normalized betweenness of the top nodes and edges, from `sources` searches. When pivots were sampled the error
+// bounds give the half-widths of intervals that hold the exact scores of all nodes, or all edges, with
+// probability `confidence`; they are 0 for exact scores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BetweennessSection {
+    pub weighted: bool,
+    pub sources: usize,
+    pub exact: bool,
+    pub confidence: f64,
+    pub node_error_bound: f64,
+    pub edge_error_bound: f64,
+    pub top: Vec<RankedNode>,
+    pub top_edges: Vec<RankedEdge>,
+}
+
+impl BetweennessSection {
+    // Keep the `k` highest scoring edges, ties in id order as in `RankingSection::top_k`
+    pub fn top_edges(scores: impl IntoIterator<Item = ((usize, usize), f64)>, k: usize) -> Vec<RankedEdge> {
+        let mut top: Vec<RankedEdge> = scores.into_iter().map(|(edge, score)| RankedEdge { edge, score }).collect();
+        top.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.edge.cmp(&b.edge)));
+        top.truncate(k);
+        top
+    }
+}
+
+// The top nodes by closeness and by harmonic centrality, from `sources` searches; estimates unless `exact`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClosenessSection {
+    pub weighted: bool,
+    pub sources: usize,
+    pub exact: bool,
+    pub closeness: Vec<RankedNode>,
+    pub harmonic: Vec<RankedNode>,
+}
+
+// Agreement of every pair of the compared centrality measures over all nodes, with betweenness and closeness from
+// `sources` searches, and the first `top` of the `outlier_count` nodes that rank at or above `high_percentile`
+// on one measure and at or below `low_percentile` on all the others
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CentralityComparisonSection {
+    pub measures: Vec<Measure>,
+    pub sources: usize,
+    pub exact: bool,
+    pub top_k: usize,
+    pub high_percentile: f64,
+    pub low_percentile: f64,
+    pub pairs: Vec<MeasurePair>,
+    pub outlier_count: usize,
+    pub outliers: Vec<RankOutlier<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommunitySize {
+    pub community: usize,
+    pub size: usize,
+}
+
+// Number of communities found, and the largest ones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommunitySection {
+    pub count: usize,
+    pub largest: Vec<CommunitySize>,
+}
+
+impl CommunitySection {
+    // Summarize a node-to-community assignment, keeping the `k` largest communities
+    pub fn from_assignments(assignments: &HashMap<usize, usize>, k: usize) -> Self {
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for community in assignments.values() {
+            *sizes.entry(*community).or_default() += 1;
+        }
+        let count = sizes.len();
+        let mut largest: Vec<CommunitySize> =
+            sizes.into_iter().map(|(community, size)| CommunitySize { community, size }).collect();
+        largest.sort_by_key(|entry| (std::cmp::Reverse(entry.size), entry.community));
+        largest.truncate(k);
+        CommunitySection { count, largest }
+    }
+}
+
+// Nodes above the degree threshold form the core; the top lists hold the highest degree nodes of each part
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorePeripherySection {
+    pub degree_threshold: usize,
+    pub core_count: usize,
+    pub periphery_count: usize,
+    pub top_core: Vec<usize>,
+    pub top_periphery: Vec<usize>,
+}
+
+// Number of bridge edges, and up to the limit of them as (smaller id, larger id) pairs in id order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeSection {
+    pub count: usize,
+    pub sample: Vec<(usize, usize)>,
+}
+
+// Connected components, and in directed mode the strongly connected components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentSection {
+    pub count: usize,
+    pub largest_size: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strongly_connected: Option<StrongComponents>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrongComponents {
+    pub count: usize,
+    // Nodes that can be entered or left, but not both, while respecting one-way streets
+    pub outside_largest: usize,
+}
+
+// Shortest paths of the `route` analysis; an empty list means no path was found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteSection {
+    pub paths: Vec<ShortestPath>,
+}
+
+impl Report {
+    // An empty report of a network; the analyses fill in their sections
+    pub fn new(graph: GraphSummary) -> Self {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            graph,
+            stats: None,
+            degree_centrality: None,
+            eigenvector_centrality: None,
+            katz_centrality: None,
+            betweenness: None,
+            closeness: None,
+            pagerank: None,
+            centrality_comparison: None,
+            communities: None,
+            core_periphery: None,
+            bridges: None,
+            components: None,
+            routes: None,
+            stages: Vec::new(),
+        }
+    }
+
+    // Render the report as Markdown, one section per analysis in the layout of the README's results
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String cannot fail
+        self.write_markdown(&mut out).expect("writing to a String");
+        out
+    }
+
+    fn write_markdown(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "## Results")?;
+        writeln!(out)?;
+        writeln!(out, "**Number of nodes: {}**", self.graph.nodes)?;
+        writeln!(out)?;
+        writeln!(out, "**Number of edges: {}**", self.graph.edges)?;
+        if let Some(arcs) = self.graph.arcs {
+            writeln!(out)?;
+            writeln!(out, "**Number of arcs: {}**", arcs)?;
+        }
+
+        if let Some(stats) = &self.stats {
+            heading(out, "Assortativity Coefficient")?;
+            writeln!(out, "**Assortativity Coefficient: {}**", stats.assortativity)?;
+            heading(out, "Network Density")?;
+            writeln!(out, "**Network density: {}**", stats.density)?;
+            heading(out, "Clustering Coefficient")?;
+            writeln!(out, "**Clustering coefficient: {}**", stats.clustering_coefficient)?;
+            heading(out, "Degree Distribution")?;
+            let rows = stats.degree_histogram.iter().enumerate().map(|(degree, count)| vec![degree.to_string(), count.to_string()]);
+            table(out, &["Degree", "Number of Nodes"], rows)?;
+            heading(out, "Weakly Connected Components")?;
+            writeln!(out, "**Number of weakly connected components: {}**", stats.weakly_connected_components)?;
+            if let (Some(count), Some(largest)) =
+                (stats.strongly_connected_components, stats.largest_strongly_connected_component)
+            {
+                writeln!(out)?;
+                writeln!(out, "**Number of strongly connected components: {}**", count)?;
+                writeln!(out)?;
+                writeln!(out, "**Largest strongly connected component: {} of {} nodes**", largest, self.graph.nodes)?;
+            }
+        }
+
+        if let Some(degree_centrality) = &self.degree_centrality {
+            heading(out, "Degree Centrality")?;
+            ranking_table(out, degree_centrality)?;
+        }
+
+        if let Some(eigenvector_centrality) = &self.eigenvector_centrality {
+            heading(out, "Eigenvector Centrality")?;
+            ranking_table(out, eigenvector_centrality)?;
+        }
+
+        if let Some(katz_centrality) = &self.katz_centrality {
+            heading(out, "Katz Centrality")?;
+            ranking_table(out, katz_centrality)?;
+        }
+
+        if let Some(betweenness) = &self.betweenness {
+            heading(out, "Betweenness Centrality")?;
+            let paths = if betweenness.weighted { "by edge weight" } else { "in hops" };
+            writeln!(out, "**Shortest paths from {} of {} nodes, {}**", betweenness.sources, self.graph.nodes, paths)?;
+            if !betweenness.exact {
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "**Error bound at {}% confidence: {:.4} for nodes, {:.4} for edges**",
+                    betweenness.confidence * 100.0,
+                    betweenness.node_error_bound,
+                    betweenness.edge_error_bound
+                )?;
+            }
+            writeln!(out)?;
+            let rows = betweenness.top.iter().map(|entry| vec![entry.node.to_string(), entry.score.to_string()]);
+            table(out, &["Node Index", "Value"], rows)?;
+            writeln!(out)?;
+            let rows = betweenness
+                .top_edges
+                .iter()
+                .map(|entry| vec![format!("({}, {})", entry.edge.0, entry.edge.1), entry.score.to_string()]);
+            table(out, &["Edge", "Value"], rows)?;
+        }
+
+        if let Some(closeness) = &self.closeness {
+            heading(out, "Closeness Centrality")?;
+            let distances = if closeness.weighted { "by edge weight" } else { "in hops" };
+            let kind = if closeness.exact { "exact" } else { "estimated" };
+            writeln!(
+                out,
+                "**Distances from {} of {} nodes, {}, scores {}**",
+                closeness.sources, self.graph.nodes, distances, kind
+            )?;
+            writeln!(out)?;
+            let rows = closeness.closeness.iter().map(|entry| vec![entry.node.to_string(), entry.score.to_string()]);
+            table(out, &["Node Index", "Value"], rows)?;
+            heading(out, "Harmonic Centrality")?;
+            let rows = closeness.harmonic.iter().map(|entry| vec![entry.node.to_string(), entry.score.to_string()]);
+            table(out, &["Node Index", "Value"], rows)?;
+        }
+
+        if let Some(pagerank) = &self.pagerank {
+            heading(out, "PageRank")?;
+            ranking_table(out, pagerank)?;
+        }
+
+        if let Some(comparison) = &self.centrality_comparison {
+            heading(out, "Centrality Comparison")?;
+            let searched = [Measure::Betweenness, Measure::Closeness, Measure::Harmonic];
+            if comparison.measures.iter().any(|measure| searched.contains(measure)) {
+                let kind = if comparison.exact { "exact" } else { "estimated" };
+                writeln!(
+                    out,
+                    "**Shortest paths from {} of {} nodes, scores {}**",
+                    comparison.sources, self.graph.nodes, kind
+                )?;
+                writeln!(out)?;
+            }
+            let correlation = |value: Option<f64>| value.map_or("-".to_string(), |value| format!("{:.4}", value));
+            let rows = comparison.pairs.iter().map(|pair| {
+                vec![
+                    format!("{} / {}", pair.first, pair.second),
+                    correlation(pair.spearman),
+                    correlation(pair.kendall),
+                    format!("{:.4}", pair.top_k_jaccard),
+                ]
+            });
+            let jaccard = format!("Top-{} Jaccard", comparison.top_k);
+            table(out, &["Measures", "Spearman", "Kendall", &jaccard], rows)?;
+            writeln!(out)?;
+            writeln!(
+                out,
+                "**Nodes at or above percentile {} on one measure and at or below {} on the others: {}**",
+                comparison.high_percentile, comparison.low_percentile, comparison.outlier_count
+            )?;
+            if !comparison.outliers.is_empty() {
+                writeln!(out)?;
+                let rows = comparison.outliers.iter().map(|outlier| {
+                    vec![
+                        outlier.node.to_string(),
+                        outlier.measure.to_string(),
+                        format!("{:.4}", outlier.percentile),
+                        format!("{:.4}", outlier.highest_other),
+                    ]
+                });
+                table(out, &["Node Index", "Measure", "Percentile", "Highest Elsewhere"], rows)?;
+            }
+        }
+
+        if let Some(bridges) = &self.bridges {
+            heading(out, "Number of Bridge Edges")?;
+            writeln!(out, "**Number of bridge edges: {}**", bridges.count)?;
+            writeln!(out)?;
+            let rows = bridges.sample.iter().enumerate().map(|(i, (a, b))| vec![(i + 1).to_string(), format!("({}, {})", a, b)]);
+            table(out, &["Bridge Edge", "Nodes"], rows)?;
+        }
+
+        if let Some(components) = &self.components {
+            heading(out, "Number of Connected Components")?;
+            writeln!(out, "**Number of connected components: {}**", components.count)?;
+            writeln!(out)?;
+            writeln!(out, "**Largest connected component: {} nodes**", components.largest_size)?;
+            if let Some(strong) = components.strongly_connected {
+                writeln!(out)?;
+                writeln!(out, "**Number of strongly connected components: {}**", strong.count)?;
+                writeln!(out)?;
+                writeln!(out, "**Nodes outside the largest strongly connected component: {}**", strong.outside_largest)?;
+            }
+        }
+
+        if let Some(communities) = &self.communities {
+            heading(out, "Leiden Community Detection")?;
+            writeln!(out, "**Number of communities: {}**", communities.count)?;
+            writeln!(out)?;
+            let rows = communities
+                .largest
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| vec![(i + 1).to_string(), entry.community.to_string(), entry.size.to_string()]);
+            table(out, &["Community", "ID", "Size"], rows)?;
+        }
+
+        if let Some(core_periphery) = &self.core_periphery {
+            heading(out, "Core-Periphery Structure")?;
+            writeln!(out, "**Number of core nodes: {}**", core_periphery.core_count)?;
+            writeln!(out)?;
+            writeln!(out, "**Number of periphery nodes: {}**", core_periphery.periphery_count)?;
+            writeln!(out)?;
+            // The two lists are cut to the same length, but either part may have fewer nodes
+            let rank_count = core_periphery.top_core.len().max(core_periphery.top_periphery.len());
+            let cell = |nodes: &[usize], i: usize| nodes.get(i).map_or(String::new(), usize::to_string);
+            let rows = (0..rank_count).map(|i| {
+                vec![(i + 1).to_string(), cell(&core_periphery.top_core, i), cell(&core_periphery.top_periphery, i)]
+            });
+            table(out, &["Rank", "Core Node", "Periphery Node"], rows)?;
+        }
+
+        if let Some(routes) = &self.routes {
+            heading(out, "Shortest Paths")?;
+            if routes.paths.is_empty() {
+                writeln!(out, "No path found between the starting and ending nodes.")?;
+            } else {
+                let rows = routes.paths.iter().map(|path| {
+                    let nodes: Vec<String> = path.path.iter().map(usize::to_string).collect();
+                    vec![path.index.to_string(), path.weight.to_string(), nodes.join(" - ")]
+                });
+                table(out, &["Path", "Weight", "Nodes"], rows)?;
+            }
+        }
+
+        if !self.stages.is_empty() {
+            heading(out, "Stage Timings")?;
+            let rows = self.stages.iter().map(|timing| {
+                let memory = timing
+                    .peak_memory_bytes
+                    .map_or(String::new(), |bytes| format!("{:.1}", bytes as f64 / (1024.0 * 1024.0)));
+                vec![timing.stage.clone(), format!("{:.3}", timing.seconds), memory]
+            });
+            table(out, &["Stage", "Wall Time (s)", "Peak Memory (MiB)"], rows)?;
+        }
+        Ok(())
+    }
+}
+
+fn heading(out: &mut String, title: &str) -> std::fmt::Result {
+    writeln!(out)?;
+    writeln!(out, "### {}", title)
+}
+
+fn ranking_table(out: &mut String, ranking: &RankingSection) -> std::fmt::Result {
+    let rows = ranking.top.iter().map(|entry| vec![entry.node.to_string(), entry.score.to_string()]);
+    table(out, &["Node Index", "Value"], rows)
+}
+
+// A Markdown table with every column padded to its widest cell, so it also reads well as plain text
+fn table(out: &mut String, headers: &[&str], rows: impl Iterator<Item = Vec<String>>) -> std::fmt::Result {
+    let rows: Vec<Vec<String>> = rows.collect();
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(column, header)| rows.iter().map(|row| row[column].len()).chain([header.len()]).max().unwrap_or(0))
+        .collect();
+
+    let line = |out: &mut String, cells: &mut dyn Iterator<Item = &str>| -> std::fmt::Result {
+        for (cell, width) in cells.zip(&widths) {
+            write!(out, "| {:<width$} ", cell, width = width)?;
+        }
+        writeln!(out, "|")
+    };
+    line(out, &mut headers.iter().copied())?;
+    for width in &widths {
+        write!(out, "|{}", "-".repeat(width + 2))?;
+    }
+    writeln!(out, "|")?;
+    for row in &rows {
+        line(out, &mut row.iter().map(String::as_str))?;
+    }
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_report() -> Report {
+        let mut report = Report::new(GraphSummary { nodes: 4, edges: 3, arcs: None });
+        let properties = NetworkProperties {
+            weakly_connected_components: 1,
+            strongly_connected_components: None,
+            largest_strongly_connected_component: None,
+        };
+        report.stats = Some(StatsSection::new(properties, vec![0, 2, 2], 0.0, 0.5, -0.5));
+        report.pagerank = Some(RankingSection::top_k([(0, 0.18), (1, 0.32), (2, 0.32), (3, 0.18)], 2));
+        report.bridges = Some(BridgeSection { count: 3, sample: vec![(0, 1), (1, 2)] });
+        report.stages = vec![
+            StageTiming { stage: "load".to_string(), seconds: 1.25, peak_memory_bytes: Some(3 << 20) },
+            StageTiming { stage: "pagerank".to_string(), seconds: 0.5, peak_memory_bytes: None },
+        ];
+        report
+    }
+
+    #[test]
+    fn test_top_k_breaks_ties_by_id() {
+        let ranking = RankingSection::top_k([(7, 1.0), (3, 2.0), (5, 2.0), (1, 0.5)], 3);
+        let nodes: Vec<usize> = ranking.top.iter().map(|entry| entry.node).collect();
+        assert_eq!(nodes, vec![3, 5, 7]);
+    }
+
+    #[test]
+    fn test_community_section_counts_sizes() {
+        let assignments = HashMap::from([(0, 10), (1, 10), (2, 10), (3, 20), (4, 30), (5, 30)]);
+        let section = CommunitySection::from_assignments(&assignments, 2);
+
+        assert_eq!(section.count, 3);
+        assert_eq!(
+            section.largest,
+            vec![CommunitySize { community: 10, size: 3 }, CommunitySize { community: 30, size: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_json_leaves_out_analyses_that_did_not_run() {
+        let report = sample_report();
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["stats"]["degree_histogram"], serde_json::json!([0, 2, 2]));
+        assert_eq!(json["pagerank"]["top"][0], serde_json::json!({ "node": 1, "score": 0.32 }));
+        assert_eq!(json["bridges"]["sample"], serde_json::json!([[0, 1], [1, 2]]));
+        assert!(json.get("communities").is_none() && json["graph"].get("arcs").is_none());
+        assert!(json["stats"].get("strongly_connected_components").is_none());
+        assert_eq!(json["stages"][0], serde_json::json!({ "stage": "load", "seconds": 1.25, "peak_memory_bytes": 3 << 20 }));
+
+        let reloaded: Report = serde_json::from_value(json).unwrap();
+        assert_eq!(reloaded, report);
+    }
+
+    #[test]
+    fn test_markdown_tables() {
+        let markdown = sample_report().to_markdown();
+
+        assert!(markdown.contains("**Assortativity Coefficient: -0.5**"));
+        assert!(markdown.contains(
+            "| Degree | Number of Nodes |\n\
+             |--------|-----------------|\n\
+             | 0      | 0               |\n\
+             | 1      | 2               |\n"
+        ));
+        assert!(markdown.contains("| Node Index | Value |\n|------------|-------|\n| 1          | 0.32  |\n"));
+        assert!(markdown.contains("| 2           | (1, 2) |\n"));
+        assert!(!markdown.contains("Leiden"));
+        assert!(markdown.contains("| load     | 1.250         | 3.0               |\n| pagerank | 0.500         |                   |\n"));
+    }
+}
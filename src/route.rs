@@ -0,0 +1,189 @@
+// route.rs
+
+use crate::data::{node_indices_by_id, RoadEdge, RoadGraph};
+use crate::progress::Progress;
+use crate::shortest_path::{find_shortest_paths, precompute_landmark_distances_with_progress, select_landmarks};
+use petgraph::graph::NodeIndex;
+use petgraph::EdgeType;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Routes between dataset ids on a road graph, built on the landmark search of shortest_path.rs
+
+// How many landmarks guide the search and how many paths to find, between the given ids or random endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteOptions {
+    pub landmarks: usize,
+    pub paths: usize,
+    // Original ids of the start and end node; random nodes when None
+    pub endpoints: Option<(usize, usize)>,
+}
+
+impl Default for RouteOptions {
+    fn default() -> Self {
+        RouteOptions { landmarks: 10, paths: 10, endpoints: None }
+    }
+}
+
+// One path of a route, as the sequence of original node ids it passes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortestPath {
+    // 1-based rank of the path among those found
+    pub index: usize,
+    pub weight: f64,
+    pub path: Vec<usize>,
+}
+
+
+// Pick a random start node and a random end node reachable from it, drawing both from the given generator.
+// The graph must have at least one node.
+pub fn get_start_end_nodes<Ty: EdgeType, R: Rng + ?Sized>(graph: &RoadGraph<Ty>, rng: &mut R) -> (NodeIndex, NodeIndex) {
+    // Get the number of nodes in the graph
+    let num_nodes = graph.node_count();
+
+    // Choose a random start node
+    let start_node = rng.gen_range(0..num_nodes);
+
+    // Convert the start node index to a `NodeIndex`
+    let start_node_index = NodeIndex::new(start_node);
+
+    // Perform a breadth-first search to find all nodes reachable from the start node
+    let mut connected_component = Vec::new();
+    let mut bfs = petgraph::visit::Bfs::new(graph, start_node_index);
+
+    while let Some(node) = bfs.next(graph) {
+        connected_component.push(node);
+    }
+
+    // Choose a random node from the connected component as the end node
+    let end_node_index = connected_component.choose(rng).unwrap().to_owned();
+
+    // Return the start and end node indices as a tuple
+    (start_node_index, end_node_index)
+}
+
+
+// Find the shortest paths between two connected nodes using landmarks, as sequences of original node ids. The
+// endpoints are given by their ids or picked at random; the landmarks are always random. Both are drawn from
+// `rng`, and the landmark precomputation reports to `progress`. On a directed graph the paths respect one-way
+// streets. Returns None if the given endpoints are not part of the graph, or if the graph has no nodes.
+pub fn find_routes<Ty: EdgeType, R: Rng + ?Sized>(
+    graph: &RoadGraph<Ty>,
+    options: &RouteOptions,
+    rng: &mut R,
+    progress: &dyn Progress,
+) -> Option<Vec<ShortestPath>> {
+    // There are no endpoints to pick from an empty graph
+    if graph.node_count() == 0 {
+        return None;
+    }
+
+    // Select landmarks
+    let landmarks = select_landmarks(graph, options.landmarks, rng);
+
+    // Precompute landmark distances
+    let landmark_distances =
+        precompute_landmark_distances_with_progress(graph, &landmarks, |edge: &RoadEdge| edge.weight, progress);
+
+    // Get starting and ending nodes
+    let (start_node, end_node) = match options.endpoints {
+        Some((from, to)) => {
+            let indices = node_indices_by_id(graph);
+            match (indices.get(&from), indices.get(&to)) {
+                (Some(&start_node), Some(&end_node)) => (start_node, end_node),
+                _ => return None,
+            }
+        }
+        None => get_start_end_nodes(graph, rng),
+    };
+
+    // Shortest Path
+    let shortest_paths = find_shortest_paths(
+        graph,
+        start_node,
+        end_node,
+        &landmark_distances,
+        options.paths,
+        |edge: &RoadEdge| edge.weight,
+    );
+
+    let mut shortest_paths_output = Vec::new();
+    for (i, (path_weight, path)) in shortest_paths.iter().enumerate() {
+        let mut current_node = start_node;
+        let mut path_ids = vec![graph[current_node].id];
+        while current_node != end_node {
+            match path.get(&current_node) {
+                Some(next_node) => {
+                    current_node = *next_node;
+                    path_ids.push(graph[current_node].id);
+                }
+                None => {
+                    break;
+                }
+            }
+        }
+        shortest_paths_output.push(ShortestPath {
+            index: i + 1,
+            weight: *path_weight,
+            path: path_ids,
+        });
+    }
+    Some(shortest_paths_output)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{build_road_graph, NodeCoordinates, RoadNetwork};
+    use crate::progress::NoProgress;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // The path 0 - 1 - 2 - 3
+    fn create_test_graph() -> RoadGraph {
+        let network = RoadNetwork::from_edges([(0, 1, RoadEdge::default()), (1, 2, RoadEdge::default()), (2, 3, RoadEdge::default())]);
+        build_road_graph(network, &NodeCoordinates::new()).0
+    }
+
+    #[test]
+    fn test_get_start_end_nodes() {
+        let graph = create_test_graph();
+        let (start_node, end_node) = get_start_end_nodes(&graph, &mut StdRng::seed_from_u64(7));
+
+        assert!(graph.node_indices().any(|n| n == start_node));
+        assert!(graph.node_indices().any(|n| n == end_node));
+        // The same seed picks the same endpoints
+        assert_eq!(get_start_end_nodes(&graph, &mut StdRng::seed_from_u64(7)), (start_node, end_node));
+    }
+
+    #[test]
+    fn test_find_routes_is_reproducible() {
+        let graph = create_test_graph();
+        let options = RouteOptions::default();
+        let route = |seed| find_routes(&graph, &options, &mut StdRng::seed_from_u64(seed), &NoProgress).unwrap();
+
+        let paths = route(3);
+        assert!(!paths.is_empty());
+        assert_eq!(route(3), paths);
+    }
+
+    #[test]
+    fn test_find_routes_between_ids() {
+        let graph = create_test_graph();
+        let options = RouteOptions { endpoints: Some((3, 0)), ..RouteOptions::default() };
+        let paths = find_routes(&graph, &options, &mut StdRng::seed_from_u64(1), &NoProgress).unwrap();
+
+        assert_eq!(paths[0], ShortestPath { index: 1, weight: 3.0, path: vec![3, 2, 1, 0] });
+        let missing = RouteOptions { endpoints: Some((3, 9)), ..RouteOptions::default() };
+        assert!(find_routes(&graph, &missing, &mut StdRng::seed_from_u64(1), &NoProgress).is_none());
+    }
+
+    #[test]
+    fn test_find_routes_on_empty_graph() {
+        let graph: RoadGraph = RoadGraph::default();
+        assert!(find_routes(&graph, &RouteOptions::default(), &mut StdRng::seed_from_u64(1), &NoProgress).is_none());
+    }
+}
@@ -1,67 +1,112 @@
 // shortest_path.rs
 
-use petgraph::Graph;
 use petgraph::algo::dijkstra;
-use petgraph::Undirected;
-use petgraph::graph::NodeIndex;
-use petgraph::visit::Bfs;
+use petgraph::visit::{EdgeRef, GraphProp, IntoEdges, IntoEdgesDirected, IntoNodeIdentifiers, Reversed, Visitable};
 use std::collections::HashMap;
-use petgraph::visit::EdgeRef;
+use std::hash::Hash;
 use rand::seq::SliceRandom;
-use std::cmp::{Ord, Ordering, PartialEq, PartialOrd};
+use rand::Rng;
+use std::cmp::{Ord, PartialEq, PartialOrd};
 use std::collections::BinaryHeap;
-use crate::HashSet;
+use std::collections::HashSet;
+use crate::progress::{NoProgress, Progress};
 
-// Landmark-based approach
+// Landmark-based approach. Every function works on any petgraph graph reference, such as `&RoadGraph` or
+// `&RoadNetwork`, and reads edge lengths through an accessor on the edge payload, e.g. `|edge: &RoadEdge| edge.weight`.
 
-// Take a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges; and an integer `k` specifying the number of landmarks to select. The function uses a random approach to select `k` landmarks from the nodes in the graph and returns them as a vector of `NodeIndex` values.
-pub fn select_landmarks(graph: &Graph<(f64, f64), f64, Undirected>, k: usize) -> Vec<NodeIndex> {
-    // Initialize a random number generator.
-    let mut rng = rand::thread_rng();
+// Take a graph reference, an integer `k` specifying the number of landmarks to select, and the random number generator to draw them with. The function uses a random approach to select `k` landmarks from the nodes in the graph and returns their identifiers; a seeded generator selects the same landmarks on every run.
+pub fn select_landmarks<G: IntoNodeIdentifiers, R: Rng + ?Sized>(graph: G, k: usize, rng: &mut R) -> Vec<G::NodeId> {
+    // Get the identifiers of all nodes in the graph and shuffle them randomly.
+    let mut landmarks: Vec<G::NodeId> = graph.node_identifiers().collect();
+    landmarks.shuffle(rng);
 
-    // Get the indices of all nodes in the graph and shuffle them randomly.
-    let mut landmarks: Vec<NodeIndex> = graph.node_indices().collect();
-    landmarks.shuffle(&mut rng);
-
-    // Truncate the shuffled indices to select the first `k` indices as the landmarks and return them as a vector.
+    // Truncate the shuffled identifiers to select the first `k` as the landmarks and return them as a vector.
     landmarks.truncate(k);
     landmarks
 }
 
-// Take a reference to a generic `Graph` with node properties of `(f64, f64)` type, edge weights of type `f64`, and undirected edges; and a slice of `NodeIndex` values representing the landmarks in the graph. The function computes the shortest distances from each landmark to every other node in the graph using Dijkstra's algorithm and returns the distances as a nested `HashMap`, where the outer key represents the landmark node and the inner key represents the target node, and the value is the shortest distance between them.
-pub fn precompute_landmark_distances(
-    graph: &Graph<(f64, f64), f64, Undirected>,
-    landmarks: &[NodeIndex],
-) -> HashMap<NodeIndex, HashMap<NodeIndex, f64>> {
-    // Initialize a new empty `HashMap` to store the distances from each landmark to every other node in the graph.
-    let mut landmark_distances: HashMap<NodeIndex, HashMap<NodeIndex, f64>> = HashMap::new();
+// Shortest distances between each landmark and every node it is connected to, keyed first by the landmark.
+// On an undirected graph the distance to a landmark equals the distance from it, so only `from_landmark` is
+// filled; on a directed graph one-way streets make them differ and `to_landmark` holds the reverse distances.
+#[derive(Debug)]
+pub struct LandmarkDistances<N> {
+    pub from_landmark: HashMap<N, HashMap<N, f64>>,
+    pub to_landmark: Option<HashMap<N, HashMap<N, f64>>>,
+}
+
+impl<N: Hash + Eq + Copy> LandmarkDistances<N> {
+    // Shortest distance from the given node to the landmark, if the landmark can be reached
+    fn to(&self, landmark: N, node: N) -> Option<f64> {
+        self.to_landmark.as_ref().unwrap_or(&self.from_landmark).get(&landmark)?.get(&node).copied()
+    }
+}
+
+// Take a graph reference, a slice of node identifiers representing the landmarks in the graph, and an accessor for the edge lengths. The function computes the shortest distances from each landmark to every other node in the graph using Dijkstra's algorithm and returns them in a `LandmarkDistances`, where the outer key represents the landmark node and the inner key represents the target node, and the value is the shortest distance between them. For directed graphs Dijkstra's algorithm is also run on the reversed graph to get the distances towards each landmark.
+pub fn precompute_landmark_distances<G, F>(
+    graph: G,
+    landmarks: &[G::NodeId],
+    edge_weight: F,
+) -> LandmarkDistances<G::NodeId>
+where
+    G: IntoEdgesDirected + Visitable + GraphProp,
+    G::NodeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    precompute_landmark_distances_with_progress(graph, landmarks, edge_weight, &NoProgress)
+}
+
+// The landmark distances as above, reporting every landmark whose distances are done to `progress`
+pub fn precompute_landmark_distances_with_progress<G, F>(
+    graph: G,
+    landmarks: &[G::NodeId],
+    edge_weight: F,
+    progress: &dyn Progress,
+) -> LandmarkDistances<G::NodeId>
+where
+    G: IntoEdgesDirected + Visitable + GraphProp,
+    G::NodeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
+    // Initialize a new empty `LandmarkDistances` to store the distances between each landmark and every other node in the graph.
+    let mut landmark_distances = LandmarkDistances {
+        from_landmark: HashMap::new(),
+        to_landmark: graph.is_directed().then(HashMap::new),
+    };
 
     // Iterate through each landmark in the input slice and compute the shortest distances from the landmark to every other node in the graph using Dijkstra's algorithm.
+    progress.start("Landmarks", Some(landmarks.len() as u64));
     for &landmark in landmarks {
-        let distances = dijkstra(graph, landmark, None, |e| *e.weight());
+        let distances = dijkstra(graph, landmark, None, |e| edge_weight(e.weight()));
 
         // Insert the distances from the landmark to every other node in the graph into the `landmark_distances` hashmap.
-        landmark_distances.insert(landmark, distances);
+        landmark_distances.from_landmark.insert(landmark, distances);
+
+        // Following the arcs backwards gives the distances from every node to the landmark.
+        if let Some(to_landmark) = landmark_distances.to_landmark.as_mut() {
+            to_landmark.insert(landmark, dijkstra(Reversed(graph), landmark, None, |e| edge_weight(e.weight())));
+        }
+        progress.advance(1);
     }
+    progress.finish();
 
-    // Return the `landmark_distances` hashmap.
+    // Return the `landmark_distances`.
     landmark_distances
 }
 
-// Take as input the starting node index `start_node`, the ending node index `end_node`, a reference to a nested `HashMap` of `NodeIndex` to `f64` values representing the precomputed distances from each landmark to every other node in the graph, and a constant `alpha` of type `f64`. The function returns an approximation of the shortest path distance between the start and end nodes using the landmark-based approach, where `alpha` is a tuning parameter that determines the trade-off between speed and accuracy of the approximation.
-pub fn approximate_shortest_path(
-    start_node: NodeIndex,
-    end_node: NodeIndex,
-    landmark_distances: &HashMap<NodeIndex, HashMap<NodeIndex, f64>>,
+// Take as input the starting node `start_node`, the ending node `end_node`, a reference to the `LandmarkDistances` precomputed from each landmark, and a constant `alpha` of type `f64`. The function returns an approximation of the shortest path distance between the start and end nodes using the landmark-based approach, where `alpha` is a tuning parameter that determines the trade-off between speed and accuracy of the approximation.
+pub fn approximate_shortest_path<N: Hash + Eq + Copy>(
+    start_node: N,
+    end_node: N,
+    landmark_distances: &LandmarkDistances<N>,
     alpha: f64,
 ) -> f64 {
     // Initialize a variable to store the minimum distance seen so far to infinity.
     let mut min_distance = f64::INFINITY;
 
-    // Iterate through each landmark in the precomputed distances hashmap and compute the total distance from the start node to the end node through the current landmark. If the total distance is less than or equal to the current minimum distance multiplied by the tuning parameter alpha, update the minimum distance.
-    for (_, landmark_dist) in landmark_distances.iter() {
+    // Iterate through each landmark in the precomputed distances and compute the total distance from the start node to the landmark and on to the end node. If the total distance is less than or equal to the current minimum distance multiplied by the tuning parameter alpha, update the minimum distance.
+    for (&landmark, landmark_dist) in landmark_distances.from_landmark.iter() {
         if let (Some(dist_start), Some(dist_end)) = (
-            landmark_dist.get(&start_node),
+            landmark_distances.to(landmark, start_node),
             landmark_dist.get(&end_node),
         ) {
             let distance = dist_start + dist_end;
@@ -76,41 +121,57 @@ pub fn approximate_shortest_path(
 }
 
 
-// Have three fields: a `f64` value, a `NodeIndex`, and a nested `HashMap` of `NodeIndex` values.
-#[derive(Debug, PartialEq)]
-struct QueueItem(f64, NodeIndex, HashMap<NodeIndex, NodeIndex>);
+// Have three fields: a `f64` value, a node, and a `HashMap` from each node on the path to the next one.
+#[derive(Debug)]
+struct QueueItem<N>(f64, N, HashMap<N, N>);
+
+// Implement the `PartialEq` trait for `QueueItem`, comparing the path weights only.
+impl<N> PartialEq for QueueItem<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
 
 // Implement the `Eq` trait for `QueueItem`.
-impl Eq for QueueItem {}
+impl<N> Eq for QueueItem<N> {}
 
 // Implement the `PartialOrd` trait for `QueueItem`.
-impl PartialOrd for QueueItem {
+impl<N> PartialOrd for QueueItem<N> {
     // Define a function called `partial_cmp` that takes a reference to another `QueueItem` struct and returns an `Option` of type `Ordering`.
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        // Call the `partial_cmp` method on the `f64` value of the `other` `QueueItem` and compare it to the `f64` value of `self`. This will return an `Option` of type `Ordering`.
-        other.0.partial_cmp(&self.0)
+        // Delegate to the `Ord` implementation so both orderings always agree.
+        Some(self.cmp(other))
     }
 }
 
 // Implement the `Ord` trait for `QueueItem`.
-impl Ord for QueueItem {
+impl<N> Ord for QueueItem<N> {
     // Define a function called `cmp` that takes another `QueueItem` struct and returns an `Ordering`.
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // Call the `partial_cmp` method defined in the `PartialOrd` implementation and use `unwrap_or` to return `Equal` if the result is `None`.
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+        // Compare the `f64` value of `other` to the `f64` value of `self` so the `BinaryHeap` behaves as a min-heap, and use `unwrap_or` to return `Equal` if the values are not comparable.
+        other.0.partial_cmp(&self.0).unwrap_or(std::cmp::Ordering::Equal)
     }
 }
 
 
-// Finds the shortest paths between two nodes in a graph using landmarks
-// Returns a vector of tuples containing the weight of each path and a map of the nodes in the path and their predecessors
-pub fn find_shortest_paths(
-    graph: &Graph<(f64, f64), f64, Undirected>,
-    start_node: NodeIndex,
-    end_node: NodeIndex,
-    landmark_distances: &HashMap<NodeIndex, HashMap<NodeIndex, f64>>,
+// The weight of a path and a map from each node on it to the next one
+pub type WeightedPath<N> = (f64, HashMap<N, N>);
+
+// Finds the shortest paths between two nodes in a graph using landmarks, reading edge lengths through `edge_weight`
+// Returns a vector of tuples containing the weight of each path and a map of the nodes in the path and their successors
+pub fn find_shortest_paths<G, F>(
+    graph: G,
+    start_node: G::NodeId,
+    end_node: G::NodeId,
+    landmark_distances: &LandmarkDistances<G::NodeId>,
     num_paths: usize,
-) -> Vec<(f64, HashMap<NodeIndex, NodeIndex>)> {
+    edge_weight: F,
+) -> Vec<WeightedPath<G::NodeId>>
+where
+    G: IntoEdges,
+    G::NodeId: Hash + Eq,
+    F: Fn(&G::EdgeWeight) -> f64,
+{
     // Set the value of alpha, which controls the accuracy of the algorithm
     let alpha = 3.0; // Adjust this value based on your needs
 
@@ -145,12 +206,12 @@ pub fn find_shortest_paths(
         // Add the current node to the visited set
         visited.insert(current_node);
 
-        // For each outgoing edge from the current node
+        // For each outgoing edge from the current node (on a directed graph, only the arcs leaving it)
         for edge in graph.edges(current_node) {
             let neighbor = edge.target();
             if !visited.contains(&neighbor) {
                 // Calculate the weight of the new path and the approximate distance from the neighbor to the end node
-                let new_weight = weight + *edge.weight();
+                let new_weight = weight + edge_weight(edge.weight());
                 let approx_neighbor_distance =
                     approximate_shortest_path(neighbor, end_node, landmark_distances, alpha);
 
@@ -173,21 +234,34 @@ pub fn find_shortest_paths(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use petgraph::graph::EdgeIndex;
-
-    fn create_test_graph() -> Graph<(f64, f64), f64, Undirected> {
-        let mut graph = Graph::<(f64, f64), f64, Undirected>::new_undirected();
-        let a = graph.add_node((0.0, 0.0));
-        let b = graph.add_node((1.0, 0.0));
-        let c = graph.add_node((1.0, 1.0));
-        let d = graph.add_node((0.0, 1.0));
-
-        graph.add_edge(a, b, 1.0);
-        graph.add_edge(b, c, 1.0);
-        graph.add_edge(c, d, 1.0);
-        graph.add_edge(d, a, 1.0);
-        graph.add_edge(a, c, 1.41);
-        graph.add_edge(b, d, 1.41);
+    use crate::data::{RoadEdge, RoadGraph, RoadNode};
+    use petgraph::graph::NodeIndex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Every test draws from the same seed, so a failure reproduces
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(42)
+    }
+
+    // The edge length accessor for road graphs
+    fn road_weight(edge: &RoadEdge) -> f64 {
+        edge.weight
+    }
+
+    fn create_test_graph() -> RoadGraph {
+        let mut graph = RoadGraph::new_undirected();
+        let a = graph.add_node(RoadNode { id: 0, position: Some((0.0, 0.0)) });
+        let b = graph.add_node(RoadNode { id: 1, position: Some((1.0, 0.0)) });
+        let c = graph.add_node(RoadNode { id: 2, position: Some((1.0, 1.0)) });
+        let d = graph.add_node(RoadNode { id: 3, position: Some((0.0, 1.0)) });
+
+        graph.add_edge(a, b, RoadEdge::from_weight(1.0));
+        graph.add_edge(b, c, RoadEdge::from_weight(1.0));
+        graph.add_edge(c, d, RoadEdge::from_weight(1.0));
+        graph.add_edge(d, a, RoadEdge::from_weight(1.0));
+        graph.add_edge(a, c, RoadEdge::from_weight(1.41));
+        graph.add_edge(b, d, RoadEdge::from_weight(1.41));
 
         graph
     }
@@ -195,25 +269,40 @@ mod tests {
     #[test]
     fn test_select_landmarks() {
         let graph = create_test_graph();
-        let landmarks = select_landmarks(&graph, 2);
+        let landmarks = select_landmarks(&graph, 2, &mut rng());
 
         assert_eq!(landmarks.len(), 2);
+        assert_ne!(landmarks[0], landmarks[1]);
+        // The same seed selects the same landmarks
+        assert_eq!(select_landmarks(&graph, 2, &mut rng()), landmarks);
     }
 
     #[test]
     fn test_precompute_landmark_distances() {
         let graph = create_test_graph();
-        let landmarks = select_landmarks(&graph, 2);
-        let landmark_distances = precompute_landmark_distances(&graph, &landmarks);
+        let landmarks = select_landmarks(&graph, 2, &mut rng());
+        let landmark_distances = precompute_landmark_distances(&graph, &landmarks, road_weight);
 
-        assert_eq!(landmark_distances.len(), 2);
+        assert_eq!(landmark_distances.from_landmark.len(), 2);
+        assert!(landmark_distances.to_landmark.is_none());
+    }
+
+    #[test]
+    fn test_precompute_landmark_distances_reports_landmarks() {
+        let graph = create_test_graph();
+        let landmarks = select_landmarks(&graph, 3, &mut rng());
+        let progress = crate::progress::tests::CountingProgress::default();
+        precompute_landmark_distances_with_progress(&graph, &landmarks, road_weight, &progress);
+
+        assert_eq!(progress.tasks(), vec![("Landmarks".to_string(), Some(3))]);
+        assert_eq!(progress.steps(), 3);
     }
 
     #[test]
     fn test_approximate_shortest_path() {
         let graph = create_test_graph();
-        let landmarks = select_landmarks(&graph, 2);
-        let landmark_distances = precompute_landmark_distances(&graph, &landmarks);
+        let landmarks = select_landmarks(&graph, 2, &mut rng());
+        let landmark_distances = precompute_landmark_distances(&graph, &landmarks, road_weight);
 
         let start_node = NodeIndex::new(0);
         let end_node = NodeIndex::new(2);
@@ -227,19 +316,69 @@ mod tests {
     #[test]
     fn test_find_shortest_paths() {
         let graph = create_test_graph();
-        let landmarks = select_landmarks(&graph, 2);
-        let landmark_distances = precompute_landmark_distances(&graph, &landmarks);
+        let landmarks = select_landmarks(&graph, 2, &mut rng());
+        let landmark_distances = precompute_landmark_distances(&graph, &landmarks, road_weight);
 
         let start_node = NodeIndex::new(0);
         let end_node = NodeIndex::new(2);
         let num_paths = 1;
 
-        let shortest_paths = find_shortest_paths(&graph, start_node, end_node, &landmark_distances, num_paths);
+        let shortest_paths = find_shortest_paths(&graph, start_node, end_node, &landmark_distances, num_paths, road_weight);
 
         assert_eq!(shortest_paths.len(), num_paths);
         assert_eq!(shortest_paths[0].0, 1.41);
     }
-}
 
+    // A one-way ring a -> b -> c -> d -> a with a two-way shortcut between a and c
+    fn create_directed_test_graph() -> RoadGraph<petgraph::Directed> {
+        let mut graph = RoadGraph::<petgraph::Directed>::default();
+        let a = graph.add_node(RoadNode { id: 0, position: Some((0.0, 0.0)) });
+        let b = graph.add_node(RoadNode { id: 1, position: Some((1.0, 0.0)) });
+        let c = graph.add_node(RoadNode { id: 2, position: Some((1.0, 1.0)) });
+        let d = graph.add_node(RoadNode { id: 3, position: Some((0.0, 1.0)) });
+
+        graph.add_edge(a, b, RoadEdge::from_weight(1.0));
+        graph.add_edge(b, c, RoadEdge::from_weight(1.0));
+        graph.add_edge(c, d, RoadEdge::from_weight(1.0));
+        graph.add_edge(d, a, RoadEdge::from_weight(1.0));
+        graph.add_edge(a, c, RoadEdge::from_weight(5.0));
+        graph.add_edge(c, a, RoadEdge::from_weight(5.0));
+
+        graph
+    }
+
+    #[test]
+    fn test_precompute_landmark_distances_directed() {
+        let graph = create_directed_test_graph();
+        let b = NodeIndex::new(1);
+        let landmark_distances = precompute_landmark_distances(&graph, &[b], road_weight);
+
+        // Leaving b the ring must be followed forwards, and reaching b from c means going round through d and a
+        assert_eq!(landmark_distances.from_landmark[&b][&NodeIndex::new(0)], 3.0);
+        assert_eq!(landmark_distances.to(b, NodeIndex::new(0)), Some(1.0));
+        assert_eq!(landmark_distances.to(b, NodeIndex::new(2)), Some(3.0));
+    }
 
+    #[test]
+    fn test_find_shortest_paths_directed() {
+        let graph = create_directed_test_graph();
+        let landmarks: Vec<NodeIndex> = graph.node_indices().collect();
+        let landmark_distances = precompute_landmark_distances(&graph, &landmarks, road_weight);
+
+        // Against the one-way ring, d can only be reached from a by going around it
+        let shortest_paths = find_shortest_paths(&graph, NodeIndex::new(0), NodeIndex::new(3), &landmark_distances, 1, road_weight);
+        assert_eq!(shortest_paths.len(), 1);
+        assert_eq!(shortest_paths[0].0, 3.0);
+    }
 
+    #[test]
+    fn test_find_shortest_paths_graph_map() {
+        // On a GraphMap with plain f64 weights the paths are given in the original ids
+        let graph = petgraph::graphmap::DiGraphMap::<u32, f64>::from_edges([(10, 20, 1.0), (20, 30, 1.0), (10, 30, 5.0)]);
+        let landmark_distances = precompute_landmark_distances(&graph, &[10, 30], |weight: &f64| *weight);
+
+        let shortest_paths = find_shortest_paths(&graph, 10, 30, &landmark_distances, 1, |weight: &f64| *weight);
+        assert_eq!(shortest_paths[0].0, 2.0);
+        assert_eq!(shortest_paths[0].1[&10], 20);
+    }
+}
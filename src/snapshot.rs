@@ -0,0 +1,476 @@
+// snapshot.rs
+
+use crate::data::{HighwayClass, InputFormat, NodeCoordinates, RoadEdge, RoadNetwork};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::time::UNIX_EPOCH;
+
+// Every snapshot starts with these bytes
+const MAGIC: &[u8; 8] = b"CRNSNAP\0";
+
+// Bump whenever the layout below changes; snapshots written with another version are rejected
+pub const FORMAT_VERSION: u32 = 2;
+
+// Header flag set when the snapshot carries node coordinates
+const FLAG_COORDINATES: u32 = 1;
+// Header flag set when the snapshot records the fingerprint of the file it was built from
+const FLAG_SOURCE: u32 = 1 << 1;
+
+// Sentinel for a missing lane count; missing lengths, travel times and coordinates are stored as NaN
+const NO_LANES: u32 = u32::MAX;
+
+// Highway classes in the order of their on-disk codes; code 0 means no class
+const HIGHWAY_CLASSES: [HighwayClass; 9] = [
+    HighwayClass::Motorway,
+    HighwayClass::Trunk,
+    HighwayClass::Primary,
+    HighwayClass::Secondary,
+    HighwayClass::Tertiary,
+    HighwayClass::Unclassified,
+    HighwayClass::Residential,
+    HighwayClass::LivingStreet,
+    HighwayClass::Service,
+];
+
+// Input formats in the order of their on-disk codes
+const INPUT_FORMATS: [InputFormat; 5] =
+    [InputFormat::EdgeList, InputFormat::Dimacs, InputFormat::OsmXml, InputFormat::OsmPbf, InputFormat::Snapshot];
+
+// Layout (all integers and floats little-endian):
+//
+//   magic            8 bytes   "CRNSNAP\0"
+//   version          u32
+//   flags            u32       FLAG_COORDINATES | FLAG_SOURCE
+//   source           2 x u64   length and modification time of the source file (only with FLAG_SOURCE)
+//   source_format    u32       code of the format the source file was read as (only with FLAG_SOURCE)
+//   node_count       u64       n
+//   edge_count       u64       m
+//   node_ids         n x u64   original node ids, ascending; position in this array is the node index
+//   offsets          (n+1) x u64  CSR row offsets into the adjacency arrays
+//   targets          offsets[n] x u64  neighbor node index of each adjacency entry
+//   edge_ids         offsets[n] x u64  edge index of each adjacency entry
+//   weights          m x f64
+//   lengths          m x f64
+//   travel_times     m x f64
+//   lanes            m x u32
+//   highway          m x u8
+//   coordinates      n x (f64 lon, f64 lat)   (only with FLAG_COORDINATES)
+//   checksum         u32       CRC-32 of every byte after the magic and before the checksum
+
+// Errors that can occur while reading or writing a snapshot
+#[derive(Debug)]
+pub enum SnapshotError {
+    // The snapshot could not be read or written
+    Io(io::Error),
+    // The file does not start with the snapshot magic bytes
+    BadMagic,
+    // The snapshot was written by a different version of the format
+    UnsupportedVersion { found: u32, expected: u32 },
+    // The stored checksum does not match the contents
+    ChecksumMismatch { stored: u32, computed: u32 },
+    // The file ends before all declared sections were read
+    Truncated,
+    // The sections are internally inconsistent, e.g. an adjacency entry points past the node array
+    Corrupt(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "I/O error: {}", err),
+            SnapshotError::BadMagic => write!(f, "not a road network snapshot"),
+            SnapshotError::UnsupportedVersion { found, expected } => {
+                write!(f, "snapshot format version {} is not supported (expected {})", found, expected)
+            }
+            SnapshotError::ChecksumMismatch { stored, computed } => {
+                write!(f, "snapshot checksum mismatch (stored {:08x}, computed {:08x})", stored, computed)
+            }
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::Corrupt(message) => write!(f, "snapshot is corrupt: {}", message),
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+// Size and modification time of the file a snapshot was built from, and the format it was read as, used to detect
+// stale snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceFingerprint {
+    pub len: u64,
+    pub modified_secs: u64,
+    pub format: InputFormat,
+}
+
+impl SourceFingerprint {
+    // Read the fingerprint of a file read as `format` from its metadata
+    pub fn of(file_path: &str, format: InputFormat) -> io::Result<Self> {
+        let metadata = fs::metadata(file_path)?;
+        let modified_secs = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Ok(SourceFingerprint { len: metadata.len(), modified_secs, format })
+    }
+}
+
+// The contents of a snapshot
+#[derive(Debug)]
+pub struct Snapshot {
+    pub network: RoadNetwork,
+    pub coordinates: NodeCoordinates,
+    // Fingerprint of the file the snapshot was built from, if it was recorded
+    pub source: Option<SourceFingerprint>,
+}
+
+// Function to write a road network, its coordinates and optionally the fingerprint of its source file as a snapshot
+pub fn write_snapshot(
+    graph: &RoadNetwork,
+    coordinates: &NodeCoordinates,
+    source: Option<SourceFingerprint>,
+    file_path: &str,
+) -> Result<(), SnapshotError> {
+    // Number the nodes in ascending id order
+    let mut node_ids: Vec<usize> = graph.nodes().collect();
+    node_ids.sort_unstable();
+    let index_of: HashMap<usize, usize> = node_ids.iter().enumerate().map(|(index, &node)| (node, index)).collect();
+
+    // Collect the edges and the adjacency of every node as (neighbor index, edge index) pairs
+    let edges: Vec<(usize, usize, &RoadEdge)> = graph.all_edges().collect();
+    let mut adjacency: Vec<Vec<(usize, usize)>> = vec![Vec::new(); node_ids.len()];
+    for (edge_index, (a, b, _)) in edges.iter().enumerate() {
+        let (a, b) = (index_of[a], index_of[b]);
+        adjacency[a].push((b, edge_index));
+        if a != b {
+            adjacency[b].push((a, edge_index));
+        }
+    }
+
+    let has_coordinates = !coordinates.is_empty();
+    let mut flags = 0;
+    if has_coordinates {
+        flags |= FLAG_COORDINATES;
+    }
+    if source.is_some() {
+        flags |= FLAG_SOURCE;
+    }
+
+    let mut buffer: Vec<u8> = Vec::new();
+    buffer.extend_from_slice(MAGIC);
+    buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    buffer.extend_from_slice(&flags.to_le_bytes());
+    if let Some(source) = source {
+        buffer.extend_from_slice(&source.len.to_le_bytes());
+        buffer.extend_from_slice(&source.modified_secs.to_le_bytes());
+        let format_code = INPUT_FORMATS.iter().position(|&format| format == source.format).unwrap() as u32;
+        buffer.extend_from_slice(&format_code.to_le_bytes());
+    }
+    buffer.extend_from_slice(&(node_ids.len() as u64).to_le_bytes());
+    buffer.extend_from_slice(&(edges.len() as u64).to_le_bytes());
+
+    for &node in &node_ids {
+        buffer.extend_from_slice(&(node as u64).to_le_bytes());
+    }
+
+    let mut offset = 0u64;
+    buffer.extend_from_slice(&offset.to_le_bytes());
+    for neighbors in &adjacency {
+        offset += neighbors.len() as u64;
+        buffer.extend_from_slice(&offset.to_le_bytes());
+    }
+    for &(target, _) in adjacency.iter().flatten() {
+        buffer.extend_from_slice(&(target as u64).to_le_bytes());
+    }
+    for &(_, edge_index) in adjacency.iter().flatten() {
+        buffer.extend_from_slice(&(edge_index as u64).to_le_bytes());
+    }
+
+    for (_, _, edge) in &edges {
+        buffer.extend_from_slice(&edge.weight.to_le_bytes());
+    }
+    for (_, _, edge) in &edges {
+        buffer.extend_from_slice(&edge.length.unwrap_or(f64::NAN).to_le_bytes());
+    }
+    for (_, _, edge) in &edges {
+        buffer.extend_from_slice(&edge.travel_time.unwrap_or(f64::NAN).to_le_bytes());
+    }
+    for (_, _, edge) in &edges {
+        buffer.extend_from_slice(&edge.lanes.unwrap_or(NO_LANES).to_le_bytes());
+    }
+    for (_, _, edge) in &edges {
+        let code = edge
+            .highway
+            .and_then(|class| HIGHWAY_CLASSES.iter().position(|candidate| *candidate == class))
+            .map_or(0, |position| position as u8 + 1);
+        buffer.push(code);
+    }
+
+    if has_coordinates {
+        for node in &node_ids {
+            let (lon, lat) = coordinates.get(node).copied().unwrap_or((f64::NAN, f64::NAN));
+            buffer.extend_from_slice(&lon.to_le_bytes());
+            buffer.extend_from_slice(&lat.to_le_bytes());
+        }
+    }
+
+    let checksum = crc32fast::hash(&buffer[MAGIC.len()..]);
+    buffer.extend_from_slice(&checksum.to_le_bytes());
+
+    let mut file = File::create(file_path)?;
+    file.write_all(&buffer)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+// Sequential little-endian reader over the snapshot bytes
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        let end = self.position.checked_add(len).ok_or(SnapshotError::Truncated)?;
+        let slice = self.bytes.get(self.position..end).ok_or(SnapshotError::Truncated)?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64, SnapshotError> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    // Read a count and make sure the remaining bytes could possibly hold that many entries of the given size
+    fn count(&mut self, entry_size: usize) -> Result<usize, SnapshotError> {
+        let count = usize::try_from(self.u64()?).map_err(|_| SnapshotError::Truncated)?;
+        if count.saturating_mul(entry_size) > self.bytes.len() - self.position {
+            return Err(SnapshotError::Truncated);
+        }
+        Ok(count)
+    }
+
+    fn vec<T>(&mut self, len: usize, mut read: impl FnMut(&mut Self) -> Result<T, SnapshotError>) -> Result<Vec<T>, SnapshotError> {
+        (0..len).map(|_| read(self)).collect()
+    }
+}
+
+// Function to bulk-load a snapshot, verifying its magic bytes, format version and checksum before decoding it
+pub fn read_snapshot(file_path: &str) -> Result<Snapshot, SnapshotError> {
+    let bytes = fs::read(file_path)?;
+
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+    let mut cursor = Cursor { bytes: &bytes, position: MAGIC.len() };
+    let version = cursor.u32()?;
+    if version != FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion { found: version, expected: FORMAT_VERSION });
+    }
+
+    // Verify the checksum before trusting any of the declared sizes
+    if bytes.len() < MAGIC.len() + 4 + 4 {
+        return Err(SnapshotError::Truncated);
+    }
+    let (body, stored) = bytes.split_at(bytes.len() - 4);
+    let stored = u32::from_le_bytes(stored.try_into().unwrap());
+    let computed = crc32fast::hash(&body[MAGIC.len()..]);
+    if stored != computed {
+        return Err(SnapshotError::ChecksumMismatch { stored, computed });
+    }
+    let mut cursor = Cursor { bytes: body, position: cursor.position };
+
+    let flags = cursor.u32()?;
+    let source = if flags & FLAG_SOURCE != 0 {
+        let (len, modified_secs) = (cursor.u64()?, cursor.u64()?);
+        let format_code = cursor.u32()?;
+        let format = *INPUT_FORMATS
+            .get(format_code as usize)
+            .ok_or_else(|| SnapshotError::Corrupt(format!("unknown input format code {}", format_code)))?;
+        Some(SourceFingerprint { len, modified_secs, format })
+    } else {
+        None
+    };
+
+    let node_count = cursor.count(8)?;
+    let edge_count = cursor.count(8)?;
+    let node_ids = cursor.vec(node_count, |c| c.u64().map(|id| id as usize))?;
+    let offsets = cursor.vec(node_count + 1, |c| c.u64().map(|offset| offset as usize))?;
+    let entries = offsets[node_count];
+    if offsets[0] != 0 || offsets.windows(2).any(|pair| pair[0] > pair[1]) || entries > edge_count * 2 {
+        return Err(SnapshotError::Corrupt("adjacency offsets are not monotonic".to_string()));
+    }
+    let targets = cursor.vec(entries, |c| c.u64().map(|target| target as usize))?;
+    let edge_ids = cursor.vec(entries, |c| c.u64().map(|edge| edge as usize))?;
+
+    let weights = cursor.vec(edge_count, Cursor::f64)?;
+    let lengths = cursor.vec(edge_count, Cursor::f64)?;
+    let travel_times = cursor.vec(edge_count, Cursor::f64)?;
+    let lanes = cursor.vec(edge_count, Cursor::u32)?;
+    let highway_codes = cursor.vec(edge_count, Cursor::u8)?;
+
+    let mut coordinates = NodeCoordinates::new();
+    if flags & FLAG_COORDINATES != 0 {
+        for &node in &node_ids {
+            let position = (cursor.f64()?, cursor.f64()?);
+            if !position.0.is_nan() && !position.1.is_nan() {
+                coordinates.insert(node, position);
+            }
+        }
+    }
+    if cursor.position != body.len() {
+        return Err(SnapshotError::Corrupt("unexpected trailing bytes".to_string()));
+    }
+
+    // Rebuild the network, adding each edge the first time one of its endpoints lists it
+    let mut network = RoadNetwork::with_capacity(node_count, edge_count);
+    for &node in &node_ids {
+        network.add_node(node);
+    }
+    let mut added = vec![false; edge_count];
+    for node_index in 0..node_count {
+        for entry in offsets[node_index]..offsets[node_index + 1] {
+            let (target, edge_index) = (targets[entry], edge_ids[entry]);
+            if target >= node_count || edge_index >= edge_count {
+                return Err(SnapshotError::Corrupt(format!("adjacency entry {} is out of range", entry)));
+            }
+            if added[edge_index] {
+                continue;
+            }
+            added[edge_index] = true;
+
+            let highway = match highway_codes[edge_index] {
+                0 => None,
+                code => Some(*HIGHWAY_CLASSES.get(code as usize - 1).ok_or_else(|| {
+                    SnapshotError::Corrupt(format!("unknown highway class code {}", code))
+                })?),
+            };
+            let edge = RoadEdge {
+                weight: weights[edge_index],
+                length: Some(lengths[edge_index]).filter(|value| !value.is_nan()),
+                travel_time: Some(travel_times[edge_index]).filter(|value| !value.is_nan()),
+                lanes: Some(lanes[edge_index]).filter(|value| *value != NO_LANES),
+                highway,
+            };
+            network.add_edge(node_ids[node_index], node_ids[target], edge);
+        }
+    }
+
+    Ok(Snapshot { network, coordinates, source })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm::import_osm;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("crnsa_{}_{}.snap", name, std::process::id()));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let import = import_osm("test_data/small_network.osm").unwrap();
+        let source = SourceFingerprint::of("test_data/small_network.osm", InputFormat::OsmXml).unwrap();
+        let path = temp_path("round_trip");
+
+        write_snapshot(&import.network, &import.coordinates, Some(source), &path).unwrap();
+        let snapshot = read_snapshot(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot.source, Some(source));
+        assert_eq!(snapshot.coordinates, import.coordinates);
+        assert_eq!(snapshot.network.node_count(), import.network.node_count());
+        assert_eq!(snapshot.network.edge_count(), import.network.edge_count());
+        for (a, b, edge) in import.network.all_edges() {
+            assert_eq!(snapshot.network.edge_weight(a, b), Some(edge));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_records_source_format() {
+        let graph = RoadNetwork::from_edges([(0, 1, RoadEdge::default())]);
+        let source = SourceFingerprint::of("test_data/test_graph.txt", InputFormat::EdgeList).unwrap();
+        let path = temp_path("source_format");
+
+        write_snapshot(&graph, &NodeCoordinates::new(), Some(source), &path).unwrap();
+        let snapshot = read_snapshot(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The same file read in another format does not match the cached fingerprint
+        assert_eq!(snapshot.source.map(|source| source.format), Some(InputFormat::EdgeList));
+        let as_dimacs = SourceFingerprint::of("test_data/test_graph.txt", InputFormat::Dimacs).unwrap();
+        assert_ne!(snapshot.source, Some(as_dimacs));
+    }
+
+    #[test]
+    fn test_snapshot_rejects_corruption() {
+        let mut graph = RoadNetwork::new();
+        graph.add_edge(0, 1, RoadEdge::default());
+        graph.add_edge(1, 2, RoadEdge::from_weight(2.5));
+        let path = temp_path("corrupt");
+        write_snapshot(&graph, &NodeCoordinates::new(), None, &path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+        let result = read_snapshot(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(SnapshotError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_snapshot_rejects_other_versions() {
+        let graph = RoadNetwork::new();
+        let path = temp_path("version");
+        write_snapshot(&graph, &NodeCoordinates::new(), None, &path).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let result = read_snapshot(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(SnapshotError::UnsupportedVersion { found, .. }) if found == FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn test_snapshot_rejects_other_files() {
+        let result = read_snapshot("test_data/test_graph.txt");
+        assert!(matches!(result, Err(SnapshotError::BadMagic)));
+    }
+}
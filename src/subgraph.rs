@@ -0,0 +1,241 @@
+// subgraph.rs
+
+use crate::data::{build_road_graph, CoordinateReport, NodeCoordinates, RoadGraph, RoadNetworkOf};
+use petgraph::unionfind::UnionFind;
+use petgraph::{EdgeType, Undirected};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// A part of a road network whose nodes are renumbered 0..n in the order they appear in the source network,
+// together with the mapping back to the original node ids
+#[derive(Debug, Clone)]
+pub struct Subgraph<Ty: EdgeType = Undirected> {
+    pub network: RoadNetworkOf<Ty>,
+    // Coordinates of the subgraph nodes, keyed by their new ids
+    pub coordinates: NodeCoordinates,
+    // Original id of every node, indexed by its new id
+    pub original_ids: Vec<usize>,
+    new_ids: HashMap<usize, usize>,
+}
+
+impl<Ty: EdgeType> Subgraph<Ty> {
+    // Original id of the node with the given new id
+    pub fn original_id(&self, node: usize) -> usize {
+        self.original_ids[node]
+    }
+
+    // New id of the node with the given original id, if it is part of the subgraph
+    pub fn new_id(&self, original: usize) -> Option<usize> {
+        self.new_ids.get(&original).copied()
+    }
+
+    // Convert the subgraph into the road graph the analyses run on. The renumbering only serves the extraction:
+    // the nodes of the road graph carry their original ids again, and so does the coordinate report.
+    pub fn into_road_graph(self) -> (RoadGraph<Ty>, CoordinateReport) {
+        let (mut graph, mut report) = build_road_graph(self.network, &self.coordinates);
+        for node in graph.node_weights_mut() {
+            node.id = self.original_ids[node.id];
+        }
+        for node in report.missing_nodes.iter_mut() {
+            *node = self.original_ids[*node];
+        }
+        report.missing_nodes.sort_unstable();
+        (graph, report)
+    }
+}
+
+// Axis-aligned region in (longitude, latitude) degrees, bounds included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, (lon, lat): (f64, f64)) -> bool {
+        (self.min_lon..=self.max_lon).contains(&lon) && (self.min_lat..=self.max_lat).contains(&lat)
+    }
+}
+
+// Function to induce the subgraph on the given nodes: every edge of the network between two of them is kept.
+// Nodes that are not part of the network are ignored.
+pub fn induce_subgraph<Ty: EdgeType>(
+    graph: &RoadNetworkOf<Ty>,
+    coordinates: &NodeCoordinates,
+    nodes: impl IntoIterator<Item = usize>,
+) -> Subgraph<Ty> {
+    let selected: HashSet<usize> = nodes.into_iter().collect();
+
+    // Number the nodes in network order, so the result does not depend on the order of the selection
+    let original_ids: Vec<usize> = graph.nodes().filter(|node| selected.contains(node)).collect();
+    let new_ids: HashMap<usize, usize> = original_ids.iter().enumerate().map(|(new, &original)| (original, new)).collect();
+
+    let mut network = RoadNetworkOf::with_capacity(original_ids.len(), 0);
+    for new in 0..original_ids.len() {
+        network.add_node(new);
+    }
+    for (a, b, edge) in graph.all_edges() {
+        if let (Some(&new_a), Some(&new_b)) = (new_ids.get(&a), new_ids.get(&b)) {
+            network.add_edge(new_a, new_b, *edge);
+        }
+    }
+
+    let coordinates = original_ids
+        .iter()
+        .enumerate()
+        .filter_map(|(new, original)| coordinates.get(original).map(|&position| (new, position)))
+        .collect();
+
+    Subgraph { network, coordinates, original_ids, new_ids }
+}
+
+// Function to find the weakly connected components of the network, largest first. Components of equal size
+// keep the order in which their first node appears in the network.
+pub fn weak_components<Ty: EdgeType>(graph: &RoadNetworkOf<Ty>) -> Vec<Vec<usize>> {
+    let position: HashMap<usize, usize> = graph.nodes().enumerate().map(|(index, node)| (node, index)).collect();
+    let mut union_find = UnionFind::new(position.len());
+    for (a, b, _) in graph.all_edges() {
+        union_find.union(position[&a], position[&b]);
+    }
+
+    let mut component_of_root: HashMap<usize, usize> = HashMap::new();
+    let mut components: Vec<Vec<usize>> = Vec::new();
+    for (index, node) in graph.nodes().enumerate() {
+        let root = union_find.find_mut(index);
+        let component = *component_of_root.entry(root).or_insert_with(|| {
+            components.push(Vec::new());
+            components.len() - 1
+        });
+        components[component].push(node);
+    }
+
+    components.sort_by_key(|nodes| std::cmp::Reverse(nodes.len()));
+    components
+}
+
+// Function to extract the largest weakly connected component, the part of the network most analyses are run on
+pub fn largest_component<Ty: EdgeType>(graph: &RoadNetworkOf<Ty>, coordinates: &NodeCoordinates) -> Subgraph<Ty> {
+    let largest = weak_components(graph).into_iter().next().unwrap_or_default();
+    induce_subgraph(graph, coordinates, largest)
+}
+
+// Function to extract the nodes whose coordinates fall inside the bounding box; nodes without coordinates are left out
+pub fn bounding_box_subgraph<Ty: EdgeType>(
+    graph: &RoadNetworkOf<Ty>,
+    coordinates: &NodeCoordinates,
+    bounds: &BoundingBox,
+) -> Subgraph<Ty> {
+    let inside = graph
+        .nodes()
+        .filter(|node| coordinates.get(node).is_some_and(|&position| bounds.contains(position)));
+    induce_subgraph(graph, coordinates, inside)
+}
+
+// Function to extract the nodes within the given number of hops of the center node, found by a breadth-first
+// search. Arc directions are ignored, so the region around a node on a one-way street is not cut in half.
+// Returns None if the center is not part of the network.
+pub fn bfs_subgraph<Ty: EdgeType>(
+    graph: &RoadNetworkOf<Ty>,
+    coordinates: &NodeCoordinates,
+    center: usize,
+    radius: usize,
+) -> Option<Subgraph<Ty>> {
+    if !graph.contains_node(center) {
+        return None;
+    }
+
+    let mut visited: HashSet<usize> = HashSet::from([center]);
+    let mut queue = VecDeque::from([(center, 0)]);
+    while let Some((node, hops)) = queue.pop_front() {
+        if hops == radius {
+            continue;
+        }
+        let neighbors = graph
+            .neighbors_directed(node, petgraph::Outgoing)
+            .chain(graph.neighbors_directed(node, petgraph::Incoming));
+        for neighbor in neighbors {
+            if visited.insert(neighbor) {
+                queue.push_back((neighbor, hops + 1));
+            }
+        }
+    }
+
+    Some(induce_subgraph(graph, coordinates, visited))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DirectedRoadNetwork, RoadEdge, RoadNetwork};
+
+    // A path 10 - 20 - 30 - 40 laid out along the equator, and a separate edge 50 - 60 further east
+    fn create_test_network() -> (RoadNetwork, NodeCoordinates) {
+        let mut graph = RoadNetwork::new();
+        let mut coordinates = NodeCoordinates::new();
+        for (a, b) in [(10, 20), (20, 30), (30, 40), (50, 60)] {
+            graph.add_edge(a, b, RoadEdge::from_weight(a as f64));
+        }
+        for (index, node) in [10, 20, 30, 40, 50, 60].into_iter().enumerate() {
+            coordinates.insert(node, (index as f64, 0.0));
+        }
+        (graph, coordinates)
+    }
+
+    #[test]
+    fn test_induce_subgraph_reindexes_nodes() {
+        let (graph, coordinates) = create_test_network();
+        let subgraph = induce_subgraph(&graph, &coordinates, [30, 20, 60, 99]);
+
+        assert_eq!(subgraph.original_ids, vec![20, 30, 60]);
+        assert_eq!(subgraph.network.node_count(), 3);
+        assert_eq!(subgraph.network.edge_count(), 1);
+        assert_eq!(subgraph.network.edge_weight(0, 1).unwrap().weight, 20.0);
+        assert_eq!(subgraph.new_id(30), Some(1));
+        assert_eq!(subgraph.new_id(10), None);
+        assert_eq!(subgraph.original_id(2), 60);
+        assert_eq!(subgraph.coordinates[&2], (5.0, 0.0));
+    }
+
+    #[test]
+    fn test_largest_component() {
+        let (graph, coordinates) = create_test_network();
+        assert_eq!(weak_components(&graph), vec![vec![10, 20, 30, 40], vec![50, 60]]);
+
+        let subgraph = largest_component(&graph, &coordinates);
+        assert_eq!(subgraph.original_ids, vec![10, 20, 30, 40]);
+        assert_eq!(subgraph.network.edge_count(), 3);
+
+        // The road graph built from the component reports the original ids again
+        let (road_graph, report) = subgraph.into_road_graph();
+        let ids: Vec<usize> = road_graph.node_weights().map(|node| node.id).collect();
+        assert_eq!(ids, vec![10, 20, 30, 40]);
+        assert_eq!(road_graph[petgraph::graph::NodeIndex::new(2)].position, Some((2.0, 0.0)));
+        assert!(report.missing_nodes.is_empty());
+    }
+
+    #[test]
+    fn test_bounding_box_subgraph() {
+        let (graph, coordinates) = create_test_network();
+        let bounds = BoundingBox { min_lon: 0.5, min_lat: -1.0, max_lon: 4.0, max_lat: 1.0 };
+        let subgraph = bounding_box_subgraph(&graph, &coordinates, &bounds);
+
+        assert_eq!(subgraph.original_ids, vec![20, 30, 40, 50]);
+        assert_eq!(subgraph.network.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_bfs_subgraph_ignores_arc_direction() {
+        let mut graph = DirectedRoadNetwork::new();
+        for (a, b) in [(1, 2), (3, 2), (3, 4), (4, 5)] {
+            graph.add_edge(a, b, RoadEdge::default());
+        }
+
+        let subgraph = bfs_subgraph(&graph, &NodeCoordinates::new(), 2, 2).unwrap();
+        assert_eq!(subgraph.original_ids, vec![1, 2, 3, 4]);
+        assert!(subgraph.network.contains_edge(2, 1) && !subgraph.network.contains_edge(1, 2));
+        assert!(bfs_subgraph(&graph, &NodeCoordinates::new(), 7, 1).is_none());
+    }
+}
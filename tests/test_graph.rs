@@ -0,0 +1,195 @@
+// test_graph.rs
+
+// Integration tests of the public API on test_data/test_graph.txt, the path 0 - 1 - 2 - 3
+
+use crnsa::data::{self, LoadOptions, NodeCoordinates, RoadGraph};
+use crnsa::preprocess::PreprocessPolicy;
+use crnsa::progress::NoProgress;
+use crnsa::report::{GraphSummary, RankingSection, Report, StatsSection};
+use crnsa::{assort, bed, betweenness, cca, centrality, cpa, leiden, loader, network_analysis, pagerank, route, CsrGraph};
+use crnsa::{PipelineConfig, RoadEdge};
+use petgraph::graph::NodeIndex;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::{HashMap, HashSet};
+
+const TEST_GRAPH: &str = "test_data/test_graph.txt";
+
+// Load an edge list the way the binary does, skipping malformed lines and normalizing the edges
+fn load_graph(file_path: &str) -> RoadGraph {
+    let options = LoadOptions { lenient: true, preprocess: PreprocessPolicy::shortest(), ..LoadOptions::default() };
+    let (network, _) = data::load_edge_list(file_path, &options).expect("Unable to load the file");
+    data::build_road_graph(network, &NodeCoordinates::new()).0
+}
+
+// Dataset id of every node of a result keyed by node index
+fn ids<T>(graph: &RoadGraph, result: impl IntoIterator<Item = (NodeIndex, T)>) -> HashMap<usize, T> {
+    result.into_iter().map(|(node, value)| (graph[node].id, value)).collect()
+}
+
+#[test]
+fn test_load_graph_from_txt() {
+    let graph = load_graph(TEST_GRAPH);
+
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 3);
+    let mut node_ids: Vec<usize> = graph.node_weights().map(|node| node.id).collect();
+    node_ids.sort_unstable();
+    assert_eq!(node_ids, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_load_graph_from_txt_has_no_parallel_edges() {
+    // Reverse listings and repeated lines collapse into one edge, and the self-loop is dropped
+    let graph = load_graph("test_data/duplicate_edges.txt");
+
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 3);
+}
+
+#[test]
+fn test_loader_builds_road_graphs() {
+    let (network, coordinates, mut load_report) =
+        loader::parse_network::<petgraph::Undirected>(TEST_GRAPH, data::InputFormat::EdgeList, PreprocessPolicy::shortest(), &NoProgress)
+            .unwrap();
+    assert_eq!((network.node_count(), network.edge_count()), (4, 3));
+    assert!(coordinates.is_empty());
+    assert!(matches!(load_report.parse, Some(loader::ParseReport::EdgeList { skipped_lines: 0, .. })));
+
+    let (graph, directed_graph) = loader::build_road_graphs(network, None, coordinates, true, &mut load_report);
+    assert_eq!(graph.node_count(), 4);
+    assert!(directed_graph.is_none());
+    assert_eq!(load_report.component, Some(loader::ComponentRestriction { nodes: 4, total_nodes: 4 }));
+    assert!(load_report.coordinates.is_none());
+}
+
+#[test]
+fn test_loader_caches_only_when_asked() {
+    let options = loader::InputOptions::new(TEST_GRAPH);
+    assert_eq!(options.format, data::InputFormat::EdgeList);
+    let ((graph, _), load_report) = loader::load_road_graphs(&options, PreprocessPolicy::shortest(), &NoProgress).unwrap();
+    assert_eq!(graph.node_count(), 4);
+    assert!(load_report.cache.is_none());
+}
+
+#[test]
+fn test_degree_measures() {
+    let graph = load_graph(TEST_GRAPH);
+
+    let degrees = ids(&graph, centrality::degree_centrality(&graph));
+    assert_eq!(degrees, HashMap::from([(0, 1.0), (1, 2.0), (2, 2.0), (3, 1.0)]));
+    assert_eq!(network_analysis::degree_distribution(&graph).to_vec(), vec![0, 2, 2]);
+    assert_eq!(network_analysis::network_density(&graph), 0.5);
+    assert_eq!(network_analysis::clustering_coefficient(&graph), 0.0);
+    // On a path the ends attach to the middle, so high degrees meet low ones
+    assert!(assort::calculate_assortativity_coefficient(&graph) < 0.0);
+}
+
+#[test]
+fn test_betweenness_on_graph() {
+    let graph = load_graph(TEST_GRAPH);
+
+    // Each inner node of the path lies between two of the three pairs of other nodes
+    let exact = betweenness::betweenness_centrality(&graph, None, true);
+    assert_eq!(ids(&graph, exact.nodes), HashMap::from([(0, 0.0), (1, 2.0 / 3.0), (2, 2.0 / 3.0), (3, 0.0)]));
+
+    // Sampling every node as a pivot is exact as well
+    let pivots = betweenness::select_pivots(&graph, 10, &mut StdRng::seed_from_u64(7));
+    let sampled = betweenness::weighted_betweenness_centrality(&graph, |edge: &RoadEdge| edge.weight, Some(&pivots), true);
+    assert!(sampled.is_exact());
+    assert_eq!(sampled.edges.len(), 3);
+}
+
+#[test]
+fn test_pagerank_on_graph_and_csr() {
+    let graph = load_graph(TEST_GRAPH);
+    let ranks = ids(&graph, pagerank::pagerank(&graph, 0.85, 100));
+
+    assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    assert!(ranks[&1] > ranks[&0] && ranks[&2] > ranks[&3]);
+
+    // The CSR form keeps the node indices of the road graph, so its ranks translate the same way
+    let csr = CsrGraph::from_graph(&graph, |edge: &RoadEdge| edge.weight);
+    let csr_ranks = ids(&graph, pagerank::pagerank(&csr, 0.85, 100));
+    for (node, rank) in &ranks {
+        assert!((rank - csr_ranks[node]).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_structure_analyses() {
+    let graph = load_graph(TEST_GRAPH);
+    let id = |node: NodeIndex| graph[node].id;
+
+    // Every edge of a path is a bridge
+    let bridges: HashSet<(usize, usize)> =
+        bed::find_bridge_edges(&graph).into_iter().map(|(a, b)| (id(a).min(id(b)), id(a).max(id(b)))).collect();
+    assert_eq!(bridges, HashSet::from([(0, 1), (1, 2), (2, 3)]));
+
+    let (num_components, components) = cca::analyze_connected_components(&graph);
+    assert_eq!(num_components, 1);
+    assert_eq!(components[0].len(), 4);
+
+    let (core, periphery) = cpa::core_periphery_analysis(&graph, 2);
+    assert_eq!(core.into_iter().map(id).collect::<HashSet<_>>(), HashSet::from([1, 2]));
+    assert_eq!(periphery.into_iter().map(id).collect::<HashSet<_>>(), HashSet::from([0, 3]));
+
+    let communities = leiden::leiden_communities(&graph, |edge: &RoadEdge| edge.weight);
+    assert_eq!(communities.len(), 4);
+}
+
+#[test]
+fn test_route_between_ids() {
+    let graph = load_graph(TEST_GRAPH);
+    let options = route::RouteOptions { endpoints: Some((0, 3)), ..route::RouteOptions::default() };
+    let paths = route::find_routes(&graph, &options, &mut StdRng::seed_from_u64(42), &NoProgress).unwrap();
+
+    assert_eq!(paths[0].weight, 3.0);
+    assert_eq!(paths[0].path, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_pipeline_config_for_test_graph() {
+    let config = PipelineConfig::from_toml(
+        r#"
+        seed = 1
+        analyses = [{ analysis = "pagerank", iterations = 20 }, { analysis = "route" }]
+
+        [input]
+        path = "test_data/test_graph.txt"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.input.format, data::InputFormat::EdgeList);
+    assert_eq!(config.analyses.len(), 2);
+    assert_eq!(config.preprocess, PreprocessPolicy::shortest());
+}
+
+#[test]
+fn test_report_for_test_graph() {
+    let graph = load_graph(TEST_GRAPH);
+    let id = |node: NodeIndex| graph[node].id;
+    let mut report = Report::new(GraphSummary { nodes: graph.node_count(), edges: graph.edge_count(), arcs: None });
+
+    let stats = StatsSection::new(
+        data::network_properties(&graph),
+        network_analysis::degree_distribution(&graph).to_vec(),
+        network_analysis::clustering_coefficient(&graph),
+        network_analysis::network_density(&graph),
+        assort::calculate_assortativity_coefficient(&graph),
+    );
+    report.stats = Some(stats);
+    let ranks = pagerank::pagerank(&graph, 0.85, 100).into_iter().map(|(node, rank)| (id(node), rank));
+    report.pagerank = Some(RankingSection::top_k(ranks, 2));
+
+    // The middle of the path ranks highest
+    let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["stats"]["weakly_connected_components"], 1);
+    assert_eq!(json["pagerank"]["top"][0]["node"], 1);
+    assert_eq!(json["pagerank"]["top"][1]["node"], 2);
+
+    let markdown = report.to_markdown();
+    assert!(markdown.contains("**Network density: 0.5**"));
+    assert!(markdown.contains("| 2      | 2               |"));
+}